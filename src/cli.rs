@@ -11,6 +11,34 @@ each iteration for specification-driven development.")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Show more of Claude's output (-v: tool results and event details, -vv: untruncated)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Hide Claude's output and show only loop progress
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Cli {
+    /// Output verbosity: -1 for `--quiet`, otherwise the number of `-v` flags
+    pub fn verbosity(&self) -> i8 {
+        if self.quiet {
+            -1
+        } else {
+            self.verbose.min(2) as i8
+        }
+    }
+}
+
+/// The flag that reproduces `verbosity` (e.g. when re-running inside Docker)
+pub fn verbosity_flag(verbosity: i8) -> Option<String> {
+    match verbosity {
+        v if v < 0 => Some("--quiet".to_string()),
+        0 => None,
+        v => Some(format!("-{}", "v".repeat(v as usize))),
+    }
 }
 
 #[derive(Subcommand)]
@@ -27,6 +55,14 @@ pub enum Commands {
         /// Maximum iterations (0 = unlimited)
         #[arg(short, long, env = "FRESHER_MAX_ITERATIONS")]
         max_iterations: Option<u32>,
+
+        /// CI mode: no colour or TTY, require config, print a summary line
+        #[arg(long, env = "FRESHER_CI")]
+        ci: bool,
+
+        /// Remove an existing run lock before starting
+        #[arg(long)]
+        force_unlock: bool,
     },
 
     /// Run building mode - implement tasks from the plan
@@ -34,6 +70,49 @@ pub enum Commands {
         /// Maximum iterations (0 = unlimited)
         #[arg(short, long, env = "FRESHER_MAX_ITERATIONS")]
         max_iterations: Option<u32>,
+
+        /// CI mode: no colour or TTY, require config, print a summary line
+        #[arg(long, env = "FRESHER_CI")]
+        ci: bool,
+
+        /// Remove an existing run lock before starting
+        #[arg(long)]
+        force_unlock: bool,
+
+        /// Refuse to build when specs changed since the plan was made
+        #[arg(long)]
+        strict: bool,
+
+        /// Serve a local live dashboard at this address (e.g. 127.0.0.1:8080)
+        #[arg(long, value_name = "ADDR")]
+        serve: Option<String>,
+
+        /// Full-screen terminal UI (plain output when stdout isn't a terminal)
+        #[arg(long)]
+        tui: bool,
+    },
+
+    /// Watch specs and re-plan automatically when they change
+    Watch {
+        /// Maximum planning iterations per change
+        #[arg(short, long, default_value_t = 3)]
+        max_iterations: u32,
+
+        /// Also watch the source directory (paths.src_dir)
+        #[arg(long)]
+        src: bool,
+
+        /// Continue into building mode after each planning run
+        #[arg(long)]
+        build: bool,
+
+        /// Quiet period before reacting to a burst of changes
+        #[arg(long, default_value_t = 2000)]
+        debounce_ms: u64,
+
+        /// Use polling instead of native file notifications
+        #[arg(long)]
+        poll: bool,
     },
 
     /// Verify implementation plan against specs
@@ -45,6 +124,53 @@ pub enum Commands {
         /// Path to implementation plan file
         #[arg(short, long, default_value = "IMPLEMENTATION_PLAN.md")]
         plan_file: String,
+
+        /// Fail when specs changed since the plan was made
+        #[arg(long)]
+        strict: bool,
+
+        /// Fail when any spec's coverage is below this percentage
+        #[arg(long, value_name = "PCT")]
+        min_coverage: Option<f64>,
+
+        /// Fail when more than this many tasks have no spec refs
+        #[arg(long, value_name = "N")]
+        max_orphan_tasks: Option<usize>,
+
+        /// Fail when a task refers to a spec or requirement that doesn't exist
+        #[arg(long)]
+        no_dangling_refs: bool,
+
+        /// Fail when a task has no priority
+        #[arg(long)]
+        require_priority: bool,
+
+        /// Print every requirement ID with the tasks citing it
+        #[arg(long)]
+        matrix: bool,
+
+        /// Suggest requirement refs for tasks from text similarity, instead
+        /// of checking the plan
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "strict",
+                "min_coverage",
+                "max_orphan_tasks",
+                "no_dangling_refs",
+                "require_priority",
+                "matrix",
+            ]
+        )]
+        suggest_refs: bool,
+
+        /// Minimum confidence (0-1) for a suggested ref
+        #[arg(long, value_name = "SCORE", requires = "suggest_refs", default_value_t = crate::suggest::DEFAULT_MIN_CONFIDENCE)]
+        min_confidence: f64,
+
+        /// Write the suggested refs into the plan
+        #[arg(long, requires = "suggest_refs")]
+        apply: bool,
     },
 
     /// Self-upgrade to the latest version
@@ -84,3 +210,35 @@ pub enum DockerCommands {
     /// Build the devcontainer image
     Build,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_flags() {
+        let cli = Cli::try_parse_from(["fresher", "build", "-vv"]).unwrap();
+        assert_eq!(cli.verbosity(), 2);
+        assert_eq!(verbosity_flag(cli.verbosity()).as_deref(), Some("-vv"));
+
+        let cli = Cli::try_parse_from(["fresher", "-q", "plan"]).unwrap();
+        assert_eq!(cli.verbosity(), -1);
+        assert_eq!(verbosity_flag(cli.verbosity()).as_deref(), Some("--quiet"));
+
+        let cli = Cli::try_parse_from(["fresher", "plan"]).unwrap();
+        assert_eq!(verbosity_flag(cli.verbosity()), None);
+
+        assert!(Cli::try_parse_from(["fresher", "build", "-v", "--quiet"]).is_err());
+    }
+
+    #[test]
+    fn test_suggest_refs_skips_checks() {
+        assert!(Cli::try_parse_from(["fresher", "verify", "--suggest-refs", "--apply"]).is_ok());
+        for flag in ["--strict", "--no-dangling-refs", "--matrix"] {
+            assert!(Cli::try_parse_from(["fresher", "verify", "--suggest-refs", flag]).is_err());
+        }
+        assert!(
+            Cli::try_parse_from(["fresher", "verify", "--suggest-refs", "--max-orphan-tasks", "0"]).is_err()
+        );
+    }
+}
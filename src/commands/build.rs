@@ -4,17 +4,64 @@ use std::path::Path;
 use std::process::Stdio;
 use tokio::process::Command;
 use tokio::signal;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::{broadcast, mpsc};
 
-use crate::config::Config;
+use crate::cli::verbosity_flag;
+use crate::commands::iteration::{Iteration, Output};
+use crate::config::{Config, OutcomeAction};
+use crate::dashboard::{Dashboard, PlanProgress};
 use crate::docker;
+use crate::guardrails::Guardrails;
 use crate::hooks;
+use crate::impl_plan::ImplIndex;
+use crate::lock::RunLock;
+use crate::notify::{self, NotifyEvent};
+use crate::scope::{self, Scope};
+use crate::secrets::Scanner;
+use crate::spec_drift;
 use crate::state::{count_commits_since, get_current_sha, FinishType, State};
-use crate::streaming::{process_stream, StreamHandler};
+use crate::streaming::{
+    process_stream, subagents_summary, IterationOutcome, StreamHandler, SubagentRun, ToolStats,
+};
 use crate::templates;
+use crate::todos;
+use crate::tui::{self, Tui, TuiControls, TuiEvent};
 use crate::verify::has_pending_tasks_with_impl_dir;
 
+/// Print a line, or append it to the TUI log while the TUI owns the terminal
+macro_rules! say {
+    ($out:expr, $($arg:tt)*) => {
+        $out.say(format!($($arg)*))
+    };
+}
+
+/// `say!` for warnings and errors: the TUI log, or stderr
+macro_rules! say_err {
+    ($out:expr, $($arg:tt)*) => {
+        $out.warn(format!($($arg)*))
+    };
+}
+
 /// Run the build command - building mode loop
-pub async fn run(max_iterations: Option<u32>) -> Result<()> {
+///
+/// Returns how the loop finished; `main` maps it to the process exit code.
+pub async fn run(
+    max_iterations: Option<u32>,
+    ci: bool,
+    force_unlock: bool,
+    strict: bool,
+    serve: Option<String>,
+    tui: bool,
+    verbosity: i8,
+) -> Result<FinishType> {
+    if ci {
+        if tui {
+            bail!("--tui can't be used with --ci");
+        }
+        colored::control::set_override(false);
+    }
+
     // Check for .fresher directory
     if !Path::new(".fresher").exists() {
         bail!(
@@ -35,6 +82,11 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         );
     }
 
+    // CI runs must not silently fall back to default configuration
+    if ci && !Path::new(".fresher/config.toml").exists() {
+        bail!(".fresher/config.toml not found (required in CI mode)");
+    }
+
     // Load configuration
     let mut config = Config::load()?;
     config.fresher.mode = "building".to_string();
@@ -45,6 +97,23 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         docker_args.push("--max-iterations".to_string());
         docker_args.push(max.to_string());
     }
+    if ci {
+        docker_args.push("--ci".to_string());
+    }
+    if force_unlock {
+        docker_args.push("--force-unlock".to_string());
+    }
+    if strict {
+        docker_args.push("--strict".to_string());
+    }
+    if let Some(addr) = &serve {
+        docker_args.push("--serve".to_string());
+        docker_args.push(addr.clone());
+    }
+    if tui {
+        docker_args.push("--tui".to_string());
+    }
+    docker_args.extend(verbosity_flag(verbosity));
     match docker::run_in_container(&config, &docker_args)? {
         docker::PROCEED_NORMALLY => {} // Continue with normal execution
         code => std::process::exit(code), // Docker handled it, exit with its code
@@ -64,29 +133,115 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     }
 
     let project_dir = std::env::current_dir()?;
+    let scope = Scope::from_config(&config)?;
+    let guardrails = Guardrails::from_config(&config.guardrails)?;
+    let scanner = if config.secrets.enabled {
+        Some(Scanner::from_config(&config.secrets)?)
+    } else {
+        None
+    };
+
+    // Warn (or refuse with --strict) when specs changed after planning
+    if let Some(drift) = spec_drift::check(
+        &project_dir,
+        Path::new(&config.paths.spec_dir),
+        Path::new(&config.paths.impl_dir),
+    )? {
+        drift.print_warning();
+        if strict {
+            bail!("Specs changed since the plan was made (--strict)");
+        }
+        eprintln!();
+    }
+
+    // Hold the run lock for the whole loop. Taken after Docker dispatch so that
+    // only the process actually running the loop (host or container) holds it.
+    let mut run_lock = RunLock::acquire(&project_dir, &config.fresher.mode, force_unlock)?;
+    run_lock.start_heartbeat();
+
+    // Optional local dashboard
+    let impl_dir = Path::new(&config.paths.impl_dir);
+    let dashboard = match &serve {
+        Some(addr) => {
+            let dashboard =
+                Dashboard::serve(addr, &config.fresher.mode, config.fresher.max_iterations)
+                    .await?;
+            dashboard.set_plan(PlanProgress::load(impl_dir, plan_path));
+            Some(dashboard)
+        }
+        None => None,
+    };
 
     // Initialize state
     let mut state = State::new();
 
     println!("{}", "Starting Fresher (Building Mode)".bold().green());
     println!("{}", "─".repeat(40));
+    if let Some(dashboard) = &dashboard {
+        println!(
+            "  Dashboard: {}",
+            format!("http://{}/", dashboard.addr()).cyan()
+        );
+    }
     println!();
 
     // Run started hook
-    if !hooks::run_started_hook(&state, &config, &project_dir).await? {
-        return Ok(());
+    if !hooks::run_started_hook(&mut state, &config, &project_dir).await? {
+        state.set_finish(FinishType::Manual);
+        if ci {
+            println!("{}", state.summary_line(&config.fresher.mode));
+        }
+        return Ok(FinishType::Manual);
     }
 
-    // Set up Ctrl+C handler
     let should_stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-    let should_stop_clone = should_stop.clone();
 
+    // Optional full-screen UI; plain output when it can't be used
+    let pause = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let event_tx = dashboard
+        .as_ref()
+        .map(|d| d.event_sender())
+        .or_else(|| tui.then(|| broadcast::channel(256).0));
+    let tui = if tui && !tui::is_supported() {
+        eprintln!("{}", "stdout is not a terminal; --tui falls back to plain output".yellow());
+        None
+    } else if let (true, Some(event_tx)) = (tui, &event_tx) {
+        let controls = TuiControls {
+            stop: should_stop.clone(),
+            pause: pause.clone(),
+        };
+        let ui = Tui::start(
+            &config.fresher.mode,
+            config.fresher.max_iterations,
+            controls,
+            event_tx.subscribe(),
+        )?;
+        ui.send(TuiEvent::Plan(PlanProgress::load(impl_dir, plan_path)));
+        Some(ui)
+    } else {
+        None
+    };
+
+    // Set up Ctrl+C handler
+    let should_stop_clone = should_stop.clone();
+    let interrupt_tx = tui.as_ref().map(|t| t.sender());
     tokio::spawn(async move {
         signal::ctrl_c().await.ok();
         should_stop_clone.store(true, std::sync::atomic::Ordering::SeqCst);
-        println!("\n{}", "Received interrupt, finishing current iteration...".yellow());
+        let message = "Received interrupt, finishing current iteration...";
+        match interrupt_tx {
+            Some(tx) => {
+                let _ = tx.send(TuiEvent::Log(message.to_string()));
+            }
+            None => println!("\n{}", message.yellow()),
+        }
     });
 
+    let mut run_tools = ToolStats::default();
+    let mut run_subagents: Vec<SubagentRun> = Vec::new();
+
+    let out = Output(tui.as_ref());
+
     // Main loop
     loop {
         // Check for interrupt
@@ -98,31 +253,53 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         // Check max iterations
         if config.fresher.max_iterations > 0 && state.iteration >= config.fresher.max_iterations {
             state.set_finish(FinishType::MaxIterations);
-            println!("\n{}", "Max iterations reached".yellow());
+            say!(out, "\n{}", "Max iterations reached".yellow());
             break;
         }
 
         // Check if there are pending tasks (hierarchical or legacy)
-        let impl_dir = Path::new(&config.paths.impl_dir);
         if !has_pending_tasks_with_impl_dir(plan_path, impl_dir) {
             state.set_finish(FinishType::Complete);
-            println!("{}", "All tasks complete!".green());
+            say!(out, "{}", "All tasks complete!".green());
             break;
         }
 
         // Start new iteration
         let iteration_sha = get_current_sha();
         state.start_iteration(iteration_sha.clone());
+        let baseline = match scope.is_some() || config.guardrails.rollback {
+            true => scope::Baseline::take(&project_dir).unwrap_or_default(),
+            false => scope::Baseline::default(),
+        };
+        let focus = match config.todos.check_focus {
+            true => todos::focus_task(plan_path, impl_dir),
+            false => None,
+        };
+        if let Some(dashboard) = &dashboard {
+            dashboard.set_iteration(state.iteration);
+            dashboard.set_plan(PlanProgress::load(impl_dir, plan_path));
+        }
 
-        println!(
-            "{} {}",
-            format!("Iteration {}", state.iteration).bold().cyan(),
-            "─".repeat(30)
-        );
+        match &tui {
+            Some(tui) => {
+                let plan = PlanProgress::load(impl_dir, plan_path);
+                tui.send(TuiEvent::Iteration {
+                    iteration: state.iteration,
+                    focus: plan.current_focus.clone(),
+                });
+                tui.send(TuiEvent::Plan(plan));
+            }
+            None => println!(
+                "{} {}",
+                format!("Iteration {}", state.iteration).bold().cyan(),
+                "─".repeat(30)
+            ),
+        }
 
         // Run next_iteration hook
         let (should_continue, should_skip) =
-            hooks::run_next_iteration_hook(&state, &config, &project_dir).await?;
+            hooks::run_next_iteration_hook(&mut state, &config, &project_dir).await?;
+        out.hook("next_iteration", hook_outcome(should_continue, should_skip));
 
         if !should_continue {
             state.set_finish(FinishType::Manual);
@@ -130,58 +307,238 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         }
 
         if should_skip {
-            println!("{}", "Skipping iteration (hook requested)".yellow());
+            say!(out, "{}", "Skipping iteration (hook requested)".yellow());
             continue;
         }
 
         // Build claude command
-        let prompt = get_prompt(&config)?;
-        let result = run_claude_iteration(&prompt, &config).await?;
+        let base_prompt = get_prompt(&config)?;
+        let prompt = state.prompt_with_additions(&base_prompt);
+        let (should_continue, should_skip) =
+            hooks::run_pre_agent_hook(&mut state, &config, &project_dir, &prompt).await?;
+        out.hook("pre_agent", hook_outcome(should_continue, should_skip));
+
+        if !should_continue {
+            state.set_finish(FinishType::Manual);
+            break;
+        }
+
+        if should_skip {
+            say!(out, "{}", "Skipping iteration (hook requested)".yellow());
+            continue;
+        }
+
+        // Prompt additions and model override queued by hook responses
+        let (prompt, model) = state.take_hook_steering(&base_prompt, &config.fresher.model);
+        if model != config.fresher.model {
+            say!(out, "  {} {} (hook override)", "Model:".dimmed(), model.cyan());
+        }
+        let max_turns = state.max_turns_override.unwrap_or(config.fresher.max_turns);
+        if max_turns != config.fresher.max_turns {
+            say!(out, "  {} {} (retry)", "Max turns:".dimmed(), max_turns.to_string().cyan());
+        }
+
+        let index_before = ImplIndex::load(impl_dir).ok();
+        let stderr_tx = tui.as_ref().map(|t| t.sender());
+        let mut handler = StreamHandler::from_output(&config.output, verbosity)
+            .event_tx(event_tx.clone())
+            .partial(config.fresher.partial_messages)
+            .guardrails(guardrails.clone())
+            .scope(scope.clone())
+            .show_todos(config.todos.show);
+        if tui.is_some() {
+            // The TUI renders the stream itself; anything printed would
+            // draw over it
+            handler.show_text = false;
+            handler.show_tool_calls = false;
+            handler.show_tool_results = false;
+            handler.verbose = false;
+        }
+        let result =
+            run_claude_iteration(&prompt, &model, max_turns, &config, &handler, stderr_tx).await?;
 
         // Record iteration result
-        let commits_this_iteration = iteration_sha
+        let mut commits_this_iteration = iteration_sha
             .as_ref()
             .map(|sha| count_commits_since(sha))
             .unwrap_or(0);
 
+        let outcome = result.outcome();
+        let action = state.record_outcome(outcome, &config.outcomes, max_turns);
         state.complete_iteration(result.exit_code, commits_this_iteration);
+        state.record_usage(&result.usage, result.cost_usd);
         state.save()?;
 
+        // Tool and token usage for this iteration, and for the run summary
+        say!(out, "  {} {}", "Tools:".dimmed(), result.tool_stats.summary().dimmed());
+        if !result.subagents.is_empty() {
+            say!(out, "  {} {}", "Sub-agents:".dimmed(), subagents_summary(&result.subagents).dimmed());
+            for run in &result.subagents {
+                say!(out, "    {}{}", "  ".repeat(run.depth), run.summary().dimmed());
+            }
+        }
+        if result.usage.total() > 0 {
+            say!(out, "  {} {}", "Tokens:".dimmed(), result.usage.summary().dimmed());
+        }
+        if outcome != IterationOutcome::Success {
+            say!(out, "  {} {} ({})", "Outcome:".dimmed(), outcome.to_string().yellow(), action);
+        }
+        let log_path = project_dir.join(config.paths.iteration_log(state.iteration));
+        if let Err(e) = result.append_to_log(&log_path, state.iteration) {
+            say_err!(out, "Warning: could not write {}: {}", log_path.display(), e);
+        }
+        if !result.todos.is_empty() {
+            let focus_check = focus
+                .as_deref()
+                .map(|task| (task, todos::on_focus(&result.todos, task)));
+            say!(out, "  {} {}", "Todos:".dimmed(), todos::progress(&result.todos).dimmed());
+            if let Some((task, false)) = focus_check {
+                say!(out, "  {} todo list does not cover the focus task: {}", "⚠".yellow(), task);
+            }
+            if let Err(e) = todos::append_to_log(&log_path, &result.todos, focus_check) {
+                say_err!(out, "Warning: could not write {}: {}", log_path.display(), e);
+            }
+            state.todos = result.todos.clone();
+            state.todo_on_focus = focus_check.map(|(_, on_focus)| on_focus);
+        }
+        run_tools.merge(&result.tool_stats);
+        run_subagents.extend(result.subagents.iter().cloned());
+
+        // Guardrails, path scope and secrets
+        let iteration = Iteration {
+            project_dir: &project_dir,
+            sha: iteration_sha.as_deref(),
+            log_path: &log_path,
+            out,
+        };
+        if iteration.guardrail_violation(&result, &config, &baseline, &mut state)
+            || iteration.enforce_scope(scope.as_ref(), &baseline, &mut state)
+            || iteration.scan_secrets(
+                scanner.as_ref(),
+                config.secrets.action,
+                &mut state,
+                &mut commits_this_iteration,
+            )
+        {
+            break;
+        }
+
+        notify::send(&config, &state, NotifyEvent::Iteration).await;
+
         // Print iteration summary
         if commits_this_iteration > 0 {
-            println!(
+            say!(
+                out,
                 "  {} {}",
                 "Commits:".dimmed(),
                 commits_this_iteration.to_string().green()
             );
         }
 
-        // Check for errors
-        if result.exit_code != 0 {
-            state.set_finish(FinishType::Error);
-            eprintln!("\n{}", format!("Claude exited with code {}", result.exit_code).red());
+        let should_continue = hooks::run_post_iteration_hook(
+            &mut state,
+            &config,
+            &project_dir,
+            result.exit_code,
+            commits_this_iteration,
+        )
+        .await?;
+        out.hook("post_iteration", hook_outcome(should_continue, false));
+        if !should_continue {
+            state.set_finish(FinishType::Manual);
+            break;
+        }
+
+        // Task and feature completion hooks
+        if let (Some(before), Ok(after)) = (&index_before, ImplIndex::load(impl_dir)) {
+            let mut should_continue = true;
+            for change in after.progress_since(before) {
+                if change.tasks_completed > 0 {
+                    should_continue &= hooks::run_task_complete_hook(
+                        &mut state,
+                        &config,
+                        &project_dir,
+                        &change,
+                    )
+                    .await?;
+                    out.hook("on_task_complete", hook_outcome(should_continue, false));
+                }
+                if change.feature_completed {
+                    say!(out, "  {} {}", "Feature complete:".dimmed(), change.feature.green());
+                    should_continue &= hooks::run_feature_complete_hook(
+                        &mut state,
+                        &config,
+                        &project_dir,
+                        &change,
+                    )
+                    .await?;
+                    out.hook("on_feature_complete", hook_outcome(should_continue, false));
+                }
+            }
+            if !should_continue {
+                state.set_finish(FinishType::Manual);
+                break;
+            }
+        }
+
+        if iteration.check_error(&result, action, &config, &mut state).await?
+            || iteration.check_limits(outcome, action, &config, &mut state)
+        {
             break;
         }
 
-        // Smart termination: check for no changes
+        // Smart termination: check for no changes (retried and stalled
+        // iterations are handled by the outcome policy instead)
         if config.fresher.smart_termination {
             let current_sha = get_current_sha();
-            if current_sha == state.iteration_sha && commits_this_iteration == 0 {
+            if current_sha == state.iteration_sha
+                && commits_this_iteration == 0
+                && !matches!(action, OutcomeAction::Retry | OutcomeAction::Stall)
+            {
                 state.set_finish(FinishType::NoChanges);
-                println!("\n{}", "No changes made this iteration".yellow());
+                say!(out, "\n{}", "No changes made this iteration".yellow());
                 break;
             }
         }
 
-        println!();
+        // Pause after this iteration until resumed (or stopped) from the TUI
+        if let Some(tui) = &tui {
+            if pause.load(std::sync::atomic::Ordering::SeqCst) {
+                tui.send(TuiEvent::Paused(true));
+                while pause.load(std::sync::atomic::Ordering::SeqCst)
+                    && !should_stop.load(std::sync::atomic::Ordering::SeqCst)
+                {
+                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                }
+                tui.send(TuiEvent::Paused(false));
+            }
+        } else {
+            println!();
+        }
+    }
+
+    // Give the terminal back before the finished hook and summary print
+    if let Some(tui) = tui {
+        tui.shutdown().await;
     }
 
     // Finalize
     state.update_duration();
     state.save()?;
 
+    if let Some(dashboard) = &dashboard {
+        dashboard.set_plan(PlanProgress::load(impl_dir, plan_path));
+        if let Some(finish) = &state.finish_type {
+            dashboard.set_finished(&finish.to_string());
+        }
+    }
+
+    if let Some(finish) = state.finish_type {
+        notify::send(&config, &state, NotifyEvent::Finished(finish)).await;
+    }
+
     // Run finished hook
-    hooks::run_finished_hook(&state, &config, &project_dir).await?;
+    hooks::run_finished_hook(&mut state, &config, &project_dir).await?;
 
     // Print summary
     println!();
@@ -189,12 +546,30 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     println!("{}", "─".repeat(40));
     println!("  Iterations: {}", state.iteration.to_string().cyan());
     println!("  Commits:    {}", state.total_commits.to_string().cyan());
+    println!("  Tools:      {}", run_tools.summary().cyan());
+    if !run_subagents.is_empty() {
+        println!("  Sub-agents: {}", subagents_summary(&run_subagents).cyan());
+    }
+    println!("  Tokens:     {}", state.usage.summary().cyan());
+    println!("  Cost:       ${:.4}", state.cost_usd);
     println!("  Duration:   {}s", state.duration.to_string().cyan());
     if let Some(finish) = &state.finish_type {
         println!("  Finished:   {}", finish.to_string().yellow());
     }
+    if ci {
+        println!("{}", state.summary_line(&config.fresher.mode));
+    }
 
-    Ok(())
+    Ok(state.finish_type.unwrap_or(FinishType::Complete))
+}
+
+/// Name a hook result the way the TUI shows it
+fn hook_outcome(should_continue: bool, should_skip: bool) -> &'static str {
+    match (should_continue, should_skip) {
+        (false, _) => "abort",
+        (true, true) => "skip",
+        (true, false) => "continue",
+    }
 }
 
 /// Get the prompt for building mode
@@ -212,7 +587,11 @@ fn get_prompt(_config: &Config) -> Result<String> {
 /// Run a single Claude iteration
 async fn run_claude_iteration(
     prompt: &str,
+    model: &str,
+    max_turns: u32,
     config: &Config,
+    handler: &StreamHandler,
+    stderr_tx: Option<mpsc::UnboundedSender<TuiEvent>>,
 ) -> Result<crate::streaming::ProcessResult> {
     let mut cmd = Command::new("claude");
 
@@ -231,23 +610,60 @@ async fn run_claude_iteration(
     }
 
     cmd.arg("--output-format").arg("stream-json");
-    cmd.arg("--max-turns").arg(config.fresher.max_turns.to_string());
+    cmd.arg("--max-turns").arg(max_turns.to_string());
     cmd.arg("--no-session-persistence"); // Critical: fresh context
-    cmd.arg("--model").arg(&config.fresher.model);
+    cmd.arg("--model").arg(model);
     cmd.arg("--verbose");
+    if config.fresher.partial_messages {
+        cmd.arg("--include-partial-messages");
+    }
 
     // Set up stdio
     cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::inherit());
+    if stderr_tx.is_some() {
+        cmd.stderr(Stdio::piped());
+    } else {
+        cmd.stderr(Stdio::inherit());
+    }
+
+    // Own process group, so stopping the agent also stops the tools it
+    // started. The agent is then off the terminal, so it must not read stdin.
+    cmd.stdin(Stdio::null());
+    #[cfg(unix)]
+    cmd.process_group(0);
 
     // Spawn process
     let mut child = cmd.spawn()?;
 
+    // With the TUI active, show Claude's stderr in its log instead
+    if let (Some(stderr), Some(tx)) = (child.stderr.take(), stderr_tx.clone()) {
+        tokio::spawn(async move {
+            let mut lines = tokio::io::BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = tx.send(TuiEvent::Log(line));
+            }
+        });
+    }
+
     // Process stdout stream
     let stdout = child.stdout.take().expect("Failed to capture stdout");
-    let handler = StreamHandler::new();
+    let stream = process_stream(tokio::io::BufReader::new(stdout), handler);
+    tokio::pin!(stream);
+    let result = loop {
+        tokio::select! {
+            result = &mut stream => break result?,
+            // The terminal's Ctrl+C no longer reaches the agent's group
+            _ = signal::ctrl_c() => {
+                #[cfg(unix)]
+                hooks::signal_process_group(&child, libc::SIGINT);
+            }
+        }
+    };
 
-    let result = process_stream(tokio::io::BufReader::new(stdout), &handler).await?;
+    // A guardrail tripped: don't let the agent (or anything it started) carry on
+    if result.violation.is_some() {
+        hooks::kill_process_group(&mut child).await;
+    }
 
     // Wait for process to complete
     let status = child.wait().await?;
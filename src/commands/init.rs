@@ -36,8 +36,10 @@ pub async fn run(force: bool) -> Result<()> {
     let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
 
     // Create config.toml
-    let mut config = Config::default();
-    config.commands = commands.clone();
+    let config = Config {
+        commands: commands.clone(),
+        ..Config::default()
+    };
     let config_content = templates::CONFIG_TEMPLATE
         .replace("{timestamp}", &timestamp)
         .replace("{project_type}", project_type.name())
@@ -0,0 +1,242 @@
+//! Policy steps shared by the plan and build loops
+//!
+//! Once the agent has finished an iteration, both loops check for guardrail
+//! violations, out-of-scope changes and secrets, then for errors, budgets and
+//! the outcome policy. Each step reports what it found and, when the loop has
+//! to stop, records the finish type and returns `true`.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+use crate::config::{Config, OutcomeAction, ScopeAction, SecretsAction};
+use crate::guardrails;
+use crate::hooks;
+use crate::scope::{self, Scope};
+use crate::secrets::{self, Scanner};
+use crate::state::{count_commits_since, get_current_sha, FinishType, State};
+use crate::streaming::{IterationOutcome, ProcessResult};
+use crate::tui::{Tui, TuiEvent};
+
+/// Where loop output goes: the TUI log while it runs, otherwise the terminal
+#[derive(Clone, Copy)]
+pub struct Output<'a>(pub Option<&'a Tui>);
+
+impl Output<'_> {
+    pub fn say(&self, line: String) {
+        match self.0 {
+            Some(tui) => tui.log(line),
+            None => println!("{}", line),
+        }
+    }
+
+    /// `say` for warnings and errors, which go to stderr
+    pub fn warn(&self, line: String) {
+        match self.0 {
+            Some(tui) => tui.log(line),
+            None => eprintln!("{}", line),
+        }
+    }
+
+    /// Show a hook result in the TUI, if it is running
+    pub fn hook(&self, hook: &str, outcome: &str) {
+        if let Some(tui) = self.0 {
+            tui.send(TuiEvent::Hook(hook.to_string(), outcome.to_string()));
+            // The hook may have written to the terminal
+            tui.send(TuiEvent::Redraw);
+        }
+    }
+}
+
+/// The iteration that just ran
+pub struct Iteration<'a> {
+    pub project_dir: &'a Path,
+    /// Commit the iteration started from
+    pub sha: Option<&'a str>,
+    pub log_path: &'a Path,
+    pub out: Output<'a>,
+}
+
+impl Iteration<'_> {
+    fn log_warning(&self, e: anyhow::Error) {
+        self.out.warn(format!("Warning: could not write {}: {}", self.log_path.display(), e));
+    }
+
+    /// A guardrail tripped during the iteration; rolls it back if configured
+    pub fn guardrail_violation(
+        &self,
+        result: &ProcessResult,
+        config: &Config,
+        baseline: &scope::Baseline,
+        state: &mut State,
+    ) -> bool {
+        let Some(violation) = &result.violation else {
+            return false;
+        };
+        self.out.warn(format!("\n{} {}", "Guardrail violation:".red().bold(), violation));
+        if config.guardrails.rollback {
+            let report = guardrails::roll_back_iteration(self.project_dir, self.sha, baseline);
+            self.out.say(report.yellow().to_string());
+        }
+        state.set_finish(FinishType::PolicyViolation);
+        true
+    }
+
+    /// Files changed outside the allowed paths
+    pub fn enforce_scope(
+        &self,
+        scope: Option<&Scope>,
+        baseline: &scope::Baseline,
+        state: &mut State,
+    ) -> bool {
+        let (Some(scope), Some(sha)) = (scope, self.sha) else {
+            return false;
+        };
+        match scope.enforce(self.project_dir, sha, baseline) {
+            Ok(violations) if !violations.is_empty() => {
+                self.out.say(format!(
+                    "  {} {} ({})",
+                    "Out of scope:".red(),
+                    violations.join(", "),
+                    scope.action
+                ));
+                if let Err(e) = scope::append_to_log(self.log_path, scope.action, &violations) {
+                    self.log_warning(e);
+                }
+                state.scope_violations = violations;
+                if scope.action == ScopeAction::Stop {
+                    state.set_finish(FinishType::PolicyViolation);
+                    return true;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => self.out.warn(format!("{} scope check failed: {}", "Warning:".yellow(), e)),
+        }
+        false
+    }
+
+    /// Possible secrets in the iteration's commits; only locations are
+    /// printed, the (redacted) findings go to the iteration log
+    ///
+    /// A rewrite squashes the iteration's commits, so `commits` and the
+    /// run's commit count and last commit are brought up to date.
+    pub fn scan_secrets(
+        &self,
+        scanner: Option<&Scanner>,
+        action: SecretsAction,
+        state: &mut State,
+        commits: &mut u32,
+    ) -> bool {
+        let (Some(scanner), Some(sha)) = (scanner, self.sha) else {
+            return false;
+        };
+        let findings = match scanner.scan_commits(self.project_dir, sha) {
+            Ok(findings) if !findings.is_empty() => findings,
+            Ok(_) => return false,
+            Err(e) => {
+                self.out.warn(format!("{} secret scan failed: {}", "Warning:".yellow(), e));
+                return false;
+            }
+        };
+
+        let locations: Vec<String> =
+            findings.iter().map(|f| format!("{}:{}", f.file, f.line)).collect();
+        self.out.say(format!(
+            "  {} {} in {} ({})",
+            "Possible secrets:".red(),
+            findings.len(),
+            locations.join(", "),
+            action
+        ));
+        if let Err(e) = secrets::append_to_log(self.log_path, &findings) {
+            self.log_warning(e);
+        }
+        state.secret_findings = findings
+            .iter()
+            .map(|f| format!("{}:{} {}", f.file, f.line, f.rule))
+            .collect();
+
+        match action {
+            SecretsAction::Warn => false,
+            SecretsAction::Block => {
+                state.set_finish(FinishType::PolicyViolation);
+                true
+            }
+            SecretsAction::Rewrite => {
+                if let Err(e) = secrets::rewrite_commits(self.project_dir, sha, &findings) {
+                    self.out.warn(format!("{} secret rewrite failed: {}", "Error:".red(), e));
+                    state.set_finish(FinishType::PolicyViolation);
+                    return true;
+                }
+                let message = "Rewrote iteration commits with secrets redacted";
+                self.out.say(format!("  {}", message.yellow()));
+                let squashed = count_commits_since(sha);
+                state.total_commits = state.total_commits - *commits + squashed;
+                state.last_commit_sha = get_current_sha();
+                *commits = squashed;
+                false
+            }
+        }
+    }
+
+    /// A non-zero exit, unless the outcome policy says otherwise, or an
+    /// outcome the policy treats as an error; the `on_error` hook may ignore it
+    pub async fn check_error(
+        &self,
+        result: &ProcessResult,
+        action: OutcomeAction,
+        config: &Config,
+        state: &mut State,
+    ) -> Result<bool> {
+        if action != OutcomeAction::Error
+            && (result.exit_code == 0 || action != OutcomeAction::Continue)
+        {
+            return Ok(false);
+        }
+        self.out.warn(format!("\n{}", result.error_message().red()));
+
+        let ignore_error =
+            hooks::run_on_error_hook(state, config, self.project_dir, result.exit_code).await?;
+        self.out.hook("on_error", if ignore_error { "skip" } else { "continue" });
+        if ignore_error {
+            return Ok(false);
+        }
+        state.set_finish(FinishType::Error);
+        Ok(true)
+    }
+
+    /// A used-up run budget, then the outcome policy: stop now, or once
+    /// enough iterations stalled
+    pub fn check_limits(
+        &self,
+        outcome: IterationOutcome,
+        action: OutcomeAction,
+        config: &Config,
+        state: &mut State,
+    ) -> bool {
+        if let Some(reason) = state.exceeded_budget(&config.budget) {
+            state.set_finish(FinishType::Budget);
+            self.out.say(format!("\n{} {}", "Budget exhausted:".yellow(), reason));
+            return true;
+        }
+
+        match action {
+            OutcomeAction::Stop => {
+                state.set_finish(FinishType::OutcomeStop);
+                let message = format!("Stopping after {} outcome", outcome);
+                self.out.say(format!("\n{}", message.yellow()));
+                true
+            }
+            OutcomeAction::Stall if state.is_stalled(&config.outcomes) => {
+                state.set_finish(FinishType::Stalled);
+                let message = format!(
+                    "Stalled after {} iterations without progress",
+                    state.stalled_iterations
+                );
+                self.out.say(format!("\n{}", message.yellow()));
+                true
+            }
+            _ => false,
+        }
+    }
+}
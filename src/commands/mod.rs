@@ -1,8 +1,10 @@
 pub mod build;
 pub mod docker;
 pub mod init;
+mod iteration;
 pub mod migrate;
 pub mod plan;
 pub mod upgrade;
 pub mod verify;
 pub mod version;
+pub mod watch;
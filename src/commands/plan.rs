@@ -5,16 +5,38 @@ use std::process::Stdio;
 use tokio::process::Command;
 use tokio::signal;
 
-use crate::config::Config;
+use crate::cli::verbosity_flag;
+use crate::commands::iteration::{Iteration, Output};
+use crate::config::{Config, OutcomeAction};
 use crate::docker;
+use crate::guardrails::Guardrails;
 use crate::hooks;
+use crate::lock::RunLock;
+use crate::notify::{self, NotifyEvent};
+use crate::scope::{self, Scope};
+use crate::secrets::Scanner;
+use crate::spec_drift;
 use crate::state::{count_commits_since, get_current_sha, FinishType, State};
-use crate::streaming::{process_stream, StreamHandler};
+use crate::streaming::{
+    process_stream, subagents_summary, IterationOutcome, StreamHandler, SubagentRun, ToolStats,
+};
 use crate::templates;
+use crate::todos;
 use crate::verify::has_pending_tasks_with_impl_dir;
 
 /// Run the plan command - planning mode loop
-pub async fn run(max_iterations: Option<u32>) -> Result<()> {
+///
+/// Returns how the loop finished; `main` maps it to the process exit code.
+pub async fn run(
+    max_iterations: Option<u32>,
+    ci: bool,
+    force_unlock: bool,
+    verbosity: i8,
+) -> Result<FinishType> {
+    if ci {
+        colored::control::set_override(false);
+    }
+
     // Check for .fresher directory
     if !Path::new(".fresher").exists() {
         bail!(
@@ -23,6 +45,11 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         );
     }
 
+    // CI runs must not silently fall back to default configuration
+    if ci && !Path::new(".fresher/config.toml").exists() {
+        bail!(".fresher/config.toml not found (required in CI mode)");
+    }
+
     // Load configuration
     let mut config = Config::load()?;
     config.fresher.mode = "planning".to_string();
@@ -33,6 +60,13 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         docker_args.push("--max-iterations".to_string());
         docker_args.push(max.to_string());
     }
+    if ci {
+        docker_args.push("--ci".to_string());
+    }
+    if force_unlock {
+        docker_args.push("--force-unlock".to_string());
+    }
+    docker_args.extend(verbosity_flag(verbosity));
     match docker::run_in_container(&config, &docker_args)? {
         docker::PROCEED_NORMALLY => {} // Continue with normal execution
         code => std::process::exit(code), // Docker handled it, exit with its code
@@ -52,6 +86,18 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     }
 
     let project_dir = std::env::current_dir()?;
+    let scope = Scope::from_config(&config)?;
+    let guardrails = Guardrails::from_config(&config.guardrails)?;
+    let scanner = if config.secrets.enabled {
+        Some(Scanner::from_config(&config.secrets)?)
+    } else {
+        None
+    };
+
+    // Hold the run lock for the whole loop. Taken after Docker dispatch so that
+    // only the process actually running the loop (host or container) holds it.
+    let mut run_lock = RunLock::acquire(&project_dir, &config.fresher.mode, force_unlock)?;
+    run_lock.start_heartbeat();
 
     // Initialize state
     let mut state = State::new();
@@ -61,8 +107,12 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     println!();
 
     // Run started hook
-    if !hooks::run_started_hook(&state, &config, &project_dir).await? {
-        return Ok(());
+    if !hooks::run_started_hook(&mut state, &config, &project_dir).await? {
+        state.set_finish(FinishType::Manual);
+        if ci {
+            println!("{}", state.summary_line(&config.fresher.mode));
+        }
+        return Ok(FinishType::Manual);
     }
 
     // Set up Ctrl+C handler
@@ -75,6 +125,9 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         println!("\n{}", "Received interrupt, finishing current iteration...".yellow());
     });
 
+    let mut run_tools = ToolStats::default();
+    let mut run_subagents: Vec<SubagentRun> = Vec::new();
+
     // Main loop
     loop {
         // Check for interrupt
@@ -93,6 +146,10 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         // Start new iteration
         let iteration_sha = get_current_sha();
         state.start_iteration(iteration_sha.clone());
+        let baseline = match scope.is_some() || config.guardrails.rollback {
+            true => scope::Baseline::take(&project_dir).unwrap_or_default(),
+            false => scope::Baseline::default(),
+        };
 
         println!(
             "{} {}",
@@ -102,7 +159,7 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
 
         // Run next_iteration hook
         let (should_continue, should_skip) =
-            hooks::run_next_iteration_hook(&state, &config, &project_dir).await?;
+            hooks::run_next_iteration_hook(&mut state, &config, &project_dir).await?;
 
         if !should_continue {
             state.set_finish(FinishType::Manual);
@@ -115,22 +172,116 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
         }
 
         // Build claude command
-        let prompt = get_prompt(&config)?;
-        let result = run_claude_iteration(&prompt, &config).await?;
+        let base_prompt = get_prompt(&config)?;
+        let prompt = state.prompt_with_additions(&base_prompt);
+        let (should_continue, should_skip) =
+            hooks::run_pre_agent_hook(&mut state, &config, &project_dir, &prompt).await?;
+
+        if !should_continue {
+            state.set_finish(FinishType::Manual);
+            break;
+        }
+
+        if should_skip {
+            println!("{}", "Skipping iteration (hook requested)".yellow());
+            continue;
+        }
+
+        // Prompt additions and model override queued by hook responses
+        let (prompt, model) = state.take_hook_steering(&base_prompt, &config.fresher.model);
+        if model != config.fresher.model {
+            println!("  {} {} (hook override)", "Model:".dimmed(), model.cyan());
+        }
+        let max_turns = state.max_turns_override.unwrap_or(config.fresher.max_turns);
+        if max_turns != config.fresher.max_turns {
+            println!("  {} {} (retry)", "Max turns:".dimmed(), max_turns.to_string().cyan());
+        }
+
+        let handler = StreamHandler::from_output(&config.output, verbosity)
+            .partial(config.fresher.partial_messages)
+            .guardrails(guardrails.clone())
+            .scope(scope.clone())
+            .show_todos(config.todos.show);
+        let result = run_claude_iteration(&prompt, &model, max_turns, &config, &handler).await?;
 
         // Record iteration result
-        let commits_this_iteration = iteration_sha
+        let mut commits_this_iteration = iteration_sha
             .as_ref()
             .map(|sha| count_commits_since(sha))
             .unwrap_or(0);
 
+        let outcome = result.outcome();
+        let action = state.record_outcome(outcome, &config.outcomes, max_turns);
         state.complete_iteration(result.exit_code, commits_this_iteration);
+        state.record_usage(&result.usage, result.cost_usd);
         state.save()?;
 
-        // Check for errors
-        if result.exit_code != 0 {
-            state.set_finish(FinishType::Error);
-            eprintln!("\n{}", format!("Claude exited with code {}", result.exit_code).red());
+        // Tool and token usage for this iteration, and for the run summary
+        println!("  {} {}", "Tools:".dimmed(), result.tool_stats.summary().dimmed());
+        if !result.subagents.is_empty() {
+            println!("  {} {}", "Sub-agents:".dimmed(), subagents_summary(&result.subagents).dimmed());
+            for run in &result.subagents {
+                println!("    {}{}", "  ".repeat(run.depth), run.summary().dimmed());
+            }
+        }
+        if result.usage.total() > 0 {
+            println!("  {} {}", "Tokens:".dimmed(), result.usage.summary().dimmed());
+        }
+        if outcome != IterationOutcome::Success {
+            println!("  {} {} ({})", "Outcome:".dimmed(), outcome.to_string().yellow(), action);
+        }
+        let log_path = project_dir.join(config.paths.iteration_log(state.iteration));
+        if let Err(e) = result.append_to_log(&log_path, state.iteration) {
+            eprintln!("Warning: could not write {}: {}", log_path.display(), e);
+        }
+        // The plan is being written, so there is no focus task to compare with
+        if !result.todos.is_empty() {
+            println!("  {} {}", "Todos:".dimmed(), todos::progress(&result.todos).dimmed());
+            if let Err(e) = todos::append_to_log(&log_path, &result.todos, None) {
+                eprintln!("Warning: could not write {}: {}", log_path.display(), e);
+            }
+            state.todos = result.todos.clone();
+        }
+        run_tools.merge(&result.tool_stats);
+        run_subagents.extend(result.subagents.iter().cloned());
+
+        // Guardrails, path scope and secrets
+        let iteration = Iteration {
+            project_dir: &project_dir,
+            sha: iteration_sha.as_deref(),
+            log_path: &log_path,
+            out: Output(None),
+        };
+        if iteration.guardrail_violation(&result, &config, &baseline, &mut state)
+            || iteration.enforce_scope(scope.as_ref(), &baseline, &mut state)
+            || iteration.scan_secrets(
+                scanner.as_ref(),
+                config.secrets.action,
+                &mut state,
+                &mut commits_this_iteration,
+            )
+        {
+            break;
+        }
+
+        notify::send(&config, &state, NotifyEvent::Iteration).await;
+
+        if !hooks::run_post_iteration_hook(
+            &mut state,
+            &config,
+            &project_dir,
+            result.exit_code,
+            commits_this_iteration,
+        )
+        .await?
+        {
+            state.set_finish(FinishType::Manual);
+            break;
+        }
+
+        if iteration.check_error(&result, action, &config, &mut state).await?
+            || iteration.check_limits(outcome, action, &config, &mut state)
+        {
             break;
         }
 
@@ -149,9 +300,13 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
                 break;
             }
 
-            // Check if no changes were made
+            // Check if no changes were made (retried and stalled iterations
+            // are handled by the outcome policy instead)
             let current_sha = get_current_sha();
-            if current_sha == state.iteration_sha && commits_this_iteration == 0 {
+            if current_sha == state.iteration_sha
+                && commits_this_iteration == 0
+                && !matches!(action, OutcomeAction::Retry | OutcomeAction::Stall)
+            {
                 state.set_finish(FinishType::NoChanges);
                 println!("\n{}", "No changes made this iteration".yellow());
                 break;
@@ -165,8 +320,24 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     state.update_duration();
     state.save()?;
 
+    // Remember which spec contents this plan was made from, so verify/build can
+    // detect specs edited afterwards. Only a plan that planning finished
+    // counts; a failure here must not skip the notification and hook.
+    if matches!(
+        state.finish_type,
+        Some(FinishType::Complete | FinishType::NoChanges | FinishType::MaxIterations)
+    ) {
+        if let Err(e) = spec_drift::record(&project_dir, Path::new(&config.paths.spec_dir)) {
+            eprintln!("{} could not record spec hashes: {}", "Warning:".yellow(), e);
+        }
+    }
+
+    if let Some(finish) = state.finish_type {
+        notify::send(&config, &state, NotifyEvent::Finished(finish)).await;
+    }
+
     // Run finished hook
-    hooks::run_finished_hook(&state, &config, &project_dir).await?;
+    hooks::run_finished_hook(&mut state, &config, &project_dir).await?;
 
     // Print summary
     println!();
@@ -174,12 +345,21 @@ pub async fn run(max_iterations: Option<u32>) -> Result<()> {
     println!("{}", "─".repeat(40));
     println!("  Iterations: {}", state.iteration.to_string().cyan());
     println!("  Commits:    {}", state.total_commits.to_string().cyan());
+    println!("  Tools:      {}", run_tools.summary().cyan());
+    if !run_subagents.is_empty() {
+        println!("  Sub-agents: {}", subagents_summary(&run_subagents).cyan());
+    }
+    println!("  Tokens:     {}", state.usage.summary().cyan());
+    println!("  Cost:       ${:.4}", state.cost_usd);
     println!("  Duration:   {}s", state.duration.to_string().cyan());
     if let Some(finish) = &state.finish_type {
         println!("  Finished:   {}", finish.to_string().yellow());
     }
+    if ci {
+        println!("{}", state.summary_line(&config.fresher.mode));
+    }
 
-    Ok(())
+    Ok(state.finish_type.unwrap_or(FinishType::Complete))
 }
 
 /// Get the prompt for planning mode
@@ -197,7 +377,10 @@ fn get_prompt(_config: &Config) -> Result<String> {
 /// Run a single Claude iteration
 async fn run_claude_iteration(
     prompt: &str,
+    model: &str,
+    max_turns: u32,
     config: &Config,
+    handler: &StreamHandler,
 ) -> Result<crate::streaming::ProcessResult> {
     let mut cmd = Command::new("claude");
 
@@ -216,23 +399,46 @@ async fn run_claude_iteration(
     }
 
     cmd.arg("--output-format").arg("stream-json");
-    cmd.arg("--max-turns").arg(config.fresher.max_turns.to_string());
+    cmd.arg("--max-turns").arg(max_turns.to_string());
     cmd.arg("--no-session-persistence"); // Critical: fresh context
-    cmd.arg("--model").arg(&config.fresher.model);
+    cmd.arg("--model").arg(model);
     cmd.arg("--verbose");
+    if config.fresher.partial_messages {
+        cmd.arg("--include-partial-messages");
+    }
 
     // Set up stdio
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::inherit());
 
+    // Own process group, so stopping the agent also stops the tools it
+    // started. The agent is then off the terminal, so it must not read stdin.
+    cmd.stdin(Stdio::null());
+    #[cfg(unix)]
+    cmd.process_group(0);
+
     // Spawn process
     let mut child = cmd.spawn()?;
 
     // Process stdout stream
     let stdout = child.stdout.take().expect("Failed to capture stdout");
-    let handler = StreamHandler::new();
+    let stream = process_stream(tokio::io::BufReader::new(stdout), handler);
+    tokio::pin!(stream);
+    let result = loop {
+        tokio::select! {
+            result = &mut stream => break result?,
+            // The terminal's Ctrl+C no longer reaches the agent's group
+            _ = signal::ctrl_c() => {
+                #[cfg(unix)]
+                hooks::signal_process_group(&child, libc::SIGINT);
+            }
+        }
+    };
 
-    let result = process_stream(tokio::io::BufReader::new(stdout), &handler).await?;
+    // A guardrail tripped: don't let the agent (or anything it started) carry on
+    if result.violation.is_some() {
+        hooks::kill_process_group(&mut child).await;
+    }
 
     // Wait for process to complete
     let status = child.wait().await?;
@@ -1,53 +1,298 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use colored::*;
+use std::collections::BTreeSet;
 use std::path::Path;
 
-use crate::config::Config;
+use crate::config::{Config, VerifyConfig};
 use crate::impl_plan::{has_hierarchical_plan, ImplIndex};
-use crate::verify::{generate_report, TaskStatus, VerifyReport};
+use crate::spec_drift::{self, SpecDrift};
+use crate::suggest::{self, Suggestions};
+use crate::verify::{
+    check_thresholds, extract_requirements, generate_hierarchical_report, generate_report,
+    hierarchical_tasks, parse_plan, TaskStatus, VerifyReport,
+};
 
-/// Run the verify command
-pub async fn run(json_output: bool, plan_file: String) -> Result<()> {
-    let config = Config::load().unwrap_or_default();
+/// Exit code when the plan breaks a `[verify]` threshold
+pub const THRESHOLD_EXIT_CODE: i32 = 2;
+
+/// Options for `verify --suggest-refs`
+#[derive(Debug, Clone, Copy)]
+pub struct SuggestOptions {
+    pub min_confidence: f64,
+    /// Write the suggested refs into the plan
+    pub apply: bool,
+}
+
+/// Run the verify command; returns false when a threshold was broken
+///
+/// `thresholds` from the command line override those in `[verify]`. With
+/// `matrix`, every requirement is listed with the tasks citing it. With
+/// `suggest`, only the suggested refs are reported.
+pub async fn run(
+    json_output: bool,
+    plan_file: String,
+    strict: bool,
+    thresholds: VerifyConfig,
+    matrix: bool,
+    suggest: Option<SuggestOptions>,
+) -> Result<bool> {
+    let config = Config::load()?;
     let plan_path = Path::new(&plan_file);
     let spec_dir = Path::new(&config.paths.spec_dir);
     let impl_dir = Path::new(&config.paths.impl_dir);
+    let mut merged = config.verify.clone();
+    merged.merge(&thresholds);
+    let thresholds = merged;
+
+    if let Some(options) = suggest {
+        run_suggest(json_output, plan_path, spec_dir, impl_dir, options)?;
+        return Ok(true);
+    }
 
     // Check for hierarchical plan first
     if has_hierarchical_plan(impl_dir) {
-        return run_hierarchical(json_output, impl_dir, spec_dir).await;
+        let drift = spec_drift::check(Path::new("."), spec_dir, impl_dir)?;
+        let passed =
+            run_hierarchical(json_output, impl_dir, spec_dir, drift.as_ref(), &thresholds, matrix)
+                .await?;
+        check_strict(drift.as_ref(), strict)?;
+        return Ok(passed);
     }
 
-    // Fall back to legacy single-file verification
+    // Fall back to legacy single-file verification. Without a plan there is
+    // nothing to verify, which only fails when thresholds are set.
     if !plan_path.exists() {
+        let violations: Vec<String> = match thresholds.is_empty() {
+            true => Vec::new(),
+            false => vec![format!("Plan file not found: {}", plan_file)],
+        };
         if json_output {
             let empty = serde_json::json!({
                 "error": "Plan file not found",
-                "path": plan_file
+                "path": plan_file,
+                "thresholds": thresholds_json(&thresholds, &violations),
             });
             println!("{}", serde_json::to_string_pretty(&empty)?);
         } else {
             eprintln!("Plan file not found: {}", plan_file);
             eprintln!();
             eprintln!("Run {} first to create an implementation plan.", "fresher plan".cyan());
+            print_violations(&thresholds, &violations);
         }
-        return Ok(());
+        return Ok(violations.is_empty());
     }
 
     let report = generate_report(plan_path, spec_dir)?;
+    let drift = spec_drift::check(Path::new("."), spec_dir, impl_dir)?;
+    let violations = check_thresholds(&report, &thresholds);
 
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&report)?);
+        let mut output = serde_json::to_value(&report)?;
+        output["spec_drift"] = serde_json::to_value(&drift)?;
+        output["thresholds"] = thresholds_json(&thresholds, &violations);
+        println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
+        if let Some(drift) = &drift {
+            drift.print_warning();
+            println!();
+        }
         print_report(&report);
+        print_requirements(&report, matrix);
+        print_violations(&thresholds, &violations);
     }
 
+    check_strict(drift.as_ref(), strict)?;
+    Ok(violations.is_empty())
+}
+
+fn thresholds_json(thresholds: &VerifyConfig, violations: &[String]) -> serde_json::Value {
+    serde_json::json!({
+        "config": thresholds,
+        "passed": violations.is_empty(),
+        "violations": violations,
+    })
+}
+
+/// Suggest requirement refs for the plan's tasks, and write them with `apply`
+fn run_suggest(
+    json_output: bool,
+    plan_path: &Path,
+    spec_dir: &Path,
+    impl_dir: &Path,
+    options: SuggestOptions,
+) -> Result<()> {
+    let tasks = if has_hierarchical_plan(impl_dir) {
+        hierarchical_tasks(&ImplIndex::load(impl_dir)?)?
+    } else if plan_path.exists() {
+        vec![(plan_path.to_path_buf(), parse_plan(plan_path)?)]
+    } else {
+        bail!("Plan file not found: {}", plan_path.display());
+    };
+
+    let requirements = extract_requirements(spec_dir)?;
+    let result = suggest::suggest(&requirements, &tasks, options.min_confidence);
+    let applied = if options.apply {
+        suggest::apply(&result.suggestions)?
+    } else {
+        0
+    };
+
+    if json_output {
+        let mut output = serde_json::to_value(&result)?;
+        output["applied"] = applied.into();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print_suggestions(&result, options.apply, applied);
+    }
+    Ok(())
+}
+
+fn print_suggestions(result: &Suggestions, apply: bool, applied: usize) {
+    println!("{}", "Suggested Refs".bold());
+    if result.suggestions.is_empty() {
+        println!("  {}", "No likely matches for tasks without requirement refs".dimmed());
+    }
+    for suggestion in &result.suggestions {
+        let confidence = format!("{:.2}", suggestion.confidence);
+        let confidence = if suggestion.confidence >= suggest::STRONG_CONFIDENCE {
+            confidence.green()
+        } else if suggestion.confidence >= suggest::FAIR_CONFIDENCE {
+            confidence.yellow()
+        } else {
+            confidence.normal()
+        };
+        println!(
+            "  {} {} {}",
+            confidence,
+            suggestion.task,
+            format!("({}:{})", suggestion.file.display(), suggestion.line_number).dimmed()
+        );
+        println!(
+            "       {} {} {}",
+            "→".dimmed(),
+            suggestion.requirement.cyan(),
+            suggestion.requirement_text
+        );
+    }
+    println!();
+
+    if !result.probably_uncovered.is_empty() {
+        println!("{}", "Probably Uncovered".bold());
+        for req in &result.probably_uncovered {
+            println!(
+                "  {} {} {} {}",
+                "✗".red(),
+                req.id.cyan(),
+                req.text,
+                format!("({}.md:{})", req.spec_name, req.line_number).dimmed()
+            );
+        }
+        println!();
+    }
+
+    if apply {
+        println!("{} Added {} refs to the plan", "✓".green(), applied);
+    } else if !result.suggestions.is_empty() {
+        println!(
+            "{} Run with {} to add {} refs to the plan",
+            "→".yellow(),
+            "--apply".cyan(),
+            result.suggestions.len()
+        );
+    }
+}
+
+/// Requirements no task cites and IDs no spec defines
+///
+/// Only specs with at least one cited requirement are listed, unless
+/// `matrix` asks for every requirement.
+fn print_requirements(report: &VerifyReport, matrix: bool) {
+    let cited: BTreeSet<&str> = report
+        .requirements
+        .iter()
+        .filter(|r| !r.tasks.is_empty())
+        .map(|r| r.spec_name.as_str())
+        .collect();
+    if !matrix && cited.is_empty() && report.unknown_requirement_refs.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("{}", if matrix { "Traceability Matrix" } else { "Requirements" }.bold());
+    let rows: Vec<_> = report
+        .requirements
+        .iter()
+        .filter(|r| matrix || cited.contains(r.spec_name.as_str()))
+        .collect();
+    let traced = rows.iter().filter(|r| !r.tasks.is_empty()).count();
+    println!("  {}/{} requirements traced to tasks", traced, rows.len());
+
+    let shown = rows.iter().filter(|r| matrix || r.tasks.is_empty());
+    for (i, row) in shown.enumerate() {
+        if !matrix && i == 10 {
+            let untraced = rows.len() - traced;
+            println!("  {}", format!("... and {} more (--matrix lists all)", untraced - 10).dimmed());
+            break;
+        }
+        let mark = if row.tasks.is_empty() { "✗".red() } else { "✓".green() };
+        println!(
+            "  {} {} {} {}",
+            mark,
+            row.id.cyan(),
+            row.text,
+            format!("({}.md:{})", row.spec_name, row.line_number).dimmed()
+        );
+        for task in &row.tasks {
+            println!("      {} {}", "←".dimmed(), task);
+        }
+    }
+
+    for unknown in &report.unknown_requirement_refs {
+        println!(
+            "  {} {} does not exist, cited by: {} {}",
+            "?".red(),
+            unknown.requirement.red(),
+            unknown.task,
+            format!("(line {})", unknown.line_number).dimmed()
+        );
+    }
+}
+
+/// List threshold violations (nothing when no thresholds are set)
+fn print_violations(thresholds: &VerifyConfig, violations: &[String]) {
+    if thresholds.is_empty() {
+        return;
+    }
+    println!();
+    println!("{}", "Thresholds".bold());
+    if violations.is_empty() {
+        println!("  {} All thresholds met", "✓".green());
+    }
+    for violation in violations {
+        println!("  {} {}", "✗".red(), violation);
+    }
+}
+
+/// With `--strict`, spec drift fails verification
+fn check_strict(drift: Option<&SpecDrift>, strict: bool) -> Result<()> {
+    if strict && drift.is_some() {
+        bail!("Specs changed since the plan was made (--strict)");
+    }
     Ok(())
 }
 
 /// Run verification for hierarchical plan structure
-async fn run_hierarchical(json_output: bool, impl_dir: &Path, _spec_dir: &Path) -> Result<()> {
+async fn run_hierarchical(
+    json_output: bool,
+    impl_dir: &Path,
+    spec_dir: &Path,
+    drift: Option<&SpecDrift>,
+    thresholds: &VerifyConfig,
+    matrix: bool,
+) -> Result<bool> {
     let index = ImplIndex::load(impl_dir)?;
+    let report = generate_hierarchical_report(&index, spec_dir)?;
+    let violations = check_thresholds(&report, thresholds);
 
     if json_output {
         // Create JSON output compatible with existing format
@@ -77,13 +322,26 @@ async fn run_hierarchical(json_output: bool, impl_dir: &Path, _spec_dir: &Path)
                 "completed": index.cross_cutting_tasks.completed,
                 "pending": index.cross_cutting_tasks.pending,
             },
+            "spec_drift": drift,
+            "orphan_tasks": report.orphan_tasks,
+            "dangling_refs": report.dangling_refs,
+            "coverage": report.coverage,
+            "requirements": report.requirements,
+            "unknown_requirement_refs": report.unknown_requirement_refs,
+            "thresholds": thresholds_json(thresholds, &violations),
         });
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
+        if let Some(drift) = drift {
+            drift.print_warning();
+            println!();
+        }
         print_hierarchical_report(&index);
+        print_requirements(&report, matrix);
+        print_violations(thresholds, &violations);
     }
 
-    Ok(())
+    Ok(violations.is_empty())
 }
 
 /// Print hierarchical plan verification report
@@ -232,11 +490,9 @@ fn print_report(report: &VerifyReport) {
         "  Completed:       {}",
         format!("{} ({}%)",
             report.completed_tasks,
-            if report.total_tasks > 0 {
-                report.completed_tasks * 100 / report.total_tasks
-            } else {
-                0
-            }
+            (report.completed_tasks * 100)
+                .checked_div(report.total_tasks)
+                .unwrap_or(0)
         ).green()
     );
     println!(
@@ -287,13 +543,17 @@ fn print_report(report: &VerifyReport) {
                 coverage_str.red()
             };
 
+            let traced = match entry.traced_requirements {
+                Some(traced) => format!("{} traced", traced),
+                None => format!("{} tasks", entry.task_count),
+            };
             println!(
-                "  {:20} {} {} ({} reqs, {} tasks)",
+                "  {:20} {} {} ({} reqs, {})",
                 entry.spec_name,
                 bar,
                 colored_coverage,
                 entry.requirement_count,
-                entry.task_count
+                traced
             );
         }
         println!();
@@ -0,0 +1,192 @@
+use anyhow::{bail, Result};
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::mpsc;
+
+use crate::cli::verbosity_flag;
+use crate::commands;
+use crate::config::Config;
+use crate::docker;
+use crate::state::FinishType;
+use crate::watch::{start_watcher, ChangeSet, Snapshot};
+
+/// Interval between scans when falling back to the polling watcher
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run the watch command - re-plan whenever specs change
+pub async fn run(
+    max_iterations: u32,
+    watch_src: bool,
+    then_build: bool,
+    debounce_ms: u64,
+    poll: bool,
+    verbosity: i8,
+) -> Result<()> {
+    // Check for .fresher directory
+    if !Path::new(".fresher").exists() {
+        bail!(".fresher/ not found. Run {} first.", "fresher init".cyan());
+    }
+
+    let config = Config::load()?;
+
+    // Run the whole watch session inside the container when Docker is enabled,
+    // so every planning/building cycle uses the same sandbox
+    let mut docker_args = vec![
+        "watch".to_string(),
+        "--max-iterations".to_string(),
+        max_iterations.to_string(),
+        "--debounce-ms".to_string(),
+        debounce_ms.to_string(),
+    ];
+    if watch_src {
+        docker_args.push("--src".to_string());
+    }
+    if then_build {
+        docker_args.push("--build".to_string());
+    }
+    if poll {
+        docker_args.push("--poll".to_string());
+    }
+    docker_args.extend(verbosity_flag(verbosity));
+    match docker::run_in_container(&config, &docker_args)? {
+        docker::PROCEED_NORMALLY => {}
+        code => std::process::exit(code),
+    }
+
+    let spec_dir = PathBuf::from(&config.paths.spec_dir);
+    if !spec_dir.exists() {
+        bail!("Spec directory {} not found", spec_dir.display());
+    }
+
+    let mut dirs = vec![spec_dir.clone()];
+    if watch_src {
+        dirs.push(PathBuf::from(&config.paths.src_dir));
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // Keep the watcher alive for the whole session
+    let (_watcher, kind) = start_watcher(&dirs, tx, poll, POLL_INTERVAL)?;
+
+    println!("{}", "Starting Fresher (Watch Mode)".bold().magenta());
+    println!("{}", "─".repeat(40));
+    for dir in &dirs {
+        println!("  Watching:   {}", dir.display().to_string().cyan());
+    }
+    println!("  Watcher:    {}", kind.to_string().cyan());
+    println!(
+        "  On change:  {}",
+        if then_build {
+            "plan, then build"
+        } else {
+            "plan"
+        }
+        .cyan()
+    );
+    println!();
+    println!("{}", "Waiting for changes (Ctrl+C to stop)...".dimmed());
+
+    let debounce = Duration::from_millis(debounce_ms);
+    let mut snapshot = Snapshot::take(&dirs);
+
+    loop {
+        // Wait for the first event of a burst, or Ctrl+C
+        tokio::select! {
+            event = rx.recv() => {
+                if event.is_none() {
+                    bail!("File watcher stopped unexpectedly");
+                }
+            }
+            _ = signal::ctrl_c() => {
+                println!("\n{}", "Stopping watch".yellow());
+                return Ok(());
+            }
+        }
+
+        // Debounce: wait until no events arrive for the debounce period
+        while let Ok(Some(())) = tokio::time::timeout(debounce, rx.recv()).await {}
+
+        let current = Snapshot::take(&dirs);
+        let changes = snapshot.diff(&current);
+        if changes.is_empty() {
+            continue;
+        }
+
+        print_changes(&changes, &dirs);
+
+        if !run_cycle(max_iterations, then_build, verbosity).await? {
+            println!("\n{}", "Stopping watch".yellow());
+            return Ok(());
+        }
+
+        // Files touched by the cycle itself are the new baseline; discard the
+        // events they produced so they don't immediately trigger another cycle
+        snapshot = Snapshot::take(&dirs);
+        while rx.try_recv().is_ok() {}
+
+        println!();
+        println!("{}", "Waiting for changes (Ctrl+C to stop)...".dimmed());
+    }
+}
+
+/// Run one planning (and optionally building) cycle.
+///
+/// Returns `false` when the watch session should stop, i.e. when a run was
+/// ended manually (Ctrl+C or a hook abort) or by a policy violation.
+async fn run_cycle(max_iterations: u32, then_build: bool, verbosity: i8) -> Result<bool> {
+    let finish = commands::plan::run(Some(max_iterations), false, false, verbosity).await?;
+    match finish {
+        FinishType::Manual | FinishType::PolicyViolation => return Ok(false),
+        FinishType::Error => {
+            eprintln!("{}", "Planning failed; waiting for further changes".red());
+            return Ok(true);
+        }
+        FinishType::Budget | FinishType::Stalled | FinishType::OutcomeStop => {
+            if then_build {
+                eprintln!(
+                    "{}",
+                    format!("Planning ended ({}); skipping the build", finish).yellow()
+                );
+            }
+            return Ok(true);
+        }
+        _ => {}
+    }
+
+    if then_build {
+        println!();
+        let finish = commands::build::run(None, false, false, false, None, false, verbosity).await?;
+        if matches!(finish, FinishType::Manual | FinishType::PolicyViolation) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn print_changes(changes: &ChangeSet, dirs: &[PathBuf]) {
+    println!();
+    println!(
+        "{} {}",
+        format!(
+            "Detected {} changed file{}",
+            changes.len(),
+            if changes.len() == 1 { "" } else { "s" }
+        )
+        .bold()
+        .cyan(),
+        "─".repeat(20)
+    );
+    for dir in dirs {
+        for (status, path) in changes.under(dir) {
+            let status = match status {
+                'A' => "added".green(),
+                'D' => "removed".red(),
+                _ => "modified".yellow(),
+            };
+            println!("  {:>8}  {}", status, path.display());
+        }
+    }
+    println!();
+}
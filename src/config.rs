@@ -1,7 +1,10 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::streaming::IterationOutcome;
 
 /// Fresher configuration loaded from environment and config.toml
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +14,24 @@ pub struct Config {
     pub paths: PathsConfig,
     pub hooks: HooksConfig,
     pub docker: DockerConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub outcomes: OutcomeConfig,
+    #[serde(default)]
+    pub guardrails: GuardrailsConfig,
+    #[serde(default)]
+    pub scope: ScopeConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub todos: TodosConfig,
+    #[serde(default)]
+    pub verify: VerifyConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +48,9 @@ pub struct FresherConfig {
     /// Task count threshold below which single-file plans are preferred
     #[serde(default = "default_single_file_threshold")]
     pub single_file_threshold: u32,
+    /// Stream partial messages: show text and tool calls as they are generated
+    #[serde(default)]
+    pub partial_messages: bool,
 }
 
 fn default_archive_completed() -> bool {
@@ -58,10 +82,104 @@ fn default_impl_dir() -> String {
     "impl".to_string()
 }
 
+impl PathsConfig {
+    /// Log file for `iteration`, relative to the project root
+    ///
+    /// Hook output from before the first iteration goes to iteration 0.
+    pub fn iteration_log(&self, iteration: u32) -> PathBuf {
+        Path::new(&self.log_dir).join(format!("iteration-{}.log", iteration))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HooksConfig {
     pub enabled: bool,
     pub timeout: u32,
+    #[serde(default)]
+    pub protocol: HookProtocol,
+    /// Inline shell commands per hook name, run after the hook scripts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, HookCommands>,
+    /// Failure policies per hook name (default: continue)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub policy: BTreeMap<String, HookPolicy>,
+}
+
+impl HooksConfig {
+    /// Failure policy for `hook_name`
+    pub fn policy_for(&self, hook_name: &str) -> HookPolicy {
+        self.policy.get(hook_name).copied().unwrap_or_default()
+    }
+}
+
+/// How hooks receive their context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookProtocol {
+    /// Environment variables only
+    #[default]
+    Env,
+    /// Also a JSON context document on stdin, and a JSON response read from
+    /// stdout
+    Json,
+}
+
+impl std::fmt::Display for HookProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookProtocol::Env => write!(f, "env"),
+            HookProtocol::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// What a hook's timeout or error counts as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailureAction {
+    /// Carry on as if the hook was not there (fail-open)
+    #[default]
+    Continue,
+    /// Treat it like exit code 1
+    Skip,
+    /// Treat it like exit code 2 (fail-closed)
+    Abort,
+}
+
+impl std::fmt::Display for HookFailureAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookFailureAction::Continue => write!(f, "continue"),
+            HookFailureAction::Skip => write!(f, "skip"),
+            HookFailureAction::Abort => write!(f, "abort"),
+        }
+    }
+}
+
+/// `[hooks.policy.<name>]`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookPolicy {
+    #[serde(default)]
+    pub on_timeout: HookFailureAction,
+    #[serde(default)]
+    pub on_error: HookFailureAction,
+}
+
+/// One inline hook command or a list of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HookCommands {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HookCommands {
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        match self {
+            HookCommands::One(command) => std::slice::from_ref(command).iter(),
+            HookCommands::Many(commands) => commands.iter(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +199,398 @@ pub struct DockerConfig {
     pub local_binary: Option<String>,
 }
 
+/// Webhook payload format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyFormat {
+    /// Generic JSON with the event, state and plan progress
+    #[default]
+    Json,
+    /// A Slack incoming-webhook message
+    Slack,
+}
+
+impl std::fmt::Display for NotifyFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyFormat::Json => write!(f, "json"),
+            NotifyFormat::Slack => write!(f, "slack"),
+        }
+    }
+}
+
+/// Built-in webhook notifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    /// Webhook URLs to POST to (empty = notifications off)
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    /// Payload format
+    #[serde(default)]
+    pub format: NotifyFormat,
+    /// Events to send: "finished" (any finish), a finish type such as
+    /// "error", "stalled" or "budget", and/or "iteration"
+    #[serde(default = "default_notify_events")]
+    pub events: Vec<String>,
+    /// Delivery attempts after the first one fails (at most
+    /// `MAX_NOTIFY_RETRIES`)
+    #[serde(default = "default_notify_retries")]
+    pub retries: u32,
+    /// Request timeout in seconds (at least 1)
+    #[serde(default = "default_notify_timeout")]
+    pub timeout: u32,
+}
+
+/// Upper bound for `notify.retries`
+pub const MAX_NOTIFY_RETRIES: u32 = 10;
+
+fn default_notify_events() -> Vec<String> {
+    vec!["finished".to_string()]
+}
+
+fn default_notify_retries() -> u32 {
+    3
+}
+
+fn default_notify_timeout() -> u32 {
+    10
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            format: NotifyFormat::default(),
+            events: default_notify_events(),
+            retries: default_notify_retries(),
+            timeout: default_notify_timeout(),
+        }
+    }
+}
+
+/// What the loop commands print from Claude's stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Print a preview of each tool result
+    #[serde(default)]
+    pub show_tool_results: bool,
+    /// Maximum characters of a tool result preview (0 = no limit)
+    #[serde(default = "default_output_truncate")]
+    pub truncate: usize,
+    /// Hide Claude's assistant text (tool calls are still shown)
+    #[serde(default)]
+    pub hide_text: bool,
+    /// Only show calls to these tools (empty = all tools)
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+fn default_output_truncate() -> usize {
+    200
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            show_tool_results: false,
+            truncate: default_output_truncate(),
+            hide_text: false,
+            tools: Vec::new(),
+        }
+    }
+}
+
+/// Run budgets; the loop finishes with `budget` once one is reached
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Maximum run cost in USD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+    /// Maximum tokens (input, output and cache) across the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+}
+
+/// Deny patterns checked against every Bash tool call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuardrailsConfig {
+    /// Regexes; a Bash command matching any of them stops the run
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// After a violation, undo the iteration's commits and the files it changed
+    #[serde(default)]
+    pub rollback: bool,
+}
+
+/// What happens to files an iteration changed out of scope
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScopeAction {
+    /// Report them and carry on
+    #[default]
+    Flag,
+    /// Restore them to the iteration's starting commit
+    Revert,
+    /// Revert them and finish with `policy_violation`
+    Stop,
+}
+
+impl std::fmt::Display for ScopeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScopeAction::Flag => write!(f, "flag"),
+            ScopeAction::Revert => write!(f, "revert"),
+            ScopeAction::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+/// Which paths an iteration may change (see `scope`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Globs an iteration may change (empty = src_dir, tests/ and the plan files)
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Globs an iteration must never change; checked before `allow`
+    #[serde(default = "default_scope_deny")]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub action: ScopeAction,
+}
+
+fn default_scope_deny() -> Vec<String> {
+    vec![
+        ".fresher/config.toml".to_string(),
+        ".fresher/hooks/".to_string(),
+        ".github/".to_string(),
+    ]
+}
+
+impl Default for ScopeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow: Vec::new(),
+            deny: default_scope_deny(),
+            action: ScopeAction::default(),
+        }
+    }
+}
+
+/// What happens when an iteration's commits contain possible secrets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsAction {
+    /// Report them and carry on
+    #[default]
+    Warn,
+    /// Finish with `policy_violation` before the next iteration
+    Block,
+    /// Squash the iteration's commits with the values redacted
+    Rewrite,
+}
+
+impl std::fmt::Display for SecretsAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretsAction::Warn => write!(f, "warn"),
+            SecretsAction::Block => write!(f, "block"),
+            SecretsAction::Rewrite => write!(f, "rewrite"),
+        }
+    }
+}
+
+/// A custom secret pattern (`[[secrets.rules]]`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretRule {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// Secret scanning of each iteration's commits (see `secrets`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    #[serde(default = "default_secrets_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub action: SecretsAction,
+    /// Bits per character above which a value next to a key/token/password
+    /// keyword is reported (0 = off)
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+    /// Extra patterns on top of the built-in rules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<SecretRule>,
+}
+
+fn default_secrets_enabled() -> bool {
+    true
+}
+
+fn default_entropy_threshold() -> f64 {
+    4.0
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_secrets_enabled(),
+            action: SecretsAction::default(),
+            entropy_threshold: default_entropy_threshold(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// The agent's `TodoWrite` list (see `todos`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodosConfig {
+    /// Print the list as a checklist whenever it changes
+    #[serde(default = "default_show_todos")]
+    pub show: bool,
+    /// Compare the list with the plan's focus task after each building iteration
+    #[serde(default)]
+    pub check_focus: bool,
+}
+
+fn default_show_todos() -> bool {
+    true
+}
+
+impl Default for TodosConfig {
+    fn default() -> Self {
+        Self {
+            show: default_show_todos(),
+            check_focus: false,
+        }
+    }
+}
+
+/// Thresholds that make `fresher verify` fail (all off by default)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifyConfig {
+    /// Minimum coverage (percent) of every spec
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_coverage: Option<f64>,
+    /// Maximum tasks without spec refs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_orphan_tasks: Option<usize>,
+    /// Fail when a task refers to a spec that doesn't exist
+    #[serde(default)]
+    pub no_dangling_refs: bool,
+    /// Fail when a task has no priority
+    #[serde(default)]
+    pub require_priority: bool,
+}
+
+impl VerifyConfig {
+    /// Apply command-line thresholds on top of these
+    pub fn merge(&mut self, other: &VerifyConfig) {
+        self.min_coverage = other.min_coverage.or(self.min_coverage);
+        self.max_orphan_tasks = other.max_orphan_tasks.or(self.max_orphan_tasks);
+        self.no_dangling_refs |= other.no_dangling_refs;
+        self.require_priority |= other.require_priority;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What the loop does after an iteration ends a given way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutcomeAction {
+    /// Carry on with the next iteration
+    Continue,
+    /// Run the next iteration with more turns (up to `max_retries` times)
+    Retry,
+    /// Count toward `stall_limit`
+    Stall,
+    /// Treat it like a non-zero exit (on_error hook, then finish with `error`)
+    Error,
+    /// Finish with `outcome_stop`
+    Stop,
+}
+
+impl std::fmt::Display for OutcomeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutcomeAction::Continue => write!(f, "continue"),
+            OutcomeAction::Retry => write!(f, "retry"),
+            OutcomeAction::Stall => write!(f, "stall"),
+            OutcomeAction::Error => write!(f, "error"),
+            OutcomeAction::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+/// Per-outcome policy (`[outcomes]`); successful iterations always continue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutcomeConfig {
+    #[serde(default = "default_max_turns_action")]
+    pub max_turns: OutcomeAction,
+    #[serde(default = "default_error_action")]
+    pub execution_error: OutcomeAction,
+    #[serde(default = "default_error_action")]
+    pub interrupted: OutcomeAction,
+    /// Consecutive retries before a `retry` outcome counts as a stall
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Turns added to `max_turns` on each retry
+    #[serde(default = "default_retry_extra_turns")]
+    pub retry_extra_turns: u32,
+    /// Consecutive stalled iterations before finishing with `stalled` (0 = never)
+    #[serde(default = "default_stall_limit")]
+    pub stall_limit: u32,
+}
+
+fn default_max_turns_action() -> OutcomeAction {
+    OutcomeAction::Retry
+}
+
+fn default_error_action() -> OutcomeAction {
+    OutcomeAction::Error
+}
+
+fn default_max_retries() -> u32 {
+    1
+}
+
+fn default_retry_extra_turns() -> u32 {
+    25
+}
+
+fn default_stall_limit() -> u32 {
+    3
+}
+
+impl Default for OutcomeConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: default_max_turns_action(),
+            execution_error: default_error_action(),
+            interrupted: default_error_action(),
+            max_retries: default_max_retries(),
+            retry_extra_turns: default_retry_extra_turns(),
+            stall_limit: default_stall_limit(),
+        }
+    }
+}
+
+impl OutcomeConfig {
+    pub fn action_for(&self, outcome: IterationOutcome) -> OutcomeAction {
+        match outcome {
+            IterationOutcome::Success => OutcomeAction::Continue,
+            IterationOutcome::MaxTurns => self.max_turns,
+            IterationOutcome::ExecutionError => self.execution_error,
+            IterationOutcome::Interrupted => self.interrupted,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -93,6 +603,7 @@ impl Default for Config {
                 model: "sonnet".to_string(),
                 archive_completed: true,
                 single_file_threshold: 8,
+                partial_messages: false,
             },
             commands: CommandsConfig {
                 test: String::new(),
@@ -108,6 +619,9 @@ impl Default for Config {
             hooks: HooksConfig {
                 enabled: true,
                 timeout: 30,
+                protocol: HookProtocol::default(),
+                commands: BTreeMap::new(),
+                policy: BTreeMap::new(),
             },
             docker: DockerConfig {
                 use_docker: false,
@@ -117,6 +631,15 @@ impl Default for Config {
                 setup_script: None,
                 local_binary: None,
             },
+            notify: NotifyConfig::default(),
+            output: OutputConfig::default(),
+            budget: BudgetConfig::default(),
+            outcomes: OutcomeConfig::default(),
+            guardrails: GuardrailsConfig::default(),
+            scope: ScopeConfig::default(),
+            secrets: SecretsConfig::default(),
+            todos: TodosConfig::default(),
+            verify: VerifyConfig::default(),
         }
     }
 }
@@ -139,10 +662,22 @@ impl Config {
 
         // Override with environment variables
         config.apply_env_overrides();
+        config.validate()?;
 
         Ok(config)
     }
 
+    /// Reject values that parse but can't work
+    fn validate(&self) -> Result<()> {
+        if self.notify.timeout == 0 {
+            bail!("notify.timeout must be at least 1 second");
+        }
+        if self.notify.retries > MAX_NOTIFY_RETRIES {
+            bail!("notify.retries must be at most {}", MAX_NOTIFY_RETRIES);
+        }
+        Ok(())
+    }
+
     /// Apply environment variable overrides (env vars take precedence)
     fn apply_env_overrides(&mut self) {
         // Mode
@@ -180,6 +715,9 @@ impl Config {
                 self.fresher.single_file_threshold = n;
             }
         }
+        if let Ok(val) = env::var("FRESHER_PARTIAL_MESSAGES") {
+            self.fresher.partial_messages = val.to_lowercase() == "true";
+        }
 
         // Commands
         if let Ok(val) = env::var("FRESHER_TEST_CMD") {
@@ -215,6 +753,13 @@ impl Config {
                 self.hooks.timeout = n;
             }
         }
+        if let Ok(val) = env::var("FRESHER_HOOK_PROTOCOL") {
+            match val.to_lowercase().as_str() {
+                "env" => self.hooks.protocol = HookProtocol::Env,
+                "json" => self.hooks.protocol = HookProtocol::Json,
+                _ => {}
+            }
+        }
 
         // Docker
         if let Ok(val) = env::var("FRESHER_USE_DOCKER") {
@@ -235,6 +780,96 @@ impl Config {
         if let Ok(val) = env::var("FRESHER_DOCKER_LOCAL_BINARY") {
             self.docker.local_binary = Some(val);
         }
+
+        // Notifications
+        if let Ok(val) = env::var("FRESHER_NOTIFY_WEBHOOKS") {
+            self.notify.webhooks = val
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+        if let Ok(val) = env::var("FRESHER_NOTIFY_FORMAT") {
+            match val.to_lowercase().as_str() {
+                "json" => self.notify.format = NotifyFormat::Json,
+                "slack" => self.notify.format = NotifyFormat::Slack,
+                _ => {}
+            }
+        }
+        if let Ok(val) = env::var("FRESHER_NOTIFY_EVENTS") {
+            self.notify.events = val
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+
+        // Budgets
+        if let Ok(val) = env::var("FRESHER_MAX_COST_USD") {
+            if let Ok(n) = val.parse() {
+                self.budget.max_cost_usd = Some(n);
+            }
+        }
+        if let Ok(val) = env::var("FRESHER_MAX_TOKENS") {
+            if let Ok(n) = val.parse() {
+                self.budget.max_tokens = Some(n);
+            }
+        }
+
+        // Outcome policy
+        if let Ok(val) = env::var("FRESHER_STALL_LIMIT") {
+            if let Ok(n) = val.parse() {
+                self.outcomes.stall_limit = n;
+            }
+        }
+
+        // Guardrails
+        if let Ok(val) = env::var("FRESHER_GUARDRAILS_ROLLBACK") {
+            self.guardrails.rollback = val.to_lowercase() == "true";
+        }
+
+        // Path scope
+        if let Ok(val) = env::var("FRESHER_SCOPE_ENABLED") {
+            self.scope.enabled = val.to_lowercase() == "true";
+        }
+
+        // Secret scanning
+        if let Ok(val) = env::var("FRESHER_SECRETS_ENABLED") {
+            self.secrets.enabled = val.to_lowercase() == "true";
+        }
+        if let Ok(val) = env::var("FRESHER_SECRETS_ACTION") {
+            match val.to_lowercase().as_str() {
+                "warn" => self.secrets.action = SecretsAction::Warn,
+                "block" => self.secrets.action = SecretsAction::Block,
+                "rewrite" => self.secrets.action = SecretsAction::Rewrite,
+                _ => {}
+            }
+        }
+
+        // Todo list
+        if let Ok(val) = env::var("FRESHER_TODOS_CHECK_FOCUS") {
+            self.todos.check_focus = val.to_lowercase() == "true";
+        }
+
+        // Output
+        if let Ok(val) = env::var("FRESHER_OUTPUT_SHOW_TOOL_RESULTS") {
+            self.output.show_tool_results = val.to_lowercase() == "true";
+        }
+        if let Ok(val) = env::var("FRESHER_OUTPUT_TRUNCATE") {
+            if let Ok(n) = val.parse() {
+                self.output.truncate = n;
+            }
+        }
+        if let Ok(val) = env::var("FRESHER_OUTPUT_HIDE_TEXT") {
+            self.output.hide_text = val.to_lowercase() == "true";
+        }
+        if let Ok(val) = env::var("FRESHER_OUTPUT_TOOLS") {
+            self.output.tools = val
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
     }
 
     /// Generate a config.toml content string
@@ -436,13 +1071,16 @@ mod tests {
 
         env::set_var("FRESHER_HOOKS_ENABLED", "false");
         env::set_var("FRESHER_HOOK_TIMEOUT", "60");
+        env::set_var("FRESHER_HOOK_PROTOCOL", "json");
         config.apply_env_overrides();
 
         assert!(!config.hooks.enabled);
         assert_eq!(config.hooks.timeout, 60);
+        assert_eq!(config.hooks.protocol, HookProtocol::Json);
 
         env::remove_var("FRESHER_HOOKS_ENABLED");
         env::remove_var("FRESHER_HOOK_TIMEOUT");
+        env::remove_var("FRESHER_HOOK_PROTOCOL");
     }
 
     #[test]
@@ -643,6 +1281,19 @@ mod tests {
         env::remove_var("FRESHER_SINGLE_FILE_THRESHOLD");
     }
 
+    #[test]
+    fn test_env_override_partial_messages() {
+        let mut config = Config::default();
+        assert!(!config.fresher.partial_messages);
+
+        env::set_var("FRESHER_PARTIAL_MESSAGES", "true");
+        config.apply_env_overrides();
+
+        assert!(config.fresher.partial_messages);
+
+        env::remove_var("FRESHER_PARTIAL_MESSAGES");
+    }
+
     #[test]
     fn test_env_override_impl_dir() {
         let mut config = Config::default();
@@ -654,4 +1305,214 @@ mod tests {
 
         env::remove_var("FRESHER_IMPL_DIR");
     }
+
+    #[test]
+    fn test_env_override_notify() {
+        let mut config = Config::default();
+
+        env::set_var("FRESHER_NOTIFY_WEBHOOKS", "http://a.example/hook, http://b.example/hook");
+        env::set_var("FRESHER_NOTIFY_FORMAT", "slack");
+        env::set_var("FRESHER_NOTIFY_EVENTS", "error,iteration,");
+        config.apply_env_overrides();
+
+        assert_eq!(
+            config.notify.webhooks,
+            vec!["http://a.example/hook", "http://b.example/hook"]
+        );
+        assert_eq!(config.notify.format, NotifyFormat::Slack);
+        assert_eq!(config.notify.events, vec!["error", "iteration"]);
+
+        env::remove_var("FRESHER_NOTIFY_WEBHOOKS");
+        env::remove_var("FRESHER_NOTIFY_FORMAT");
+        env::remove_var("FRESHER_NOTIFY_EVENTS");
+    }
+
+    #[test]
+    fn test_notify_section_optional() {
+        let mut toml_str = Config::default().to_toml_string().unwrap();
+        let start = toml_str.find("[notify]").unwrap();
+        toml_str.truncate(start);
+
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        assert!(config.notify.webhooks.is_empty());
+        assert_eq!(config.notify.events, vec!["finished"]);
+        assert_eq!(config.notify.retries, 3);
+        assert_eq!(config.notify.format, NotifyFormat::Json);
+        assert!(config.validate().is_ok());
+
+        let mut bad = config.clone();
+        bad.notify.timeout = 0;
+        assert!(bad.validate().is_err());
+        let mut bad = config.clone();
+        bad.notify.retries = MAX_NOTIFY_RETRIES + 1;
+        assert!(bad.validate().is_err());
+
+        // An unknown format is a parse error rather than a silent fallback
+        let bad = format!("{}[notify]\nformat = \"slak\"\n", toml_str);
+        assert!(toml::from_str::<Config>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_hook_commands_one_or_many() {
+        let toml_str = Config::default().to_toml_string().unwrap().replace(
+            "[docker]",
+            "[hooks.commands]\nstarted = \"make check\"\nfinished = [\"./notify.sh\", \"make clean\"]\n\n[docker]",
+        );
+
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        let started: Vec<_> = config.hooks.commands["started"].iter().collect();
+        assert_eq!(started, ["make check"]);
+        let finished: Vec<_> = config.hooks.commands["finished"].iter().collect();
+        assert_eq!(finished, ["./notify.sh", "make clean"]);
+
+        // Round-trips, and stays out of the file when empty
+        let reparsed: Config = toml::from_str(&config.to_toml_string().unwrap()).unwrap();
+        assert_eq!(reparsed.hooks.commands, config.hooks.commands);
+        assert!(!Config::default().to_toml_string().unwrap().contains("hooks.commands"));
+    }
+
+    #[test]
+    fn test_hook_policy() {
+        let toml_str = Config::default().to_toml_string().unwrap().replace(
+            "[docker]",
+            "[hooks.policy.started]\non_timeout = \"abort\"\non_error = \"skip\"\n\n[docker]",
+        );
+
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        let started = config.hooks.policy_for("started");
+        assert_eq!(started.on_timeout, HookFailureAction::Abort);
+        assert_eq!(started.on_error, HookFailureAction::Skip);
+        assert_eq!(config.hooks.policy_for("finished"), HookPolicy::default());
+
+        let bad = toml_str.replace("on_error = \"skip\"", "on_error = \"explode\"");
+        assert!(toml::from_str::<Config>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_hook_protocol() {
+        let toml_str = Config::default().to_toml_string().unwrap();
+        assert!(toml_str.contains("protocol = \"env\""));
+
+        let config: Config = toml::from_str(&toml_str.replace("protocol = \"env\"", "protocol = \"json\"")).unwrap();
+        assert_eq!(config.hooks.protocol, HookProtocol::Json);
+
+        let bad = toml_str.replace("protocol = \"env\"", "protocol = \"JSON\"");
+        assert!(toml::from_str::<Config>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_output_config() {
+        // Older config files have no [output] section
+        let toml_str = Config::default().to_toml_string().unwrap();
+        let without: String = toml_str.split("[output]").next().unwrap().to_string();
+        let config: Config = toml::from_str(&without).unwrap();
+        assert_eq!(config.output.truncate, 200);
+        assert!(config.output.tools.is_empty());
+
+        let with = format!("{}[output]\nhide_text = true\ntools = [\"Bash\", \"Edit\"]\n", without);
+        let config: Config = toml::from_str(&with).unwrap();
+        assert!(config.output.hide_text);
+        assert!(!config.output.show_tool_results);
+        assert_eq!(config.output.tools, vec!["Bash", "Edit"]);
+    }
+
+    #[test]
+    fn test_env_override_output() {
+        let mut config = Config::default();
+
+        env::set_var("FRESHER_OUTPUT_SHOW_TOOL_RESULTS", "true");
+        env::set_var("FRESHER_OUTPUT_TRUNCATE", "80");
+        env::set_var("FRESHER_OUTPUT_TOOLS", "Bash, Write,");
+        config.apply_env_overrides();
+
+        assert!(config.output.show_tool_results);
+        assert_eq!(config.output.truncate, 80);
+        assert_eq!(config.output.tools, vec!["Bash", "Write"]);
+
+        env::remove_var("FRESHER_OUTPUT_SHOW_TOOL_RESULTS");
+        env::remove_var("FRESHER_OUTPUT_TRUNCATE");
+        env::remove_var("FRESHER_OUTPUT_TOOLS");
+    }
+
+    #[test]
+    fn test_outcome_config() {
+        let config = Config::default();
+        assert_eq!(config.outcomes.action_for(IterationOutcome::Success), OutcomeAction::Continue);
+        assert_eq!(config.outcomes.action_for(IterationOutcome::MaxTurns), OutcomeAction::Retry);
+
+        let toml_str = config
+            .to_toml_string()
+            .unwrap()
+            .replace("max_turns = \"retry\"", "max_turns = \"stall\"");
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(config.outcomes.action_for(IterationOutcome::MaxTurns), OutcomeAction::Stall);
+        assert_eq!(config.outcomes.action_for(IterationOutcome::Interrupted), OutcomeAction::Error);
+    }
+
+    #[test]
+    fn test_env_override_budget() {
+        let mut config = Config::default();
+        assert!(config.budget.max_cost_usd.is_none());
+
+        env::set_var("FRESHER_MAX_COST_USD", "2.5");
+        env::set_var("FRESHER_MAX_TOKENS", "500000");
+        config.apply_env_overrides();
+
+        assert_eq!(config.budget.max_cost_usd, Some(2.5));
+        assert_eq!(config.budget.max_tokens, Some(500000));
+
+        env::remove_var("FRESHER_MAX_COST_USD");
+        env::remove_var("FRESHER_MAX_TOKENS");
+    }
+
+    #[test]
+    fn test_secrets_config() {
+        let config = Config::default();
+        assert!(config.secrets.enabled);
+        assert_eq!(config.secrets.action, SecretsAction::Warn);
+
+        let toml_str = format!(
+            "{}\n[[secrets.rules]]\nname = \"internal\"\npattern = \"itk_[a-z0-9]{{32}}\"\n",
+            config.to_toml_string().unwrap().replace("action = \"warn\"", "action = \"rewrite\"")
+        );
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(config.secrets.action, SecretsAction::Rewrite);
+        assert_eq!(config.secrets.rules[0].name, "internal");
+
+        env::set_var("FRESHER_SECRETS_ACTION", "block");
+        let mut config = Config::default();
+        config.apply_env_overrides();
+        assert_eq!(config.secrets.action, SecretsAction::Block);
+        env::remove_var("FRESHER_SECRETS_ACTION");
+    }
+
+    #[test]
+    fn test_verify_config_merge() {
+        let mut config: VerifyConfig =
+            toml::from_str("min_coverage = 80.0\nmax_orphan_tasks = 5\n").unwrap();
+        assert!(!config.is_empty());
+        config.merge(&VerifyConfig {
+            max_orphan_tasks: Some(0),
+            require_priority: true,
+            ..Default::default()
+        });
+        assert_eq!(config.min_coverage, Some(80.0));
+        assert_eq!(config.max_orphan_tasks, Some(0));
+        assert!(config.require_priority);
+        assert!(!config.no_dangling_refs);
+        assert!(Config::default().verify.is_empty());
+    }
+
+    #[test]
+    fn test_todos_config() {
+        let config = Config::default();
+        assert!(config.todos.show);
+        assert!(!config.todos.check_focus);
+
+        env::set_var("FRESHER_TODOS_CHECK_FOCUS", "true");
+        let mut config = Config::default();
+        config.apply_env_overrides();
+        assert!(config.todos.check_focus);
+        env::remove_var("FRESHER_TODOS_CHECK_FOCUS");
+    }
 }
@@ -0,0 +1,564 @@
+//! Local live dashboard for `fresher build --serve`
+//!
+//! A deliberately small HTTP/1.1 server on top of tokio: one self-contained
+//! status page, a JSON status endpoint, and a server-sent events endpoint that
+//! relays every event the [`StreamHandler`](crate::streaming::StreamHandler)
+//! receives. It only binds to loopback addresses, only answers requests whose
+//! `Host` names a loopback address or `localhost`, and serves no external assets.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::impl_plan::{has_hierarchical_plan, ImplIndex};
+use crate::streaming::{tool_call_detail, ContentBlock, StreamEvent};
+use crate::verify::{count_tasks, parse_plan};
+
+/// Number of recent tool calls kept for the status page
+const RECENT_TOOL_CALLS: usize = 25;
+
+/// Capacity of the event channel; slow SSE clients skip events beyond this
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Interval between SSE keep-alive comments
+const KEEPALIVE_SECS: u64 = 15;
+
+/// Snapshot of the run served at `/api/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardStatus {
+    pub mode: String,
+    pub started_at: DateTime<Utc>,
+    pub iteration: u32,
+    pub max_iterations: u32,
+    pub total_cost_usd: f64,
+    pub finished: Option<String>,
+    pub plan: PlanProgress,
+    pub recent_tool_calls: VecDeque<ToolCallSummary>,
+}
+
+/// Task progress of the implementation plan
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlanProgress {
+    pub total_tasks: usize,
+    pub completed_tasks: usize,
+    pub pending_tasks: usize,
+    pub current_focus: Option<String>,
+    pub features: Vec<FeatureProgress>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureProgress {
+    pub name: String,
+    pub completed_tasks: usize,
+    pub total_tasks: usize,
+}
+
+impl PlanProgress {
+    /// Read progress from the hierarchical plan, or the legacy plan file
+    pub fn load(impl_dir: &Path, plan_path: &Path) -> Self {
+        if has_hierarchical_plan(impl_dir) {
+            if let Ok(index) = ImplIndex::load(impl_dir) {
+                return Self {
+                    total_tasks: index.total_tasks(),
+                    completed_tasks: index.completed_tasks(),
+                    pending_tasks: index.pending_tasks(),
+                    current_focus: index.current_focus.clone(),
+                    features: index
+                        .features
+                        .iter()
+                        .map(|f| FeatureProgress {
+                            name: f.name.clone(),
+                            completed_tasks: f.completed_tasks,
+                            total_tasks: f.total_tasks,
+                        })
+                        .collect(),
+                };
+            }
+        }
+
+        match parse_plan(plan_path) {
+            Ok(tasks) => {
+                let (total, pending, completed, _) = count_tasks(&tasks);
+                Self {
+                    total_tasks: total,
+                    completed_tasks: completed,
+                    pending_tasks: pending,
+                    ..Default::default()
+                }
+            }
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// A tool call seen in the stream
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallSummary {
+    pub at: DateTime<Utc>,
+    pub iteration: u32,
+    pub name: String,
+    pub detail: Option<String>,
+}
+
+/// Handle to a running dashboard; cheap to clone
+#[derive(Clone)]
+pub struct Dashboard {
+    status: Arc<Mutex<DashboardStatus>>,
+    events: broadcast::Sender<StreamEvent>,
+    addr: SocketAddr,
+}
+
+impl Dashboard {
+    /// Bind `addr` and start serving in the background.
+    ///
+    /// Only loopback addresses are accepted; use port 0 to pick a free port.
+    pub async fn serve(addr: &str, mode: &str, max_iterations: u32) -> Result<Self> {
+        let addr: SocketAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid --serve address '{}' (expected IP:PORT)", addr))?;
+        if !addr.ip().is_loopback() {
+            bail!(
+                "--serve only binds to loopback addresses (e.g. 127.0.0.1:8080), got {}",
+                addr.ip()
+            );
+        }
+
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind dashboard to {}", addr))?;
+        let addr = listener.local_addr()?;
+
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let dashboard = Self {
+            status: Arc::new(Mutex::new(DashboardStatus {
+                mode: mode.to_string(),
+                started_at: Utc::now(),
+                iteration: 0,
+                max_iterations,
+                total_cost_usd: 0.0,
+                finished: None,
+                plan: PlanProgress::default(),
+                recent_tool_calls: VecDeque::new(),
+            })),
+            events,
+            addr,
+        };
+
+        // Fold stream events into the status snapshot
+        let recorder = dashboard.clone();
+        let mut rx = dashboard.events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => recorder.record_event(&event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let server = dashboard.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let _ = server.handle_connection(stream).await;
+                });
+            }
+        });
+
+        Ok(dashboard)
+    }
+
+    /// Address the dashboard is listening on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Sender to attach to a [`StreamHandler`](crate::streaming::StreamHandler)
+    pub fn event_sender(&self) -> broadcast::Sender<StreamEvent> {
+        self.events.clone()
+    }
+
+    /// Current status snapshot
+    pub fn status(&self) -> DashboardStatus {
+        self.lock().clone()
+    }
+
+    pub fn set_iteration(&self, iteration: u32) {
+        self.lock().iteration = iteration;
+    }
+
+    pub fn set_plan(&self, plan: PlanProgress) {
+        self.lock().plan = plan;
+    }
+
+    pub fn set_finished(&self, finish: &str) {
+        self.lock().finished = Some(finish.to_string());
+    }
+
+    /// Update the status snapshot from a stream event
+    pub fn record_event(&self, event: &StreamEvent) {
+        let mut status = self.lock();
+        match event {
+            StreamEvent::Assistant(e) => {
+                let Some(msg) = &e.message else {
+                    return;
+                };
+                for block in &msg.content {
+                    if let ContentBlock::ToolUse { name, input, .. } = block {
+                        let detail = tool_call_detail(input);
+                        let iteration = status.iteration;
+                        status.recent_tool_calls.push_front(ToolCallSummary {
+                            at: Utc::now(),
+                            iteration,
+                            name: name.clone(),
+                            detail,
+                        });
+                        status.recent_tool_calls.truncate(RECENT_TOOL_CALLS);
+                    }
+                }
+            }
+            StreamEvent::Result(e) => {
+                status.total_cost_usd += e.cost_usd.unwrap_or(0.0);
+            }
+            _ => {}
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DashboardStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await?;
+
+        // Drain the headers, keeping Host; requests have no body we care about
+        let mut host = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("host") {
+                    host = Some(value.trim().to_string());
+                }
+            }
+        }
+
+        let mut stream = reader.into_inner();
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+
+        // A DNS-rebinding page would reach us under its own host name
+        if !allowed_host(host.as_deref(), self.addr.port()) {
+            return respond(&mut stream, "403 Forbidden", "text/plain", "forbidden").await;
+        }
+
+        if method != "GET" {
+            return respond(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                "method not allowed",
+            )
+            .await;
+        }
+
+        match path {
+            "/" | "/index.html" => {
+                respond(&mut stream, "200 OK", "text/html; charset=utf-8", INDEX_HTML).await
+            }
+            "/api/status" => {
+                let body = serde_json::to_string(&self.status())?;
+                respond(&mut stream, "200 OK", "application/json", &body).await
+            }
+            "/events" => self.stream_events(&mut stream).await,
+            _ => respond(&mut stream, "404 Not Found", "text/plain", "not found").await,
+        }
+    }
+
+    /// Relay stream events to an SSE client until it disconnects
+    async fn stream_events(&self, stream: &mut TcpStream) -> Result<()> {
+        let mut rx = self.events.subscribe();
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\n\
+                  Content-Type: text/event-stream\r\n\
+                  Cache-Control: no-cache\r\n\
+                  Connection: keep-alive\r\n\r\n",
+            )
+            .await?;
+
+        let mut keepalive = tokio::time::interval(Duration::from_secs(KEEPALIVE_SECS));
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(event) => {
+                        let data = serde_json::to_string(&event)?;
+                        stream
+                            .write_all(format!("event: stream\ndata: {}\n\n", data).as_bytes())
+                            .await?;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                _ = keepalive.tick() => {
+                    stream.write_all(b": keep-alive\n\n").await?;
+                }
+            }
+        }
+    }
+}
+
+/// Whether `Host` names this server by a loopback address or `localhost`
+fn allowed_host(host: Option<&str>, port: u16) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+    ["127.0.0.1", "localhost", "[::1]"]
+        .iter()
+        .any(|name| host.eq_ignore_ascii_case(&format!("{}:{}", name, port)))
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Status page; inline styles and script only, no external assets
+const INDEX_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>fresher</title>
+<style>
+  body { font-family: ui-monospace, Menlo, Consolas, monospace; margin: 2em; background: #fafafa; color: #222; }
+  h1 { font-size: 1.3em; margin-bottom: 0.2em; }
+  .muted { color: #777; }
+  .grid { display: grid; grid-template-columns: repeat(4, max-content); gap: 0.3em 2em; margin: 1em 0; }
+  .bar { display: inline-block; width: 12em; height: 0.8em; background: #ddd; vertical-align: middle; }
+  .bar > span { display: block; height: 100%; background: #3a3; }
+  table { border-collapse: collapse; }
+  td { padding: 0.1em 1em 0.1em 0; vertical-align: top; }
+  #log { max-height: 24em; overflow-y: auto; background: #fff; border: 1px solid #ddd; padding: 0.5em; white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<h1>fresher <span id="mode" class="muted"></span></h1>
+<div class="muted" id="started"></div>
+<div class="grid">
+  <div>Iteration</div><div id="iteration">-</div>
+  <div>Cost</div><div id="cost">-</div>
+  <div>Tasks</div><div id="tasks">-</div>
+  <div>Finished</div><div id="finished">-</div>
+</div>
+<h2>Plan</h2>
+<div id="focus" class="muted"></div>
+<table id="features"></table>
+<h2>Recent tool calls</h2>
+<table id="tools"></table>
+<h2>Live stream <span id="conn" class="muted"></span></h2>
+<div id="log"></div>
+<script>
+function text(id, value) { document.getElementById(id).textContent = value; }
+function bar(done, total) {
+  const pct = total > 0 ? Math.round(done * 100 / total) : 100;
+  return '<span class="bar"><span style="width:' + pct + '%"></span></span> ' + done + '/' + total;
+}
+function esc(s) {
+  return String(s).replace(/[&<>"]/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;' }[c]));
+}
+async function refresh() {
+  try {
+    const s = await (await fetch('/api/status')).json();
+    text('mode', s.mode);
+    text('started', 'started ' + new Date(s.started_at).toLocaleString());
+    text('iteration', s.max_iterations > 0 ? s.iteration + ' / ' + s.max_iterations : s.iteration);
+    text('cost', '$' + s.total_cost_usd.toFixed(4));
+    text('tasks', s.plan.completed_tasks + ' / ' + s.plan.total_tasks + ' complete');
+    text('finished', s.finished || 'running');
+    text('focus', s.plan.current_focus ? 'Current focus: ' + s.plan.current_focus : '');
+    document.getElementById('features').innerHTML = s.plan.features.map(f =>
+      '<tr><td>' + esc(f.name) + '</td><td>' + bar(f.completed_tasks, f.total_tasks) + '</td></tr>').join('');
+    document.getElementById('tools').innerHTML = s.recent_tool_calls.map(t =>
+      '<tr><td class="muted">' + new Date(t.at).toLocaleTimeString() + '</td><td>#' + t.iteration +
+      '</td><td>' + esc(t.name) + '</td><td>' + esc(t.detail || '') + '</td></tr>').join('');
+  } catch (e) {}
+}
+function log(line) {
+  const el = document.getElementById('log');
+  el.textContent += line + '\n';
+  if (el.textContent.length > 100000) el.textContent = el.textContent.slice(-50000);
+  el.scrollTop = el.scrollHeight;
+}
+const events = new EventSource('/events');
+events.onopen = () => text('conn', '(connected)');
+events.onerror = () => text('conn', '(disconnected)');
+events.addEventListener('stream', e => {
+  const ev = JSON.parse(e.data);
+  if (ev.type === 'assistant' && ev.message) {
+    for (const b of ev.message.content) {
+      if (b.type === 'text' && b.text) log(b.text);
+      if (b.type === 'tool_use') log('→ ' + b.name);
+    }
+    refresh();
+  } else if (ev.type === 'result') {
+    log('— iteration result' + (ev.cost_usd != null ? ' ($' + ev.cost_usd.toFixed(4) + ')' : ''));
+    refresh();
+  }
+});
+refresh();
+setInterval(refresh, 5000);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::parse_event;
+    use tokio::io::AsyncReadExt;
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", path, addr.port()).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_serve_rejects_non_loopback() {
+        let err = Dashboard::serve("0.0.0.0:0", "building", 0).await.err().unwrap();
+        assert!(err.to_string().contains("loopback"));
+    }
+
+    #[tokio::test]
+    async fn test_record_event_tracks_tools_and_cost() {
+        let dashboard = Dashboard::serve("127.0.0.1:0", "building", 5).await.unwrap();
+        dashboard.set_iteration(2);
+
+        let tool = parse_event(
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+        )
+        .unwrap();
+        let result = parse_event(r#"{"type":"result","cost_usd":0.25}"#).unwrap();
+        dashboard.record_event(&tool);
+        dashboard.record_event(&result);
+        dashboard.record_event(&result);
+
+        let status = dashboard.status();
+        assert_eq!(status.recent_tool_calls.len(), 1);
+        assert_eq!(status.recent_tool_calls[0].name, "Bash");
+        assert_eq!(status.recent_tool_calls[0].detail.as_deref(), Some("cargo test"));
+        assert_eq!(status.recent_tool_calls[0].iteration, 2);
+        assert!((status.total_cost_usd - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_http_routes() {
+        let dashboard = Dashboard::serve("127.0.0.1:0", "building", 5).await.unwrap();
+        dashboard.set_iteration(3);
+
+        let index = get(dashboard.addr(), "/").await;
+        assert!(index.starts_with("HTTP/1.1 200 OK"));
+        assert!(index.contains("EventSource"));
+
+        let status = get(dashboard.addr(), "/api/status").await;
+        assert!(status.starts_with("HTTP/1.1 200 OK"));
+        let body = status.split("\r\n\r\n").nth(1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["iteration"], 3);
+        assert_eq!(json["mode"], "building");
+
+        let missing = get(dashboard.addr(), "/nope").await;
+        assert!(missing.starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_foreign_host() {
+        let dashboard = Dashboard::serve("127.0.0.1:0", "building", 0).await.unwrap();
+        let port = dashboard.addr().port();
+
+        let rebound = format!("Host: rebind.example:{}", port);
+        for host in [rebound.as_str(), "Host: localhost:1", ""] {
+            let mut stream = TcpStream::connect(dashboard.addr()).await.unwrap();
+            let request = format!("GET /api/status HTTP/1.1\r\n{}\r\n\r\n", host);
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 403"), "{:?}: {}", host, response);
+        }
+
+        assert!(allowed_host(Some("LOCALHOST:8080"), 8080));
+        assert!(allowed_host(Some("[::1]:8080"), 8080));
+        assert!(!allowed_host(Some("127.0.0.1"), 8080));
+    }
+
+    #[tokio::test]
+    async fn test_sse_relays_stream_events() {
+        let dashboard = Dashboard::serve("127.0.0.1:0", "building", 0).await.unwrap();
+
+        let mut stream = TcpStream::connect(dashboard.addr()).await.unwrap();
+        stream
+            .write_all(format!("GET /events HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", dashboard.addr().port()).as_bytes())
+            .await
+            .unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        // Wait for the response headers so the subscription exists
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+
+        let event = parse_event(r#"{"type":"result","cost_usd":0.1}"#).unwrap();
+        dashboard.event_sender().send(event).unwrap();
+
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "event: stream\n");
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("data: {\"type\":\"result\""));
+    }
+}
@@ -147,9 +147,12 @@ pub fn run_in_container(config: &Config, args: &[String]) -> Result<i32> {
     let mut cmd = Command::new("docker");
     cmd.args(["compose", "-f", ".fresher/docker/docker-compose.yml", "run", "--rm"]);
 
-    // TTY allocation for streaming output
+    // TTY allocation for streaming output; the compose file requests a TTY,
+    // so it has to be switched off explicitly for CI and piped output
     if std::io::stdout().is_terminal() {
         cmd.arg("-t");
+    } else {
+        cmd.arg("-T");
     }
 
     // The service name
@@ -1,12 +1,20 @@
-use anyhow::{bail, Context, Result};
-use std::path::Path;
+use anyhow::Result;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
-use tokio::process::Command;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
 use tokio::time::timeout;
 
-use crate::config::Config;
+use crate::config::{Config, HookFailureAction, HookPolicy, HookProtocol};
+use crate::dashboard::PlanProgress;
+use crate::impl_plan::ProgressChange;
 use crate::state::State;
+use crate::tui;
 
 /// Hook exit codes
 pub const HOOK_CONTINUE: i32 = 0;
@@ -18,7 +26,7 @@ pub const HOOK_ABORT: i32 = 2;
 pub enum HookResult {
     /// Continue execution
     Continue,
-    /// Skip this iteration (only for next_iteration)
+    /// Skip this iteration (next_iteration, pre_agent) or ignore the failure (on_error)
     Skip,
     /// Abort the loop
     Abort,
@@ -30,80 +38,538 @@ pub enum HookResult {
     Error(String),
 }
 
-/// Run a hook script
-pub async fn run_hook(
+/// Structured reply a hook may print as its last line of output
+/// when `[hooks] protocol = "json"`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HookResponse {
+    /// Text appended to the prompt of the next iteration
+    pub append_prompt: Option<String>,
+    /// Model to use for the next iteration only
+    pub model: Option<String>,
+    /// Skip, where the hook supports it, and say why
+    pub skip_reason: Option<String>,
+    /// Key/value pairs stored in the run state (`null` removes a key)
+    pub metadata: BTreeMap<String, Value>,
+}
+
+impl HookResponse {
+    /// Fold a later command's response into this one
+    pub fn merge(&mut self, other: HookResponse) {
+        self.append_prompt = match (self.append_prompt.take(), other.append_prompt) {
+            (Some(a), Some(b)) => Some(format!("{}\n\n{}", a, b)),
+            (a, b) => a.or(b),
+        };
+        if other.model.is_some() {
+            self.model = other.model;
+        }
+        if self.skip_reason.is_none() {
+            self.skip_reason = other.skip_reason;
+        }
+        self.metadata.extend(other.metadata);
+    }
+
+    /// Record the response in `state` for the loop to pick up
+    pub fn apply(&self, state: &mut State) {
+        if let Some(text) = &self.append_prompt {
+            state.prompt_append = Some(match state.prompt_append.take() {
+                Some(existing) => format!("{}\n\n{}", existing, text),
+                None => text.clone(),
+            });
+        }
+        if let Some(model) = &self.model {
+            state.model_override = Some(model.clone());
+        }
+        for (key, value) in &self.metadata {
+            match value {
+                Value::Null => {
+                    state.metadata.remove(key);
+                }
+                Value::String(s) => {
+                    state.metadata.insert(key.clone(), s.clone());
+                }
+                other => {
+                    state.metadata.insert(key.clone(), other.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Split a hook's stdout into the text to pass through and its response
+///
+/// The response is the last non-empty line, if it is a JSON object.
+pub fn parse_response(stdout: &str) -> (String, Option<HookResponse>) {
+    let trimmed = stdout.trim_end();
+    let (rest, last) = match trimmed.rfind('\n') {
+        Some(i) => (&trimmed[..=i], &trimmed[i + 1..]),
+        None => ("", trimmed),
+    };
+
+    if !last.trim_start().starts_with('{') {
+        return (stdout.to_string(), None);
+    }
+
+    match serde_json::from_str::<HookResponse>(last) {
+        Ok(response) => (rest.to_string(), Some(response)),
+        Err(e) => {
+            tui::eprint(format!("Warning: ignoring malformed hook response: {}", e));
+            (stdout.to_string(), None)
+        }
+    }
+}
+
+/// JSON document written to a hook's stdin when `[hooks] protocol = "json"`
+pub fn hook_context(
     hook_name: &str,
     state: &State,
     config: &Config,
     project_dir: &Path,
-) -> Result<HookResult> {
-    if !config.hooks.enabled {
-        return Ok(HookResult::NotFound);
+    extra_env: &[(String, String)],
+) -> Value {
+    let plan = PlanProgress::load(
+        &project_dir.join(&config.paths.impl_dir),
+        &project_dir.join("IMPLEMENTATION_PLAN.md"),
+    );
+    let env: Map<String, Value> = extra_env
+        .iter()
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+
+    json!({
+        "hook": hook_name,
+        "mode": config.fresher.mode,
+        "project_dir": project_dir,
+        "state": state,
+        // Only the settings a hook acts on; `[notify]` webhook URLs often
+        // embed tokens
+        "config": {
+            "fresher": config.fresher,
+            "commands": config.commands,
+            "paths": config.paths,
+            "budget": config.budget,
+            "outcomes": config.outcomes,
+        },
+        "plan": plan,
+        "current_focus": plan.current_focus,
+        "last_iteration": {
+            "iteration": state.iteration,
+            "exit_code": state.last_exit_code,
+            "commit_sha": state.last_commit_sha,
+        },
+        "env": env,
+    })
+}
+
+/// One thing to execute for a hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookCommand {
+    /// `.fresher/hooks/<name>`, or an executable in `.fresher/hooks/<name>.d/`
+    Script(PathBuf),
+    /// Inline shell command from `[hooks.commands]`
+    Shell(String),
+}
+
+impl std::fmt::Display for HookCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookCommand::Script(path) => write!(f, "{}", path.display()),
+            HookCommand::Shell(command) => write!(f, "`{}`", command),
+        }
+    }
+}
+
+/// Everything to run for `hook_name`, in order: the hook script, the
+/// executables in `<name>.d/` (lexical order), then `[hooks.commands]`
+pub fn hook_commands(
+    hook_name: &str,
+    config: &Config,
+    project_dir: &Path,
+) -> Result<Vec<HookCommand>> {
+    let hooks_dir = project_dir.join(".fresher/hooks");
+    let mut commands = Vec::new();
+
+    let hook_path = hooks_dir.join(hook_name);
+    if hook_path.is_file() && is_executable(&hook_path)? {
+        commands.push(HookCommand::Script(hook_path));
     }
 
-    let hook_path = project_dir.join(".fresher/hooks").join(hook_name);
+    let hook_dir = hooks_dir.join(format!("{}.d", hook_name));
+    if hook_dir.is_dir() {
+        let mut scripts = Vec::new();
+        for entry in std::fs::read_dir(&hook_dir)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .map(|n| n.to_string_lossy().starts_with('.'))
+                .unwrap_or(true);
+            if !hidden && path.is_file() && is_executable(&path)? {
+                scripts.push(path);
+            }
+        }
+        scripts.sort();
+        commands.extend(scripts.into_iter().map(HookCommand::Script));
+    }
 
-    // Check if hook exists and is executable
-    if !hook_path.exists() {
-        return Ok(HookResult::NotFound);
+    if let Some(inline) = config.hooks.commands.get(hook_name) {
+        commands.extend(inline.iter().map(|c| HookCommand::Shell(c.clone())));
     }
 
-    // Check if executable
+    Ok(commands)
+}
+
+fn is_executable(path: &Path) -> Result<bool> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let metadata = std::fs::metadata(&hook_path)?;
-        if metadata.permissions().mode() & 0o111 == 0 {
-            return Ok(HookResult::NotFound);
+        let metadata = std::fs::metadata(path)?;
+        Ok(metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(true)
+    }
+}
+
+/// Combine the results of several commands for one hook
+///
+/// Any abort wins, then any skip, then timeouts and errors.
+pub fn combine_results(results: Vec<HookResult>) -> HookResult {
+    let rank = |r: &HookResult| match r {
+        HookResult::Abort => 5,
+        HookResult::Skip => 4,
+        HookResult::Timeout => 3,
+        HookResult::Error(_) => 2,
+        HookResult::Continue => 1,
+        HookResult::NotFound => 0,
+    };
+
+    let mut combined = HookResult::NotFound;
+    for result in results {
+        if rank(&result) > rank(&combined) {
+            combined = result;
         }
     }
+    combined
+}
+
+/// Run a hook script
+pub async fn run_hook(
+    hook_name: &str,
+    state: &State,
+    config: &Config,
+    project_dir: &Path,
+) -> Result<HookResult> {
+    Ok(run_hook_with_env(hook_name, state, config, project_dir, &[]).await?.0)
+}
+
+/// Run a hook with extra environment variables on top of the state
+///
+/// Every command from [`hook_commands`] runs, and their exit codes are
+/// combined with [`combine_results`]. Under the JSON protocol each one also
+/// gets [`hook_context`] on stdin and may answer with a [`HookResponse`].
+pub async fn run_hook_with_env(
+    hook_name: &str,
+    state: &State,
+    config: &Config,
+    project_dir: &Path,
+    extra_env: &[(String, String)],
+) -> Result<(HookResult, Option<HookResponse>)> {
+    if !config.hooks.enabled {
+        return Ok((HookResult::NotFound, None));
+    }
+
+    let commands = hook_commands(hook_name, config, project_dir)?;
+    if commands.is_empty() {
+        return Ok((HookResult::NotFound, None));
+    }
+
+    let json_protocol = config.hooks.protocol == HookProtocol::Json;
 
     // Build environment variables
     let mut env_vars = state.to_env_vars();
     env_vars.push(("FRESHER_PROJECT_DIR".to_string(), project_dir.display().to_string()));
     env_vars.push(("FRESHER_MODE".to_string(), config.fresher.mode.clone()));
+    env_vars.push(("FRESHER_HOOK_PROTOCOL".to_string(), config.hooks.protocol.to_string()));
+    env_vars.extend(extra_env.iter().cloned());
+
+    let context = json_protocol
+        .then(|| hook_context(hook_name, state, config, project_dir, extra_env).to_string());
+
+    let log_path = project_dir.join(config.paths.iteration_log(state.iteration));
+    let policy = config.hooks.policy_for(hook_name);
+
+    let mut results = Vec::new();
+    let mut merged: Option<HookResponse> = None;
+    for command in &commands {
+        let (result, response) = run_hook_command(
+            command,
+            hook_name,
+            &env_vars,
+            context.as_deref(),
+            config,
+            project_dir,
+            &log_path,
+        )
+        .await;
+        // Name the failing command when several make up the hook
+        if commands.len() > 1 {
+            match &result {
+                HookResult::Error(e) => tui::eprint(format!("Warning: {} hook {}: {}", hook_name, command, e)),
+                HookResult::Timeout => tui::eprint(format!("Warning: {} hook {} timed out", hook_name, command)),
+                _ => {}
+            }
+        }
+        results.push(apply_failure_policy(hook_name, result, policy));
+        if let Some(response) = response {
+            match &mut merged {
+                Some(merged) => merged.merge(response),
+                None => merged = Some(response),
+            }
+        }
+    }
+
+    Ok((combine_results(results), merged))
+}
+
+/// Turn a timeout or error into what the hook's policy says it counts as
+fn apply_failure_policy(hook_name: &str, result: HookResult, policy: HookPolicy) -> HookResult {
+    let (action, setting) = match &result {
+        HookResult::Timeout => (policy.on_timeout, "on_timeout"),
+        HookResult::Error(_) => (policy.on_error, "on_error"),
+        _ => return result,
+    };
+
+    match action {
+        HookFailureAction::Continue => result,
+        HookFailureAction::Skip | HookFailureAction::Abort => {
+            tui::eprint(format!(
+                "{} hook failed ({}); treating it as {} per its {} policy",
+                hook_name,
+                describe_result(&result),
+                action,
+                setting
+            ));
+            if action == HookFailureAction::Skip {
+                HookResult::Skip
+            } else {
+                HookResult::Abort
+            }
+        }
+    }
+}
 
+fn describe_result(result: &HookResult) -> String {
+    match result {
+        HookResult::Continue => "continue".to_string(),
+        HookResult::Skip => "skip".to_string(),
+        HookResult::Abort => "abort".to_string(),
+        HookResult::NotFound => "not found".to_string(),
+        HookResult::Timeout => "timed out".to_string(),
+        HookResult::Error(e) => e.clone(),
+    }
+}
+
+/// Run a single hook command with a timeout
+///
+/// Output is shown as usual and appended to `log_path`. On timeout the
+/// command's whole process group is killed.
+async fn run_hook_command(
+    command: &HookCommand,
+    hook_name: &str,
+    env_vars: &[(String, String)],
+    context: Option<&str>,
+    config: &Config,
+    project_dir: &Path,
+    log_path: &Path,
+) -> (HookResult, Option<HookResponse>) {
     // Create command
-    let mut cmd = Command::new(&hook_path);
+    let mut cmd = match command {
+        HookCommand::Script(path) => Command::new(path),
+        HookCommand::Shell(line) => {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(line);
+            cmd
+        }
+    };
     cmd.current_dir(project_dir)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .envs(env_vars);
+        .envs(env_vars.iter().cloned())
+        .stdin(if context.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // Own process group, so a timeout can take down everything the hook started
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return (HookResult::Error(format!("Failed to run hook: {}", e)), None),
+    };
+
+    if let (Some(mut stdin), Some(context)) = (child.stdin.take(), context) {
+        let context = context.to_string();
+        // Hooks that ignore stdin may exit before reading it all
+        tokio::spawn(async move {
+            let _ = stdin.write_all(context.as_bytes()).await;
+        });
+    }
+    let stdout = tokio::spawn(read_pipe(child.stdout.take()));
+    let stderr = tokio::spawn(read_pipe(child.stderr.take()));
 
     // Run with timeout
     let timeout_duration = Duration::from_secs(config.hooks.timeout as u64);
 
-    let result = timeout(timeout_duration, cmd.status()).await;
+    let status = match timeout(timeout_duration, child.wait()).await {
+        Ok(Ok(status)) => Some(status),
+        Ok(Err(e)) => return (HookResult::Error(format!("Failed to run hook: {}", e)), None),
+        Err(_) => {
+            kill_process_group(&mut child).await;
+            None
+        }
+    };
 
-    match result {
-        Ok(Ok(status)) => {
+    let stdout = collect_pipe(stdout).await;
+    let stderr = collect_pipe(stderr).await;
+
+    let (passthrough, response) = match (context, &status) {
+        (Some(_), Some(_)) => parse_response(&stdout),
+        _ => (stdout.clone(), None),
+    };
+    print!("{}", passthrough);
+    eprint!("{}", stderr);
+
+    let result = match status {
+        None => HookResult::Timeout,
+        Some(status) => {
             let code = status.code().unwrap_or(-1);
             match code {
-                HOOK_CONTINUE => Ok(HookResult::Continue),
-                HOOK_SKIP => Ok(HookResult::Skip),
-                HOOK_ABORT => Ok(HookResult::Abort),
-                _ => Ok(HookResult::Error(format!("Hook exited with code {}", code))),
+                HOOK_CONTINUE => match response.as_ref().and_then(|r| r.skip_reason.as_ref()) {
+                    Some(reason) => {
+                        tui::eprint(format!("{} hook asked to skip: {}", hook_name, reason));
+                        HookResult::Skip
+                    }
+                    None => HookResult::Continue,
+                },
+                HOOK_SKIP => HookResult::Skip,
+                HOOK_ABORT => HookResult::Abort,
+                _ => HookResult::Error(format!("Hook exited with code {}", code)),
+            }
+        }
+    };
+
+    if let Err(e) = append_hook_log(log_path, hook_name, command, &result, &stdout, &stderr) {
+        tui::eprint(format!("Warning: could not write {}: {}", log_path.display(), e));
+    }
+
+    (result, response)
+}
+
+async fn read_pipe<R: AsyncRead + Unpin>(pipe: Option<R>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf).await;
+    }
+    buf
+}
+
+/// Output read from a hook's pipe
+///
+/// Gives up after a moment if something outside the hook's process group
+/// still holds the pipe open.
+async fn collect_pipe(reader: tokio::task::JoinHandle<Vec<u8>>) -> String {
+    match timeout(Duration::from_secs(1), reader).await {
+        Ok(Ok(bytes)) => String::from_utf8_lossy(&bytes).to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Kill a child spawned with `process_group(0)` and everything it started
+pub(crate) async fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    signal_process_group(child, libc::SIGKILL);
+    // Reap the child (and kill it directly where groups are not available)
+    let _ = child.kill().await;
+}
+
+/// Send `signal` to the process group a child spawned with `process_group(0)`
+/// leads
+#[cfg(unix)]
+pub(crate) fn signal_process_group(child: &Child, signal: libc::c_int) {
+    if let Some(pid) = child.id() {
+        // SAFETY: plain kill(2) on the group the child leads
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
+        }
+    }
+}
+
+fn append_hook_log(
+    path: &Path,
+    hook_name: &str,
+    command: &HookCommand,
+    result: &HookResult,
+    stdout: &str,
+    stderr: &str,
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+    writeln!(
+        file,
+        "[{}] hook {}: {} -> {}",
+        Utc::now().to_rfc3339(),
+        hook_name,
+        command,
+        describe_result(result)
+    )?;
+    for (name, output) in [("stdout", stdout), ("stderr", stderr)] {
+        if !output.is_empty() {
+            writeln!(file, "{}:", name)?;
+            write!(file, "{}", output)?;
+            if !output.ends_with('\n') {
+                writeln!(file)?;
             }
         }
-        Ok(Err(e)) => Ok(HookResult::Error(format!("Failed to run hook: {}", e))),
-        Err(_) => Ok(HookResult::Timeout),
     }
+    Ok(())
+}
+
+/// Run a hook and record any structured response in `state`
+async fn run_steering_hook(
+    hook_name: &str,
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    extra_env: &[(String, String)],
+) -> Result<HookResult> {
+    let (result, response) =
+        run_hook_with_env(hook_name, state, config, project_dir, extra_env).await?;
+    if let Some(response) = response {
+        response.apply(state);
+    }
+    Ok(result)
 }
 
 /// Run the 'started' hook
-pub async fn run_started_hook(state: &State, config: &Config, project_dir: &Path) -> Result<bool> {
-    match run_hook("started", state, config, project_dir).await? {
+pub async fn run_started_hook(state: &mut State, config: &Config, project_dir: &Path) -> Result<bool> {
+    match run_steering_hook("started", state, config, project_dir, &[]).await? {
         HookResult::Continue | HookResult::NotFound => Ok(true),
         HookResult::Abort => {
-            eprintln!("Started hook requested abort");
+            tui::eprint("Started hook requested abort");
             Ok(false)
         }
         HookResult::Timeout => {
-            eprintln!("Warning: started hook timed out");
+            tui::eprint("Warning: started hook timed out");
             Ok(true) // Continue despite timeout
         }
         HookResult::Error(e) => {
-            eprintln!("Warning: started hook error: {}", e);
+            tui::eprint(format!("Warning: started hook error: {}", e));
             Ok(true) // Continue despite error
         }
         HookResult::Skip => Ok(true), // Skip doesn't apply to started
@@ -113,38 +579,185 @@ pub async fn run_started_hook(state: &State, config: &Config, project_dir: &Path
 /// Run the 'next_iteration' hook
 /// Returns: (should_continue, should_skip_iteration)
 pub async fn run_next_iteration_hook(
-    state: &State,
+    state: &mut State,
     config: &Config,
     project_dir: &Path,
 ) -> Result<(bool, bool)> {
-    match run_hook("next_iteration", state, config, project_dir).await? {
+    match run_steering_hook("next_iteration", state, config, project_dir, &[]).await? {
         HookResult::Continue | HookResult::NotFound => Ok((true, false)),
         HookResult::Skip => Ok((true, true)),
         HookResult::Abort => {
-            eprintln!("Next iteration hook requested abort");
+            tui::eprint("Next iteration hook requested abort");
             Ok((false, false))
         }
         HookResult::Timeout => {
-            eprintln!("Warning: next_iteration hook timed out");
+            tui::eprint("Warning: next_iteration hook timed out");
             Ok((true, false)) // Continue despite timeout
         }
         HookResult::Error(e) => {
-            eprintln!("Warning: next_iteration hook error: {}", e);
+            tui::eprint(format!("Warning: next_iteration hook error: {}", e));
             Ok((true, false)) // Continue despite error
         }
     }
 }
 
+/// Run the 'pre_agent' hook with the rendered prompt in `FRESHER_PROMPT_FILE`
+/// Returns: (should_continue, should_skip_iteration)
+pub async fn run_pre_agent_hook(
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    prompt: &str,
+) -> Result<(bool, bool)> {
+    // The prompt is too large for an environment variable; hand over a file
+    let mut prompt_file = tempfile::NamedTempFile::new()?;
+    std::io::Write::write_all(&mut prompt_file, prompt.as_bytes())?;
+    let env = [(
+        "FRESHER_PROMPT_FILE".to_string(),
+        prompt_file.path().display().to_string(),
+    )];
+
+    match run_steering_hook("pre_agent", state, config, project_dir, &env).await? {
+        HookResult::Continue | HookResult::NotFound => Ok((true, false)),
+        HookResult::Skip => Ok((true, true)),
+        HookResult::Abort => {
+            tui::eprint("Pre-agent hook requested abort");
+            Ok((false, false))
+        }
+        HookResult::Timeout => {
+            tui::eprint("Warning: pre_agent hook timed out");
+            Ok((true, false)) // Continue despite timeout
+        }
+        HookResult::Error(e) => {
+            tui::eprint(format!("Warning: pre_agent hook error: {}", e));
+            Ok((true, false)) // Continue despite error
+        }
+    }
+}
+
+/// Run the 'post_iteration' hook
+/// Returns: should_continue
+pub async fn run_post_iteration_hook(
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    exit_code: i32,
+    commits: u32,
+) -> Result<bool> {
+    let env = [
+        ("FRESHER_ITERATION_EXIT_CODE".to_string(), exit_code.to_string()),
+        ("FRESHER_ITERATION_COMMITS".to_string(), commits.to_string()),
+    ];
+
+    match run_steering_hook("post_iteration", state, config, project_dir, &env).await? {
+        HookResult::Continue | HookResult::NotFound => Ok(true),
+        HookResult::Abort => {
+            tui::eprint("Post-iteration hook requested abort");
+            Ok(false)
+        }
+        HookResult::Timeout => {
+            tui::eprint("Warning: post_iteration hook timed out");
+            Ok(true) // Continue despite timeout
+        }
+        HookResult::Error(e) => {
+            tui::eprint(format!("Warning: post_iteration hook error: {}", e));
+            Ok(true) // Continue despite error
+        }
+        HookResult::Skip => Ok(true), // Nothing left to skip
+    }
+}
+
+/// Run the 'on_error' hook before the loop stops on a failed iteration
+/// Returns: should_ignore_error (exit 1 keeps the loop going)
+pub async fn run_on_error_hook(
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    exit_code: i32,
+) -> Result<bool> {
+    let env = [("FRESHER_ITERATION_EXIT_CODE".to_string(), exit_code.to_string())];
+
+    match run_steering_hook("on_error", state, config, project_dir, &env).await? {
+        HookResult::Skip => {
+            tui::eprint("Error hook requested to ignore the failure");
+            Ok(true)
+        }
+        HookResult::Continue | HookResult::NotFound | HookResult::Abort => Ok(false),
+        HookResult::Timeout => {
+            tui::eprint("Warning: on_error hook timed out");
+            Ok(false)
+        }
+        HookResult::Error(e) => {
+            tui::eprint(format!("Warning: on_error hook error: {}", e));
+            Ok(false)
+        }
+    }
+}
+
+/// Run the 'on_task_complete' hook for a feature that had tasks checked off
+/// Returns: should_continue
+pub async fn run_task_complete_hook(
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    change: &ProgressChange,
+) -> Result<bool> {
+    run_progress_hook("on_task_complete", state, config, project_dir, change).await
+}
+
+/// Run the 'on_feature_complete' hook for a feature that became complete
+/// Returns: should_continue
+pub async fn run_feature_complete_hook(
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    change: &ProgressChange,
+) -> Result<bool> {
+    run_progress_hook("on_feature_complete", state, config, project_dir, change).await
+}
+
+async fn run_progress_hook(
+    hook_name: &str,
+    state: &mut State,
+    config: &Config,
+    project_dir: &Path,
+    change: &ProgressChange,
+) -> Result<bool> {
+    let env = [
+        ("FRESHER_FEATURE".to_string(), change.feature.clone()),
+        ("FRESHER_FEATURE_FILE".to_string(), change.file.display().to_string()),
+        ("FRESHER_TASKS_COMPLETED".to_string(), change.tasks_completed.to_string()),
+        ("FRESHER_FEATURE_COMPLETED_TASKS".to_string(), change.completed_tasks.to_string()),
+        ("FRESHER_FEATURE_TOTAL_TASKS".to_string(), change.total_tasks.to_string()),
+    ];
+
+    match run_steering_hook(hook_name, state, config, project_dir, &env).await? {
+        HookResult::Continue | HookResult::NotFound | HookResult::Skip => Ok(true),
+        HookResult::Abort => {
+            tui::eprint(format!("{} hook requested abort", hook_name));
+            Ok(false)
+        }
+        HookResult::Timeout => {
+            tui::eprint(format!("Warning: {} hook timed out", hook_name));
+            Ok(true) // Continue despite timeout
+        }
+        HookResult::Error(e) => {
+            tui::eprint(format!("Warning: {} hook error: {}", hook_name, e));
+            Ok(true) // Continue despite error
+        }
+    }
+}
+
 /// Run the 'finished' hook
-pub async fn run_finished_hook(state: &State, config: &Config, project_dir: &Path) -> Result<()> {
-    match run_hook("finished", state, config, project_dir).await? {
+pub async fn run_finished_hook(state: &mut State, config: &Config, project_dir: &Path) -> Result<()> {
+    match run_steering_hook("finished", state, config, project_dir, &[]).await? {
         HookResult::Continue | HookResult::NotFound | HookResult::Skip | HookResult::Abort => Ok(()),
         HookResult::Timeout => {
-            eprintln!("Warning: finished hook timed out");
+            tui::eprint("Warning: finished hook timed out");
             Ok(())
         }
         HookResult::Error(e) => {
-            eprintln!("Warning: finished hook error: {}", e);
+            tui::eprint(format!("Warning: finished hook error: {}", e));
             Ok(())
         }
     }
@@ -168,6 +781,7 @@ mod tests {
                 model: "sonnet".to_string(),
                 archive_completed: true,
                 single_file_threshold: 8,
+                partial_messages: false,
             },
             commands: crate::config::CommandsConfig {
                 test: String::new(),
@@ -183,6 +797,9 @@ mod tests {
             hooks: crate::config::HooksConfig {
                 enabled: hooks_enabled,
                 timeout,
+                protocol: HookProtocol::Env,
+                commands: Default::default(),
+                policy: Default::default(),
             },
             docker: crate::config::DockerConfig {
                 use_docker: false,
@@ -192,6 +809,15 @@ mod tests {
                 setup_script: None,
                 local_binary: None,
             },
+            notify: crate::config::NotifyConfig::default(),
+            output: crate::config::OutputConfig::default(),
+            budget: crate::config::BudgetConfig::default(),
+            outcomes: crate::config::OutcomeConfig::default(),
+            guardrails: crate::config::GuardrailsConfig::default(),
+            scope: crate::config::ScopeConfig::default(),
+            secrets: crate::config::SecretsConfig::default(),
+            todos: crate::config::TodosConfig::default(),
+            verify: crate::config::VerifyConfig::default(),
         }
     }
 
@@ -200,9 +826,8 @@ mod tests {
     }
 
     fn create_hook_script(dir: &TempDir, name: &str, script: &str) -> std::path::PathBuf {
-        let hooks_dir = dir.path().join(".fresher/hooks");
-        fs::create_dir_all(&hooks_dir).unwrap();
-        let hook_path = hooks_dir.join(name);
+        let hook_path = dir.path().join(".fresher/hooks").join(name);
+        fs::create_dir_all(hook_path.parent().unwrap()).unwrap();
         let mut file = fs::File::create(&hook_path).unwrap();
         file.write_all(script.as_bytes()).unwrap();
 
@@ -335,11 +960,11 @@ fi
     async fn test_run_started_hook_continue() {
         let dir = TempDir::new().unwrap();
         let config = create_test_config(true, 30);
-        let state = create_test_state();
+        let mut state = create_test_state();
 
         create_hook_script(&dir, "started", "#!/bin/bash\nexit 0\n");
 
-        let should_continue = run_started_hook(&state, &config, dir.path()).await.unwrap();
+        let should_continue = run_started_hook(&mut state, &config, dir.path()).await.unwrap();
         assert!(should_continue);
     }
 
@@ -347,11 +972,11 @@ fi
     async fn test_run_started_hook_abort() {
         let dir = TempDir::new().unwrap();
         let config = create_test_config(true, 30);
-        let state = create_test_state();
+        let mut state = create_test_state();
 
         create_hook_script(&dir, "started", "#!/bin/bash\nexit 2\n");
 
-        let should_continue = run_started_hook(&state, &config, dir.path()).await.unwrap();
+        let should_continue = run_started_hook(&mut state, &config, dir.path()).await.unwrap();
         assert!(!should_continue);
     }
 
@@ -359,11 +984,11 @@ fi
     async fn test_run_next_iteration_hook_skip() {
         let dir = TempDir::new().unwrap();
         let config = create_test_config(true, 30);
-        let state = create_test_state();
+        let mut state = create_test_state();
 
         create_hook_script(&dir, "next_iteration", "#!/bin/bash\nexit 1\n");
 
-        let (should_continue, should_skip) = run_next_iteration_hook(&state, &config, dir.path()).await.unwrap();
+        let (should_continue, should_skip) = run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
         assert!(should_continue);
         assert!(should_skip);
     }
@@ -372,11 +997,11 @@ fi
     async fn test_run_next_iteration_hook_abort() {
         let dir = TempDir::new().unwrap();
         let config = create_test_config(true, 30);
-        let state = create_test_state();
+        let mut state = create_test_state();
 
         create_hook_script(&dir, "next_iteration", "#!/bin/bash\nexit 2\n");
 
-        let (should_continue, should_skip) = run_next_iteration_hook(&state, &config, dir.path()).await.unwrap();
+        let (should_continue, should_skip) = run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
         assert!(!should_continue);
         assert!(!should_skip);
     }
@@ -385,15 +1010,326 @@ fi
     async fn test_run_finished_hook() {
         let dir = TempDir::new().unwrap();
         let config = create_test_config(true, 30);
-        let state = create_test_state();
+        let mut state = create_test_state();
 
         create_hook_script(&dir, "finished", "#!/bin/bash\nexit 0\n");
 
         // Finished hook always returns Ok(()) regardless of exit code
-        let result = run_finished_hook(&state, &config, dir.path()).await;
+        let result = run_finished_hook(&mut state, &config, dir.path()).await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_run_pre_agent_hook_sees_prompt() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        // Skip when the prompt mentions the magic word
+        let script = "#!/bin/bash\ngrep -q SKIPME \"$FRESHER_PROMPT_FILE\" && exit 1\nexit 0\n";
+        create_hook_script(&dir, "pre_agent", script);
+
+        let (should_continue, should_skip) =
+            run_pre_agent_hook(&mut state, &config, dir.path(), "please SKIPME").await.unwrap();
+        assert!(should_continue);
+        assert!(should_skip);
+
+        let (should_continue, should_skip) =
+            run_pre_agent_hook(&mut state, &config, dir.path(), "build things").await.unwrap();
+        assert!(should_continue);
+        assert!(!should_skip);
+    }
+
+    #[tokio::test]
+    async fn test_run_post_iteration_hook_env() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        let script = r#"#!/bin/bash
+if [ "$FRESHER_ITERATION_EXIT_CODE" = "0" ] && [ "$FRESHER_ITERATION_COMMITS" = "0" ]; then
+    exit 2
+fi
+exit 0
+"#;
+        create_hook_script(&dir, "post_iteration", script);
+
+        assert!(!run_post_iteration_hook(&mut state, &config, dir.path(), 0, 0).await.unwrap());
+        assert!(run_post_iteration_hook(&mut state, &config, dir.path(), 0, 2).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_on_error_hook() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        // No hook: the error stops the loop
+        assert!(!run_on_error_hook(&mut state, &config, dir.path(), 1).await.unwrap());
+
+        // Ignore transient failures (exit code 1), stop on anything else
+        let script = "#!/bin/bash\n[ \"$FRESHER_ITERATION_EXIT_CODE\" = \"1\" ] && exit 1\nexit 0\n";
+        create_hook_script(&dir, "on_error", script);
+        assert!(run_on_error_hook(&mut state, &config, dir.path(), 1).await.unwrap());
+        assert!(!run_on_error_hook(&mut state, &config, dir.path(), 137).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_feature_complete_hook_env() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        let script = r#"#!/bin/bash
+if [ "$FRESHER_FEATURE" = "auth" ] && [ "$FRESHER_TASKS_COMPLETED" = "2" ] && [ "$FRESHER_FEATURE_TOTAL_TASKS" = "3" ]; then
+    exit 2
+fi
+exit 0
+"#;
+        create_hook_script(&dir, "on_feature_complete", script);
+
+        let change = ProgressChange {
+            feature: "auth".to_string(),
+            file: "impl/auth.md".into(),
+            tasks_completed: 2,
+            completed_tasks: 3,
+            total_tasks: 3,
+            feature_completed: true,
+        };
+        let should_continue = run_feature_complete_hook(&mut state, &config, dir.path(), &change).await.unwrap();
+        assert!(!should_continue);
+
+        // Only the named hook runs
+        let should_continue = run_task_complete_hook(&mut state, &config, dir.path(), &change).await.unwrap();
+        assert!(should_continue);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let stdout = "checking CI...\n{\"model\": \"opus\", \"metadata\": {\"ci\": \"green\"}}\n";
+        let (passthrough, response) = parse_response(stdout);
+        assert_eq!(passthrough, "checking CI...\n");
+        let response = response.unwrap();
+        assert_eq!(response.model.as_deref(), Some("opus"));
+        assert_eq!(response.metadata["ci"], "green");
+
+        // Plain output is passed through untouched
+        let (passthrough, response) = parse_response("all good\n");
+        assert_eq!(passthrough, "all good\n");
+        assert!(response.is_none());
+    }
+
+    #[test]
+    fn test_hook_context_leaves_out_notify() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 30);
+        config.notify.webhooks = vec!["https://hooks.slack.com/services/T0/B0/secret".to_string()];
+
+        let context = hook_context("started", &State::new(), &config, dir.path(), &[]);
+        assert_eq!(context["config"]["fresher"]["mode"], config.fresher.mode.as_str());
+        assert!(context["config"]["paths"].is_object());
+        assert!(context["config"].get("notify").is_none());
+        assert!(!context.to_string().contains("hooks.slack.com"));
+    }
+
+    #[tokio::test]
+    async fn test_json_protocol_steers_state() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 30);
+        config.hooks.protocol = HookProtocol::Json;
+        let mut state = create_test_state();
+        state.iteration = 2;
+
+        let script = r#"#!/bin/bash
+context=$(cat)
+echo "deciding..."
+if echo "$context" | grep -q '"hook":"next_iteration"' && echo "$context" | grep -q '"iteration":2'; then
+    echo '{"append_prompt": "Fix the flaky test first.", "model": "opus", "metadata": {"attempt": 2}}'
+fi
+exit 0
+"#;
+        create_hook_script(&dir, "next_iteration", script);
+
+        let (should_continue, should_skip) =
+            run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+        assert!(should_continue);
+        assert!(!should_skip);
+        assert_eq!(state.prompt_append.as_deref(), Some("Fix the flaky test first."));
+        assert_eq!(state.model_override.as_deref(), Some("opus"));
+        assert_eq!(state.metadata["attempt"], "2");
+    }
+
+    #[tokio::test]
+    async fn test_json_protocol_skip_reason() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 30);
+        config.hooks.protocol = HookProtocol::Json;
+        let mut state = create_test_state();
+
+        let script = "#!/bin/bash\necho '{\"skip_reason\": \"waiting for CI\"}'\nexit 0\n";
+        create_hook_script(&dir, "next_iteration", script);
+
+        let (should_continue, should_skip) =
+            run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+        assert!(should_continue);
+        assert!(should_skip);
+    }
+
+    #[tokio::test]
+    async fn test_env_protocol_ignores_responses() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        create_hook_script(&dir, "started", "#!/bin/bash\necho '{\"model\": \"opus\"}'\nexit 0\n");
+
+        assert!(run_started_hook(&mut state, &config, dir.path()).await.unwrap());
+        assert!(state.model_override.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hook_directory_runs_in_order() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 30);
+        config.hooks.commands.insert(
+            "started".to_string(),
+            crate::config::HookCommands::One("echo inline >> order.txt".to_string()),
+        );
+        let mut state = create_test_state();
+
+        create_hook_script(&dir, "started", "#!/bin/bash\necho main >> order.txt\n");
+        create_hook_script(&dir, "started.d/20-team", "#!/bin/bash\necho team >> order.txt\n");
+        create_hook_script(&dir, "started.d/10-org", "#!/bin/bash\necho org >> order.txt\n");
+        fs::write(dir.path().join(".fresher/hooks/started.d/README"), "not executable").unwrap();
+
+        let commands = hook_commands("started", &config, dir.path()).unwrap();
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[3], HookCommand::Shell("echo inline >> order.txt".to_string()));
+
+        assert!(run_started_hook(&mut state, &config, dir.path()).await.unwrap());
+        let order = fs::read_to_string(dir.path().join("order.txt")).unwrap();
+        assert_eq!(order, "main\norg\nteam\ninline\n");
+    }
+
+    #[tokio::test]
+    async fn test_hook_directory_combined_verdict() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+
+        create_hook_script(&dir, "next_iteration.d/a", "#!/bin/bash\nexit 0\n");
+        create_hook_script(&dir, "next_iteration.d/b", "#!/bin/bash\nexit 1\n");
+        let (should_continue, should_skip) =
+            run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+        assert!(should_continue);
+        assert!(should_skip);
+
+        // An abort anywhere wins over skips
+        create_hook_script(&dir, "next_iteration.d/c", "#!/bin/bash\nexit 2\n");
+        let (should_continue, _) =
+            run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+        assert!(!should_continue);
+    }
+
+    #[test]
+    fn test_combine_results() {
+        let combined = combine_results(vec![
+            HookResult::Continue,
+            HookResult::Error("boom".to_string()),
+            HookResult::Continue,
+        ]);
+        assert!(matches!(combined, HookResult::Error(_)));
+        assert!(matches!(combine_results(vec![]), HookResult::NotFound));
+        assert!(matches!(
+            combine_results(vec![HookResult::Abort, HookResult::Skip, HookResult::Timeout]),
+            HookResult::Abort
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_policy_abort() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 1);
+        let mut state = create_test_state();
+
+        create_hook_script(&dir, "started", "#!/bin/bash\nsleep 10\nexit 0\n");
+
+        // Fail-open by default
+        assert!(run_started_hook(&mut state, &config, dir.path()).await.unwrap());
+
+        config.hooks.policy.insert(
+            "started".to_string(),
+            HookPolicy {
+                on_timeout: HookFailureAction::Abort,
+                ..Default::default()
+            },
+        );
+        assert!(!run_started_hook(&mut state, &config, dir.path()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_error_policy_skip() {
+        let dir = TempDir::new().unwrap();
+        let mut config = create_test_config(true, 30);
+        config.hooks.policy.insert(
+            "next_iteration".to_string(),
+            HookPolicy {
+                on_error: HookFailureAction::Skip,
+                ..Default::default()
+            },
+        );
+        let mut state = create_test_state();
+
+        create_hook_script(&dir, "next_iteration", "#!/bin/bash\nexit 7\n");
+
+        let (should_continue, should_skip) =
+            run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+        assert!(should_continue);
+        assert!(should_skip);
+    }
+
+    #[tokio::test]
+    async fn test_hook_output_captured_in_iteration_log() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 30);
+        let mut state = create_test_state();
+        state.iteration = 3;
+
+        let script = "#!/bin/bash\necho to-stdout\necho to-stderr >&2\nexit 1\n";
+        create_hook_script(&dir, "next_iteration", script);
+
+        run_next_iteration_hook(&mut state, &config, dir.path()).await.unwrap();
+
+        let log = fs::read_to_string(dir.path().join(".fresher/logs/iteration-3.log")).unwrap();
+        assert!(log.contains("hook next_iteration:"));
+        assert!(log.contains("-> skip"));
+        assert!(log.contains("stdout:\nto-stdout\n"));
+        assert!(log.contains("stderr:\nto-stderr\n"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_kills_process_group() {
+        let dir = TempDir::new().unwrap();
+        let config = create_test_config(true, 1);
+        let state = create_test_state();
+
+        // The grandchild would outlive a plain kill of the hook process
+        let script = "#!/bin/bash\nsleep 60 &\necho $! > grandchild.pid\nwait\n";
+        create_hook_script(&dir, "test_hook", script);
+
+        let result = run_hook("test_hook", &state, &config, dir.path()).await.unwrap();
+        assert!(matches!(result, HookResult::Timeout));
+
+        let pid = fs::read_to_string(dir.path().join("grandchild.pid")).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // Gone, or a zombie waiting to be reaped
+        let alive = fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+            .map(|stat| !stat.contains(") Z "))
+            .unwrap_or(false);
+        assert!(!alive);
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn test_run_hook_not_executable() {
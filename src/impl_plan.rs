@@ -160,6 +160,78 @@ impl ImplIndex {
             .filter(|f| f.pending_tasks > 0)
             .min_by_key(|f| f.pending_tasks)
     }
+
+    /// Task progress made on each feature since `before` was loaded
+    ///
+    /// A feature that was moved to `.archive/` in between counts as complete.
+    pub fn progress_since(&self, before: &ImplIndex) -> Vec<ProgressChange> {
+        let mut changes = Vec::new();
+
+        for old in &before.features {
+            let current = match self.features.iter().find(|f| f.name == old.name) {
+                Some(feature) => feature.clone(),
+                None => {
+                    let archived = self.impl_dir.join(".archive").join(format!("{}.md", old.name));
+                    match parse_feature_file(&archived) {
+                        Ok(feature) => FeatureStatus {
+                            status: FeatureState::Archived,
+                            ..feature
+                        },
+                        Err(_) => continue,
+                    }
+                }
+            };
+
+            let change = ProgressChange::between(Some(old), &current);
+            if change.tasks_completed > 0 || change.feature_completed {
+                changes.push(change);
+            }
+        }
+
+        // Features created (and worked on) since the last load
+        for feature in &self.features {
+            if !before.features.iter().any(|f| f.name == feature.name) {
+                let change = ProgressChange::between(None, feature);
+                if change.tasks_completed > 0 || change.feature_completed {
+                    changes.push(change);
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// Progress on one feature between two loads of the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressChange {
+    pub feature: String,
+    pub file: PathBuf,
+    /// Tasks checked off since the earlier load
+    pub tasks_completed: usize,
+    pub completed_tasks: usize,
+    pub total_tasks: usize,
+    /// The feature became complete (or was archived) since the earlier load
+    pub feature_completed: bool,
+}
+
+impl ProgressChange {
+    fn between(old: Option<&FeatureStatus>, new: &FeatureStatus) -> Self {
+        let is_done = |f: &FeatureStatus| {
+            matches!(f.status, FeatureState::Complete | FeatureState::Archived)
+        };
+
+        ProgressChange {
+            feature: new.name.clone(),
+            file: new.file.clone(),
+            tasks_completed: new
+                .completed_tasks
+                .saturating_sub(old.map(|f| f.completed_tasks).unwrap_or(0)),
+            completed_tasks: new.completed_tasks,
+            total_tasks: new.total_tasks,
+            feature_completed: is_done(new) && !old.is_some_and(is_done),
+        }
+    }
 }
 
 /// Parse a feature file and extract task status
@@ -617,14 +689,14 @@ fn generate_readme(
         })
         .map(|s| s.to_string());
 
-    content.push_str(&format!(
+    content.push_str(
         r#"
 ---
 
 ## Current Focus
 
-"#
-    ));
+"#,
+    );
 
     if let Some(focus) = &current_focus {
         content.push_str(&format!("**Active:** [{}.md](./{}.md)\n\n", focus, focus));
@@ -874,6 +946,42 @@ mod tests {
         assert_eq!(focus.name, "small");
     }
 
+    #[test]
+    fn test_progress_since() {
+        let dir = create_test_impl_dir();
+        let impl_dir = dir.path().join("impl");
+
+        write_file(&impl_dir, "README.md", "# Plan");
+        write_file(&impl_dir, "auth.md", "- [ ] Task 1\n- [ ] Task 2\n");
+        write_file(&impl_dir, "api.md", "- [ ] Task A\n");
+        write_file(&impl_dir, "docs.md", "- [ ] Task X\n");
+        let before = ImplIndex::load(&impl_dir).unwrap();
+
+        write_file(&impl_dir, "auth.md", "- [x] Task 1\n- [ ] Task 2\n");
+        write_file(&impl_dir, "api.md", "- [x] Task A\n");
+        write_file(&impl_dir, "docs.md", "- [x] Task X\n");
+        archive_feature(&impl_dir, "docs").unwrap();
+        let after = ImplIndex::load(&impl_dir).unwrap();
+
+        let changes = after.progress_since(&before);
+        assert_eq!(changes.len(), 3);
+
+        let api = changes.iter().find(|c| c.feature == "api").unwrap();
+        assert_eq!(api.tasks_completed, 1);
+        assert!(api.feature_completed);
+
+        let auth = changes.iter().find(|c| c.feature == "auth").unwrap();
+        assert_eq!(auth.tasks_completed, 1);
+        assert!(!auth.feature_completed);
+
+        let docs = changes.iter().find(|c| c.feature == "docs").unwrap();
+        assert!(docs.feature_completed);
+        assert_eq!(docs.file, impl_dir.join(".archive/docs.md"));
+
+        // Nothing changed since the last load
+        assert!(after.progress_since(&after).is_empty());
+    }
+
     #[test]
     fn test_archive_feature() {
         let dir = create_test_impl_dir();
@@ -5,11 +5,22 @@
 pub mod cli;
 pub mod commands;
 pub mod config;
+pub mod dashboard;
 pub mod docker;
+pub mod guardrails;
 pub mod hooks;
 pub mod impl_plan;
+pub mod lock;
+pub mod notify;
+pub mod scope;
+pub mod secrets;
+pub mod spec_drift;
 pub mod state;
+pub mod suggest;
 pub mod streaming;
 pub mod templates;
+pub mod todos;
+pub mod tui;
 pub mod upgrade;
 pub mod verify;
+pub mod watch;
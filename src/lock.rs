@@ -0,0 +1,333 @@
+//! Single-run lock for a project
+//!
+//! `fresher plan` and `fresher build` hold `.fresher/.lock` for the lifetime of
+//! the loop so that two runs (on the host, in Docker, or one of each) never work
+//! on the same repository at once. The lock lives in the project directory, which
+//! is the workspace mount shared with the container, so both sides see it.
+//!
+//! PIDs can only be checked on the host that wrote the lock, so the holder also
+//! refreshes a heartbeat timestamp; a lock whose heartbeat has gone quiet is stale.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Lock file location, relative to the project directory
+pub const LOCK_FILE: &str = ".fresher/.lock";
+
+/// How often the lock holder refreshes its heartbeat
+pub const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+/// Heartbeat age after which a lock is considered stale
+pub const STALE_AFTER_SECS: i64 = 120;
+
+/// Contents of `.fresher/.lock`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+    pub mode: String,
+    pub started_at: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+    #[serde(default)]
+    pub in_docker: bool,
+}
+
+impl LockInfo {
+    /// Describe this process as a lock holder
+    pub fn current(mode: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            pid: std::process::id(),
+            hostname: current_hostname(),
+            mode: mode.to_string(),
+            started_at: now,
+            heartbeat_at: now,
+            in_docker: crate::docker::is_inside_container(),
+        }
+    }
+
+    /// Read lock info from a lock file
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Whether this lock was written by the current process
+    pub fn is_ours(&self) -> bool {
+        self.pid == std::process::id() && self.hostname == current_hostname()
+    }
+
+    /// Whether the holder of this lock is gone
+    ///
+    /// A lock from this host is stale when its PID is no longer running. Locks
+    /// from another host (e.g. the other side of the Docker boundary) are stale
+    /// once the heartbeat is older than [`STALE_AFTER_SECS`].
+    pub fn is_stale(&self) -> bool {
+        if self.hostname == current_hostname() && !is_process_alive(self.pid) {
+            return true;
+        }
+        (Utc::now() - self.heartbeat_at).num_seconds() > STALE_AFTER_SECS
+    }
+
+    fn write_to(&self, file: &mut fs::File) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize lock")?;
+        file.write_all(content.as_bytes())
+            .context("Failed to write lock file")?;
+        Ok(())
+    }
+}
+
+/// Held run lock; removes the lock file when dropped
+#[derive(Debug)]
+pub struct RunLock {
+    path: PathBuf,
+    heartbeat: Option<JoinHandle<()>>,
+}
+
+impl RunLock {
+    /// Acquire the run lock for `project_dir`.
+    ///
+    /// Stale locks are replaced automatically. With `force`, any existing lock is
+    /// removed first. Otherwise an active lock is an error naming its holder.
+    pub fn acquire(project_dir: &Path, mode: &str, force: bool) -> Result<Self> {
+        let path = project_dir.join(LOCK_FILE);
+
+        if force && path.exists() {
+            fs::remove_file(&path).context("Failed to remove existing lock")?;
+        }
+
+        let info = LockInfo::current(mode);
+
+        // Two attempts: the second one follows removal of a stale lock
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    info.write_to(&mut file)?;
+                    return Ok(Self {
+                        path,
+                        heartbeat: None,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    // An unreadable lock is either being written right now or
+                    // left behind half-written; only the latter may be replaced
+                    let existing = match LockInfo::read(&path) {
+                        Ok(existing) => existing,
+                        Err(_) if lock_file_age_secs(&path) > STALE_AFTER_SECS as u64 => {
+                            fs::remove_file(&path).ok();
+                            continue;
+                        }
+                        Err(_) => bail!(
+                            "{} exists but is unreadable; another run may be starting.\n\
+                             If no other run is active, re-run with --force-unlock.",
+                            path.display()
+                        ),
+                    };
+
+                    if !existing.is_stale() {
+                        bail!(
+                            "Another fresher run is active in this project\n  \
+                             Mode:    {}\n  \
+                             PID:     {} on {}{}\n  \
+                             Started: {}\n\
+                             If that run is no longer alive, re-run with --force-unlock.",
+                            existing.mode,
+                            existing.pid,
+                            existing.hostname,
+                            if existing.in_docker { " (docker)" } else { "" },
+                            existing.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        );
+                    }
+
+                    fs::remove_file(&path).context("Failed to remove stale lock")?;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        }
+
+        bail!("Failed to acquire {}", path.display())
+    }
+
+    /// Refresh the heartbeat periodically until the lock is released
+    pub fn start_heartbeat(&mut self) {
+        let path = self.path.clone();
+        self.heartbeat = Some(tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                touch_heartbeat(&path);
+            }
+        }));
+    }
+
+    /// Path of the held lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        if let Some(handle) = self.heartbeat.take() {
+            handle.abort();
+        }
+        // Only remove the file if it still belongs to us (it may have been
+        // force-unlocked and re-acquired by another run)
+        if LockInfo::read(&self.path).map(|i| i.is_ours()).unwrap_or(false) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+fn touch_heartbeat(path: &Path) {
+    if let Ok(mut info) = LockInfo::read(path) {
+        if info.is_ours() {
+            info.heartbeat_at = Utc::now();
+            if let Ok(content) = toml::to_string_pretty(&info) {
+                fs::write(path, content).ok();
+            }
+        }
+    }
+}
+
+fn lock_file_age_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Best-effort hostname of this machine or container
+pub fn current_hostname() -> String {
+    if let Ok(name) = fs::read_to_string("/proc/sys/kernel/hostname") {
+        return name.trim().to_string();
+    }
+    if let Ok(name) = std::env::var("HOSTNAME") {
+        if !name.is_empty() {
+            return name;
+        }
+    }
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Check whether a process with this PID is running on this host
+fn is_process_alive(pid: u32) -> bool {
+    if Path::new("/proc/self").exists() {
+        return Path::new(&format!("/proc/{}", pid)).exists();
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup_project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".fresher")).unwrap();
+        dir
+    }
+
+    fn write_lock(dir: &TempDir, info: &LockInfo) {
+        let content = toml::to_string_pretty(info).unwrap();
+        fs::write(dir.path().join(LOCK_FILE), content).unwrap();
+    }
+
+    #[test]
+    fn test_acquire_and_release() {
+        let dir = setup_project();
+        let lock_path = dir.path().join(LOCK_FILE);
+
+        {
+            let lock = RunLock::acquire(dir.path(), "building", false).unwrap();
+            assert_eq!(lock.path(), lock_path);
+            let info = LockInfo::read(&lock_path).unwrap();
+            assert_eq!(info.mode, "building");
+            assert_eq!(info.pid, std::process::id());
+            assert!(info.is_ours());
+        }
+
+        assert!(!lock_path.exists(), "lock should be removed on drop");
+    }
+
+    #[test]
+    fn test_acquire_fails_when_held() {
+        let dir = setup_project();
+        let _lock = RunLock::acquire(dir.path(), "building", false).unwrap();
+
+        let err = RunLock::acquire(dir.path(), "planning", false).unwrap_err();
+        assert!(err.to_string().contains("--force-unlock"));
+    }
+
+    #[test]
+    fn test_acquire_replaces_dead_pid_lock() {
+        let dir = setup_project();
+        let mut info = LockInfo::current("building");
+        info.pid = u32::MAX - 1;
+        write_lock(&dir, &info);
+
+        let _lock = RunLock::acquire(dir.path(), "planning", false).unwrap();
+        let info = LockInfo::read(&dir.path().join(LOCK_FILE)).unwrap();
+        assert_eq!(info.mode, "planning");
+    }
+
+    #[test]
+    fn test_foreign_host_lock_stale_by_heartbeat() {
+        let mut info = LockInfo::current("building");
+        info.hostname = "some-container".to_string();
+        assert!(!info.is_stale(), "fresh heartbeat from another host is live");
+
+        info.heartbeat_at = Utc::now() - chrono::Duration::seconds(STALE_AFTER_SECS + 10);
+        assert!(info.is_stale());
+    }
+
+    #[test]
+    fn test_force_unlock() {
+        let dir = setup_project();
+        let mut info = LockInfo::current("building");
+        info.hostname = "some-container".to_string();
+        write_lock(&dir, &info);
+
+        assert!(RunLock::acquire(dir.path(), "building", false).is_err());
+        let _lock = RunLock::acquire(dir.path(), "building", true).unwrap();
+    }
+
+    #[test]
+    fn test_drop_keeps_foreign_lock() {
+        let dir = setup_project();
+        let lock = RunLock::acquire(dir.path(), "building", false).unwrap();
+
+        // Someone force-unlocked and took over
+        let mut info = LockInfo::current("planning");
+        info.hostname = "other-host".to_string();
+        write_lock(&dir, &info);
+
+        drop(lock);
+        assert!(dir.path().join(LOCK_FILE).exists());
+    }
+}
@@ -2,16 +2,80 @@ use anyhow::Result;
 use clap::Parser;
 use fresher::cli::{Cli, Commands, DockerCommands};
 use fresher::commands;
+use fresher::config::VerifyConfig;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let verbosity = cli.verbosity();
 
     match cli.command {
         Commands::Init { force } => commands::init::run(force).await,
-        Commands::Plan { max_iterations } => commands::plan::run(max_iterations).await,
-        Commands::Build { max_iterations } => commands::build::run(max_iterations).await,
-        Commands::Verify { json, plan_file } => commands::verify::run(json, plan_file).await,
+        Commands::Plan {
+            max_iterations,
+            ci,
+            force_unlock,
+        } => {
+            let finish = commands::plan::run(max_iterations, ci, force_unlock, verbosity).await?;
+            std::process::exit(finish.exit_code())
+        }
+        Commands::Build {
+            max_iterations,
+            ci,
+            force_unlock,
+            strict,
+            serve,
+            tui,
+        } => {
+            let finish = commands::build::run(
+                max_iterations,
+                ci,
+                force_unlock,
+                strict,
+                serve,
+                tui,
+                verbosity,
+            )
+            .await?;
+            std::process::exit(finish.exit_code())
+        }
+        Commands::Watch {
+            max_iterations,
+            src,
+            build,
+            debounce_ms,
+            poll,
+        } => {
+            commands::watch::run(max_iterations, src, build, debounce_ms, poll, verbosity).await
+        }
+        Commands::Verify {
+            json,
+            plan_file,
+            strict,
+            min_coverage,
+            max_orphan_tasks,
+            no_dangling_refs,
+            require_priority,
+            matrix,
+            suggest_refs,
+            min_confidence,
+            apply,
+        } => {
+            let suggest = suggest_refs.then_some(commands::verify::SuggestOptions {
+                min_confidence,
+                apply,
+            });
+            let thresholds = VerifyConfig {
+                min_coverage,
+                max_orphan_tasks,
+                no_dangling_refs,
+                require_priority,
+            };
+            if !commands::verify::run(json, plan_file, strict, thresholds, matrix, suggest).await? {
+                std::process::exit(commands::verify::THRESHOLD_EXIT_CODE)
+            }
+            Ok(())
+        }
         Commands::Upgrade { check } => commands::upgrade::run(check).await,
         Commands::Version => commands::version::run(),
         Commands::Docker { command } => match command {
@@ -0,0 +1,337 @@
+//! Built-in webhook notifications for run lifecycle events
+//!
+//! Replaces the usual `curl` snippet in `.fresher/hooks/finished`: payloads are
+//! built from the same data hooks receive (`State::to_env_vars`) plus plan
+//! progress, and POSTed to every URL in `[notify] webhooks` with retries.
+//! Delivery problems are reported as warnings and never fail the run.
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use colored::*;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{Config, NotifyConfig, NotifyFormat};
+use crate::dashboard::PlanProgress;
+use crate::state::{FinishType, State};
+use crate::tui;
+
+/// Base delay before the first retry; doubles on every attempt
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Longest wait between delivery attempts
+const RETRY_MAX_DELAY_MS: u64 = 30_000;
+
+/// Lifecycle event that can trigger a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// An iteration completed
+    Iteration,
+    /// The loop ended
+    Finished(FinishType),
+}
+
+impl NotifyEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotifyEvent::Iteration => "iteration",
+            NotifyEvent::Finished(_) => "finished",
+        }
+    }
+
+    /// Whether `[notify] events` selects this event.
+    ///
+    /// `"finished"` matches every finish; a finish type name (`"error"`,
+    /// `"stalled"`, `"budget"`, ...) matches only that finish.
+    pub fn is_selected(&self, events: &[String]) -> bool {
+        events.iter().any(|e| match self {
+            NotifyEvent::Iteration => e == "iteration",
+            NotifyEvent::Finished(finish) => e == "finished" || *e == finish.to_string(),
+        })
+    }
+}
+
+/// Build the webhook payload for an event
+pub fn build_payload(
+    event: NotifyEvent,
+    state: &State,
+    mode: &str,
+    plan: &PlanProgress,
+    format: NotifyFormat,
+) -> Value {
+    let project = std::env::current_dir()
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default();
+
+    if format == NotifyFormat::Slack {
+        let headline = match event {
+            NotifyEvent::Iteration => {
+                format!("Fresher {} iteration {} done", mode, state.iteration)
+            }
+            NotifyEvent::Finished(finish) => format!("Fresher {} finished: {}", mode, finish),
+        };
+        let text = format!(
+            "*{}* ({})\nIterations: {} · Commits: {} · Duration: {}s · Plan: {}/{} tasks",
+            headline,
+            project,
+            state.iteration,
+            state.total_commits,
+            state.duration,
+            plan.completed_tasks,
+            plan.total_tasks
+        );
+        return json!({ "text": text });
+    }
+
+    let env: Map<String, Value> = state
+        .to_env_vars()
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+
+    json!({
+        "event": event.name(),
+        "finish_type": match event {
+            NotifyEvent::Finished(finish) => Value::String(finish.to_string()),
+            NotifyEvent::Iteration => Value::Null,
+        },
+        "mode": mode,
+        "project": project,
+        "timestamp": Utc::now().to_rfc3339(),
+        "state": env,
+        "plan": {
+            "total_tasks": plan.total_tasks,
+            "completed_tasks": plan.completed_tasks,
+            "pending_tasks": plan.pending_tasks,
+            "current_focus": plan.current_focus,
+        },
+    })
+}
+
+/// Exponential backoff before retry `attempt` (0-based), capped
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY_MS.saturating_mul(1 << attempt.min(16));
+    Duration::from_millis(delay.min(RETRY_MAX_DELAY_MS))
+}
+
+/// POST `payload` to `url`, retrying network errors, 429 and 5xx responses
+pub async fn deliver(url: &str, payload: &Value, config: &NotifyConfig) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout as u64))
+        .build()?;
+
+    let mut attempt = 0;
+    loop {
+        let error = match client.post(url).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if !(status.is_server_error() || status.as_u16() == 429) {
+                    bail!("{} responded with {}", url, status);
+                }
+                format!("{} responded with {}", url, status)
+            }
+            Err(e) => format!("{}: {}", url, e),
+        };
+
+        if attempt >= config.retries {
+            bail!("{} (after {} attempts)", error, attempt + 1);
+        }
+        tokio::time::sleep(retry_delay(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Send `event` to all configured webhooks, if it is selected
+pub async fn send(config: &Config, state: &State, event: NotifyEvent) {
+    if config.notify.webhooks.is_empty() || !event.is_selected(&config.notify.events) {
+        return;
+    }
+
+    let plan = PlanProgress::load(
+        Path::new(&config.paths.impl_dir),
+        Path::new("IMPLEMENTATION_PLAN.md"),
+    );
+    let payload = build_payload(
+        event,
+        state,
+        &config.fresher.mode,
+        &plan,
+        config.notify.format,
+    );
+
+    for url in &config.notify.webhooks {
+        if let Err(e) = deliver(url, &payload, &config.notify).await {
+            tui::eprint(format!("{} notification failed: {}", "Warning:".yellow(), e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Local webhook receiver answering with `statuses` in turn (then 200)
+    async fn test_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let bodies = received.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                // Read until the full body (per Content-Length) has arrived
+                loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf);
+                    if let Some(split) = text.find("\r\n\r\n") {
+                        let length = text[..split]
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if buf.len() >= split + 4 + length {
+                            let body = &buf[split + 4..split + 4 + length];
+                            bodies
+                                .lock()
+                                .unwrap()
+                                .push(serde_json::from_slice(body).unwrap());
+                            break;
+                        }
+                    }
+                }
+
+                let status = statuses.next().unwrap_or(200);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, received)
+    }
+
+    fn finished_state() -> State {
+        let mut state = State::new();
+        state.iteration = 4;
+        state.total_commits = 3;
+        state.set_finish(FinishType::Error);
+        state
+    }
+
+    #[test]
+    fn test_event_selection() {
+        let events = vec!["error".to_string(), "iteration".to_string()];
+        assert!(NotifyEvent::Iteration.is_selected(&events));
+        assert!(NotifyEvent::Finished(FinishType::Error).is_selected(&events));
+        assert!(!NotifyEvent::Finished(FinishType::Complete).is_selected(&events));
+
+        let events = vec!["finished".to_string()];
+        assert!(NotifyEvent::Finished(FinishType::Complete).is_selected(&events));
+        assert!(!NotifyEvent::Iteration.is_selected(&events));
+    }
+
+    #[test]
+    fn test_json_payload() {
+        let plan = PlanProgress {
+            total_tasks: 10,
+            completed_tasks: 6,
+            pending_tasks: 4,
+            ..Default::default()
+        };
+        let payload = build_payload(
+            NotifyEvent::Finished(FinishType::Error),
+            &finished_state(),
+            "building",
+            &plan,
+            NotifyFormat::Json,
+        );
+
+        assert_eq!(payload["event"], "finished");
+        assert_eq!(payload["finish_type"], "error");
+        assert_eq!(payload["mode"], "building");
+        assert_eq!(payload["state"]["FRESHER_ITERATION"], "4");
+        assert_eq!(payload["state"]["FRESHER_FINISH_TYPE"], "error");
+        assert_eq!(payload["plan"]["completed_tasks"], 6);
+    }
+
+    #[test]
+    fn test_slack_payload() {
+        let payload = build_payload(
+            NotifyEvent::Finished(FinishType::Error),
+            &finished_state(),
+            "building",
+            &PlanProgress::default(),
+            NotifyFormat::Slack,
+        );
+
+        let text = payload["text"].as_str().unwrap();
+        assert!(text.contains("Fresher building finished: error"));
+        assert!(text.contains("Iterations: 4"));
+        assert_eq!(payload.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_server_errors() {
+        let (url, received) = test_server(vec![500, 503]).await;
+        let config = NotifyConfig {
+            retries: 2,
+            ..Default::default()
+        };
+
+        deliver(&url, &json!({"event": "finished"}), &config)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        assert_eq!(received[2]["event"], "finished");
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        assert_eq!(retry_delay(0), Duration::from_millis(500));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(10), Duration::from_secs(30));
+        assert_eq!(retry_delay(u32::MAX), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up() {
+        let (url, received) = test_server(vec![500, 500]).await;
+        let config = NotifyConfig {
+            retries: 1,
+            ..Default::default()
+        };
+
+        let err = deliver(&url, &json!({}), &config).await.unwrap_err();
+        assert!(err.to_string().contains("after 2 attempts"));
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_does_not_retry_client_errors() {
+        let (url, received) = test_server(vec![404]).await;
+        let config = NotifyConfig::default();
+
+        assert!(deliver(&url, &json!({}), &config).await.is_err());
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
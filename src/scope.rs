@@ -0,0 +1,429 @@
+//! Path scope for agent edits
+//!
+//! With `[scope] enabled = true`, every iteration's changes are checked
+//! against allow/deny globs: the diff from the iteration's starting commit to
+//! the working tree, plus files that became untracked during the iteration.
+//! Files that were already dirty when the iteration started only count if
+//! the iteration changed them further, and are never reverted, so edits made
+//! outside the run are not lost.
+//!
+//! Deny globs win over allow globs. Fresher's own files under `.fresher/` are
+//! exempt unless a deny glob matches them (`.fresher/config.toml` and
+//! `.fresher/hooks/` by default).
+//!
+//! Globs are matched against paths relative to the project root: `*` and `?`
+//! stay within one path segment, `**` spans segments, and a trailing `/`
+//! matches everything below a directory.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::config::{Config, ScopeAction};
+use crate::state::git;
+
+/// Working tree state at the start of an iteration, so changes that were
+/// already there are not blamed on it
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    /// Untracked files
+    pub untracked: BTreeSet<String>,
+    /// Tracked files with uncommitted changes, with their content hash
+    /// (`None` when deleted)
+    pub dirty: BTreeMap<String, Option<String>>,
+}
+
+impl Baseline {
+    pub fn take(project_dir: &Path) -> Result<Self> {
+        let dirty = lines(git(project_dir, &["diff", "HEAD", "--name-only", "--no-renames"])?);
+        Ok(Self {
+            untracked: untracked_files(project_dir)?,
+            dirty: hash_files(project_dir, &dirty)?,
+        })
+    }
+}
+
+/// Compiled allow/deny globs for one run
+#[derive(Debug, Clone)]
+pub struct Scope {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+    pub action: ScopeAction,
+}
+
+impl Scope {
+    /// Build the scope for `config.fresher.mode`, or `None` when disabled
+    ///
+    /// Without `allow` globs an iteration may change the source directory,
+    /// `tests/`, and the plan files. Specs are always denied while building.
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let scope = &config.scope;
+        if !scope.enabled {
+            return Ok(None);
+        }
+
+        let allow = if scope.allow.is_empty() {
+            vec![
+                format!("{}/", config.paths.src_dir),
+                "tests/".to_string(),
+                "IMPLEMENTATION_PLAN.md".to_string(),
+                format!("{}/", config.paths.impl_dir),
+            ]
+        } else {
+            scope.allow.clone()
+        };
+        let mut deny = scope.deny.clone();
+        if config.fresher.mode == "building" {
+            deny.push(format!("{}/", config.paths.spec_dir));
+        }
+
+        let compile = |globs: &[String]| -> Result<Vec<Regex>> {
+            globs.iter().map(|g| glob_to_regex(g)).collect()
+        };
+        Ok(Some(Self {
+            allow: compile(&allow)?,
+            deny: compile(&deny)?,
+            action: scope.action,
+        }))
+    }
+
+    /// Whether an iteration may change `path` (relative to the project root)
+    pub fn allows(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./");
+        if self.deny.iter().any(|re| re.is_match(path)) {
+            return false;
+        }
+        path.starts_with(".fresher/") || self.allow.iter().any(|re| re.is_match(path))
+    }
+
+    /// Out-of-scope target of a file-writing tool call, for early warnings
+    pub fn check_tool(&self, name: &str, input: &Value, project_dir: &Path) -> Option<String> {
+        let key = match name {
+            "Write" | "Edit" | "MultiEdit" => "file_path",
+            "NotebookEdit" => "notebook_path",
+            _ => return None,
+        };
+        let path = Path::new(input.get(key)?.as_str()?);
+        let relative = path.strip_prefix(project_dir).unwrap_or(path);
+        let relative = relative.to_string_lossy();
+        (!self.allows(&relative)).then(|| relative.to_string())
+    }
+
+    /// Check the files an iteration changed and apply the configured action
+    ///
+    /// `baseline` is the working tree from when the iteration started.
+    /// Returns the out-of-scope files.
+    pub fn enforce(
+        &self,
+        project_dir: &Path,
+        iteration_sha: &str,
+        baseline: &Baseline,
+    ) -> Result<Vec<String>> {
+        let mut changed = changed_files(project_dir, iteration_sha)?;
+        changed.extend(
+            untracked_files(project_dir)?
+                .into_iter()
+                .filter(|f| !baseline.untracked.contains(f)),
+        );
+
+        let candidates: BTreeSet<String> = changed
+            .into_iter()
+            .filter(|f| !self.allows(f))
+            .collect();
+        let was_dirty: BTreeSet<String> = candidates
+            .iter()
+            .filter(|f| baseline.dirty.contains_key(*f))
+            .cloned()
+            .collect();
+        let now = hash_files(project_dir, &was_dirty)?;
+        let violations: Vec<String> = candidates
+            .into_iter()
+            .filter(|f| baseline.dirty.get(f).is_none_or(|before| now.get(f) != Some(before)))
+            .collect();
+
+        let revertible: Vec<String> = violations
+            .iter()
+            .filter(|f| !was_dirty.contains(*f))
+            .cloned()
+            .collect();
+        if !revertible.is_empty() && self.action != ScopeAction::Flag {
+            revert(project_dir, iteration_sha, &revertible)?;
+        }
+        Ok(violations)
+    }
+}
+
+/// Translate a scope glob into an anchored regex
+pub fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let glob = glob.trim_start_matches("./");
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directories at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if glob.ends_with('/') {
+        pattern.push_str(".*");
+    }
+    pattern.push('$');
+    Regex::new(&pattern).with_context(|| format!("Invalid scope glob `{}`", glob))
+}
+
+fn lines(output: String) -> BTreeSet<String> {
+    output.lines().filter(|l| !l.is_empty()).map(String::from).collect()
+}
+
+/// Tracked files that differ between `sha` and the working tree
+pub fn changed_files(project_dir: &Path, sha: &str) -> Result<BTreeSet<String>> {
+    git(project_dir, &["diff", "--name-only", "--no-renames", sha]).map(lines)
+}
+
+/// Git blob hashes of `files`, `None` for ones that don't exist
+pub(crate) fn hash_files(
+    project_dir: &Path,
+    files: &BTreeSet<String>,
+) -> Result<BTreeMap<String, Option<String>>> {
+    let existing: Vec<&str> = files
+        .iter()
+        .filter(|f| project_dir.join(f).is_file())
+        .map(String::as_str)
+        .collect();
+    let mut hashes: BTreeMap<String, Option<String>> =
+        files.iter().map(|f| (f.clone(), None)).collect();
+    if !existing.is_empty() {
+        let mut args = vec!["hash-object", "--"];
+        args.extend(&existing);
+        for (file, hash) in existing.iter().zip(git(project_dir, &args)?.lines()) {
+            hashes.insert(file.to_string(), Some(hash.to_string()));
+        }
+    }
+    Ok(hashes)
+}
+
+/// Untracked files not covered by `.gitignore`
+pub fn untracked_files(project_dir: &Path) -> Result<BTreeSet<String>> {
+    git(project_dir, &["ls-files", "--others", "--exclude-standard"]).map(lines)
+}
+
+/// Restore `files` to their content at `sha` (deleting ones it didn't have)
+/// and commit the result if that undoes committed changes
+pub fn revert(project_dir: &Path, sha: &str, files: &[String]) -> Result<()> {
+    for file in files {
+        let existed = git(project_dir, &["cat-file", "-e", &format!("{}:{}", sha, file)]).is_ok();
+        if existed {
+            git(project_dir, &["checkout", sha, "--", file])?;
+        } else {
+            git(project_dir, &["rm", "-q", "--cached", "--ignore-unmatch", "--", file])?;
+            let path = project_dir.join(file);
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+
+    let mut args = vec!["diff", "--cached", "--name-only", "--"];
+    args.extend(files.iter().map(String::as_str));
+    let staged = lines(git(project_dir, &args)?);
+    if !staged.is_empty() {
+        let mut args = vec!["commit", "-q", "-m", "Revert out-of-scope changes", "--"];
+        args.extend(staged.iter().map(String::as_str));
+        git(project_dir, &args)?;
+    }
+    Ok(())
+}
+
+/// Append out-of-scope files to an iteration log
+pub fn append_to_log(path: &Path, action: ScopeAction, violations: &[String]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "  out of scope ({}):", action)?;
+    for violation in violations {
+        writeln!(file, "    {}", violation)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScopeConfig;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn scope(mode: &str, action: ScopeAction) -> Scope {
+        let mut config = Config::default();
+        config.fresher.mode = mode.to_string();
+        config.scope = ScopeConfig {
+            enabled: true,
+            action,
+            ..Default::default()
+        };
+        Scope::from_config(&config).unwrap().unwrap()
+    }
+
+    fn git_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for args in [
+            vec!["init", "-q"],
+            vec!["config", "user.email", "test@example.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            git(dir.path(), &args).unwrap();
+        }
+        dir
+    }
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let re = glob_to_regex("src/*.rs").unwrap();
+        assert!(re.is_match("src/main.rs"));
+        assert!(!re.is_match("src/commands/build.rs"));
+
+        let re = glob_to_regex("src/**/*.rs").unwrap();
+        assert!(re.is_match("src/main.rs"));
+        assert!(re.is_match("src/commands/build.rs"));
+
+        let re = glob_to_regex(".github/").unwrap();
+        assert!(re.is_match(".github/workflows/ci.yml"));
+        assert!(!re.is_match(".githubx"));
+
+        let re = glob_to_regex("*.md").unwrap();
+        assert!(re.is_match("README.md"));
+        assert!(!re.is_match("docs/guide.md"));
+    }
+
+    #[test]
+    fn test_default_scope() {
+        let building = scope("building", ScopeAction::Flag);
+        assert!(building.allows("src/lib.rs"));
+        assert!(building.allows("tests/cli.rs"));
+        assert!(building.allows("impl/parser.md"));
+        assert!(building.allows("IMPLEMENTATION_PLAN.md"));
+        assert!(building.allows(".fresher/logs/iteration-1.log"));
+        assert!(!building.allows(".fresher/config.toml"));
+        assert!(!building.allows(".fresher/hooks/finished"));
+        assert!(!building.allows(".github/workflows/ci.yml"));
+        assert!(!building.allows("specs/parser.md"));
+        assert!(!building.allows("Cargo.toml"));
+
+        // Specs are only off limits while building
+        let mut config = Config::default();
+        config.scope.enabled = true;
+        config.scope.allow = vec!["specs/".to_string()];
+        assert!(Scope::from_config(&config).unwrap().unwrap().allows("specs/parser.md"));
+
+        assert!(Scope::from_config(&Config::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_check_tool() {
+        let scope = scope("building", ScopeAction::Flag);
+        let dir = Path::new("/work/project");
+        assert_eq!(
+            scope.check_tool("Write", &json!({"file_path": "/work/project/.github/ci.yml"}), dir),
+            Some(".github/ci.yml".to_string())
+        );
+        assert_eq!(
+            scope.check_tool("Edit", &json!({"file_path": "/work/project/src/lib.rs"}), dir),
+            None
+        );
+        assert_eq!(scope.check_tool("Read", &json!({"file_path": "Cargo.toml"}), dir), None);
+    }
+
+    #[test]
+    fn test_enforce_flags_and_reverts() {
+        let dir = git_repo();
+        let root = dir.path();
+        write(root, "src/lib.rs", "one");
+        write(root, ".github/ci.yml", "on: push");
+        write(root, "notes.txt", "untracked before");
+        git(root, &["add", "src", ".github"]).unwrap();
+        git(root, &["commit", "-qm", "start"]).unwrap();
+        let sha = git(root, &["rev-parse", "HEAD"]).unwrap().trim().to_string();
+        let baseline = Baseline::take(root).unwrap();
+
+        // The iteration commits an in-scope and an out-of-scope change, then
+        // leaves a new out-of-scope file in the working tree
+        write(root, "src/lib.rs", "two");
+        write(root, ".github/ci.yml", "on: pull_request");
+        git(root, &["commit", "-qam", "work"]).unwrap();
+        write(root, "Makefile", "all:");
+
+        let flagged = scope("building", ScopeAction::Flag)
+            .enforce(root, &sha, &baseline)
+            .unwrap();
+        assert_eq!(flagged, vec![".github/ci.yml", "Makefile"]);
+        assert!(root.join("Makefile").exists());
+
+        let reverted = scope("building", ScopeAction::Revert)
+            .enforce(root, &sha, &baseline)
+            .unwrap();
+        assert_eq!(reverted, flagged);
+        assert_eq!(std::fs::read_to_string(root.join(".github/ci.yml")).unwrap(), "on: push");
+        assert_eq!(std::fs::read_to_string(root.join("src/lib.rs")).unwrap(), "two");
+        assert!(!root.join("Makefile").exists());
+        assert!(root.join("notes.txt").exists());
+        assert_eq!(
+            git(root, &["log", "-1", "--format=%s"]).unwrap().trim(),
+            "Revert out-of-scope changes"
+        );
+
+        let after = scope("building", ScopeAction::Flag)
+            .enforce(root, &sha, &baseline)
+            .unwrap();
+        assert!(after.is_empty());
+    }
+
+    #[test]
+    fn test_enforce_leaves_dirty_files_alone() {
+        let dir = git_repo();
+        let root = dir.path();
+        write(root, "Cargo.toml", "[package]");
+        write(root, "Makefile", "all:");
+        git(root, &["add", "."]).unwrap();
+        git(root, &["commit", "-qm", "start"]).unwrap();
+        let sha = git(root, &["rev-parse", "HEAD"]).unwrap().trim().to_string();
+
+        // Uncommitted edits from before the run
+        write(root, "Cargo.toml", "[package]\nname = \"mine\"");
+        write(root, "Makefile", "all: build");
+        let baseline = Baseline::take(root).unwrap();
+        assert_eq!(baseline.dirty.len(), 2);
+
+        // The iteration only touches the Makefile
+        write(root, "Makefile", "all: build test");
+        let violations = scope("building", ScopeAction::Revert)
+            .enforce(root, &sha, &baseline)
+            .unwrap();
+        assert_eq!(violations, vec!["Makefile"]);
+
+        // Reported, but neither file loses the edits made before the run
+        assert_eq!(std::fs::read_to_string(root.join("Makefile")).unwrap(), "all: build test");
+        assert_eq!(
+            std::fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"mine\""
+        );
+    }
+}
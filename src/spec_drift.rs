@@ -0,0 +1,312 @@
+//! Spec drift detection
+//!
+//! When planning finishes, fresher records a SHA-256 hash of every spec file in
+//! `.fresher/spec-hashes.toml`. `fresher verify` and `fresher build` compare the
+//! current specs against those hashes to notice specs that changed after the
+//! plan was made, and use the `**Spec:**` links of feature files to point at the
+//! parts of the plan that may be out of date.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::impl_plan::{has_hierarchical_plan, FeatureStatus, ImplIndex};
+
+/// Spec hash file location, relative to the project directory
+pub const SPEC_HASHES_FILE: &str = ".fresher/spec-hashes.toml";
+
+/// Hashes of the spec files as they were when the plan was made
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecHashes {
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// Spec path relative to the spec directory -> hex SHA-256 of its content
+    #[serde(default)]
+    pub specs: BTreeMap<String, String>,
+}
+
+impl SpecHashes {
+    /// Hash all markdown files under `spec_dir`
+    pub fn compute(spec_dir: &Path) -> Result<Self> {
+        let mut specs = BTreeMap::new();
+        if spec_dir.exists() {
+            collect_specs(spec_dir, spec_dir, &mut specs)?;
+        }
+        Ok(Self {
+            recorded_at: Some(Utc::now()),
+            specs,
+        })
+    }
+
+    /// Load recorded hashes, if planning has recorded any yet
+    pub fn load(project_dir: &Path) -> Result<Option<Self>> {
+        let path = project_dir.join(SPEC_HASHES_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let hashes = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(hashes))
+    }
+
+    /// Write hashes to `.fresher/spec-hashes.toml`
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let path = project_dir.join(SPEC_HASHES_FILE);
+        let content = toml::to_string_pretty(self).context("Failed to serialize spec hashes")?;
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Compare recorded hashes (`self`) against the current ones
+    pub fn diff(&self, current: &SpecHashes) -> SpecDrift {
+        let mut drift = SpecDrift::default();
+
+        for (spec, hash) in &current.specs {
+            match self.specs.get(spec) {
+                None => drift.added.push(spec.clone()),
+                Some(old) if old != hash => drift.changed.push(spec.clone()),
+                Some(_) => {}
+            }
+        }
+        for spec in self.specs.keys() {
+            if !current.specs.contains_key(spec) {
+                drift.removed.push(spec.clone());
+            }
+        }
+
+        drift
+    }
+}
+
+/// Specs that differ from the recorded hashes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecDrift {
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Feature files whose `**Spec:**` link points at a changed or removed spec
+    #[serde(default)]
+    pub affected_features: Vec<AffectedFeature>,
+}
+
+/// A feature file planned from a spec that has since changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AffectedFeature {
+    pub spec: String,
+    pub feature: String,
+    pub file: PathBuf,
+}
+
+impl SpecDrift {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Fill in `affected_features` from the plan's feature files
+    pub fn link_features(&mut self, features: &[FeatureStatus]) {
+        self.affected_features.clear();
+        for spec in self.changed.iter().chain(&self.removed) {
+            for feature in features {
+                if feature
+                    .spec_ref
+                    .as_deref()
+                    .map(|r| spec_ref_matches(r, spec))
+                    .unwrap_or(false)
+                {
+                    self.affected_features.push(AffectedFeature {
+                        spec: spec.clone(),
+                        feature: feature.name.clone(),
+                        file: feature.file.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Print a warning listing the drifted specs and affected feature files
+    pub fn print_warning(&self) {
+        eprintln!(
+            "{} Specs changed since the plan was made:",
+            "Warning:".yellow().bold()
+        );
+        for spec in &self.changed {
+            eprintln!("  {} {}", "modified".yellow(), spec);
+        }
+        for spec in &self.added {
+            eprintln!("  {}    {}", "added".green(), spec);
+        }
+        for spec in &self.removed {
+            eprintln!("  {}  {}", "removed".red(), spec);
+        }
+        if !self.affected_features.is_empty() {
+            eprintln!("  Affected feature files:");
+            for affected in &self.affected_features {
+                eprintln!(
+                    "    {} ({})",
+                    affected.file.display().to_string().cyan(),
+                    affected.spec
+                );
+            }
+        }
+        eprintln!("  Run {} to update the plan.", "fresher plan".cyan());
+    }
+}
+
+/// Record the current spec hashes after planning
+pub fn record(project_dir: &Path, spec_dir: &Path) -> Result<()> {
+    SpecHashes::compute(spec_dir)?.save(project_dir)
+}
+
+/// Check the specs against the recorded hashes.
+///
+/// Returns `None` when no hashes have been recorded yet or nothing changed.
+pub fn check(project_dir: &Path, spec_dir: &Path, impl_dir: &Path) -> Result<Option<SpecDrift>> {
+    let Some(recorded) = SpecHashes::load(project_dir)? else {
+        return Ok(None);
+    };
+
+    let mut drift = recorded.diff(&SpecHashes::compute(spec_dir)?);
+    if drift.is_empty() {
+        return Ok(None);
+    }
+
+    if has_hierarchical_plan(impl_dir) {
+        if let Ok(index) = ImplIndex::load(impl_dir) {
+            drift.link_features(&index.features);
+        }
+    }
+
+    Ok(Some(drift))
+}
+
+/// Whether a `**Spec:**` link (e.g. `../specs/auth.md`) refers to `spec`,
+/// a path relative to the spec directory
+fn spec_ref_matches(spec_ref: &str, spec: &str) -> bool {
+    let spec_ref = spec_ref.split('#').next().unwrap_or(spec_ref);
+    Path::new(spec_ref).ends_with(spec)
+}
+
+fn collect_specs(root: &Path, dir: &Path, specs: &mut BTreeMap<String, String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_specs(root, &path, specs)?;
+        } else if path.extension().map(|e| e == "md").unwrap_or(false) {
+            let content = fs::read(&path)?;
+            let hash = Sha256::digest(&content)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+
+            let rel = path.strip_prefix(root).unwrap_or(&path);
+            let key = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            specs.insert(key, hash);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impl_plan::FeatureState;
+    use tempfile::TempDir;
+
+    fn setup_project() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".fresher")).unwrap();
+        let spec_dir = dir.path().join("specs");
+        fs::create_dir_all(&spec_dir).unwrap();
+        fs::write(spec_dir.join("auth.md"), "# Auth\n\nMUST hash passwords").unwrap();
+        fs::write(spec_dir.join("api.md"), "# API").unwrap();
+        (dir, spec_dir)
+    }
+
+    fn feature(name: &str, spec_ref: Option<&str>) -> FeatureStatus {
+        FeatureStatus {
+            name: name.to_string(),
+            file: PathBuf::from(format!("impl/{}.md", name)),
+            status: FeatureState::Pending,
+            total_tasks: 1,
+            completed_tasks: 0,
+            pending_tasks: 1,
+            spec_ref: spec_ref.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_compute_uses_relative_paths() {
+        let (_dir, spec_dir) = setup_project();
+        fs::create_dir_all(spec_dir.join("billing")).unwrap();
+        fs::write(spec_dir.join("billing/invoices.md"), "# Invoices").unwrap();
+        fs::write(spec_dir.join("notes.txt"), "not a spec").unwrap();
+
+        let hashes = SpecHashes::compute(&spec_dir).unwrap();
+        let keys: Vec<_> = hashes.specs.keys().cloned().collect();
+        assert_eq!(keys, vec!["api.md", "auth.md", "billing/invoices.md"]);
+        assert_eq!(hashes.specs["api.md"].len(), 64);
+    }
+
+    #[test]
+    fn test_no_drift_without_recorded_hashes() {
+        let (dir, spec_dir) = setup_project();
+        let drift = check(dir.path(), &spec_dir, &dir.path().join("impl")).unwrap();
+        assert!(drift.is_none());
+    }
+
+    #[test]
+    fn test_record_then_check() {
+        let (dir, spec_dir) = setup_project();
+        record(dir.path(), &spec_dir).unwrap();
+
+        let impl_dir = dir.path().join("impl");
+        assert!(check(dir.path(), &spec_dir, &impl_dir).unwrap().is_none());
+
+        fs::write(spec_dir.join("auth.md"), "# Auth\n\nMUST use argon2").unwrap();
+        fs::remove_file(spec_dir.join("api.md")).unwrap();
+        fs::write(spec_dir.join("billing.md"), "# Billing").unwrap();
+
+        let drift = check(dir.path(), &spec_dir, &impl_dir).unwrap().unwrap();
+        assert_eq!(drift.changed, vec!["auth.md"]);
+        assert_eq!(drift.removed, vec!["api.md"]);
+        assert_eq!(drift.added, vec!["billing.md"]);
+    }
+
+    #[test]
+    fn test_link_features() {
+        let mut drift = SpecDrift {
+            changed: vec!["auth.md".to_string()],
+            removed: vec!["api.md".to_string()],
+            ..Default::default()
+        };
+        let features = vec![
+            feature("authentication", Some("../specs/auth.md")),
+            feature("oauth", Some("../specs/oauth.md")),
+            feature("api", Some("../specs/api.md#endpoints")),
+            feature("misc", None),
+        ];
+
+        drift.link_features(&features);
+
+        let names: Vec<_> = drift
+            .affected_features
+            .iter()
+            .map(|a| a.feature.as_str())
+            .collect();
+        assert_eq!(names, vec!["authentication", "api"]);
+        assert_eq!(
+            drift.affected_features[0].file,
+            PathBuf::from("impl/authentication.md")
+        );
+    }
+}
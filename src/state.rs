@@ -1,9 +1,14 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
+use crate::config::{BudgetConfig, OutcomeAction, OutcomeConfig};
+use crate::streaming::{IterationOutcome, TokenUsage};
+use crate::todos::{self, Todo};
+
 /// Represents the current state of a fresher run
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
@@ -16,8 +21,65 @@ pub struct State {
     pub finish_type: Option<FinishType>,
     pub iteration_start: Option<DateTime<Utc>>,
     pub iteration_sha: Option<String>,
+    /// Prompt text queued by hooks for the next iteration
+    #[serde(default)]
+    pub prompt_append: Option<String>,
+    /// Model requested by a hook for the next iteration
+    #[serde(default)]
+    pub model_override: Option<String>,
+    /// Key/value metadata set by hook responses
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Tokens used by the current (or last) iteration
+    #[serde(default)]
+    pub iteration_usage: TokenUsage,
+    /// Tokens used by the whole run
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Cost of the whole run in USD, as reported by Claude
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// How the last iteration ended
+    #[serde(default)]
+    pub outcome: Option<IterationOutcome>,
+    /// Consecutive iterations retried under the `retry` outcome policy
+    #[serde(default)]
+    pub outcome_retries: u32,
+    /// Consecutive iterations counted as stalled
+    #[serde(default)]
+    pub stalled_iterations: u32,
+    /// Raised `max_turns` for the next iteration, after a retry
+    #[serde(default)]
+    pub max_turns_override: Option<u32>,
+    /// Files the last iteration changed outside `[scope]`
+    #[serde(default)]
+    pub scope_violations: Vec<String>,
+    /// Locations of possible secrets in the last iteration's commits
+    /// (`file:line rule`, never the value)
+    #[serde(default)]
+    pub secret_findings: Vec<String>,
+    /// The agent's last `TodoWrite` list in the last iteration
+    #[serde(default)]
+    pub todos: Vec<Todo>,
+    /// Whether that list covered the plan's focus task (`None` if not checked)
+    #[serde(default)]
+    pub todo_on_focus: Option<bool>,
 }
 
+/// Process exit codes for each finish type.
+///
+/// `1` is left for fresher's own failures (bad config, missing `claude`)
+/// and `2` for invalid command-line usage.
+pub const EXIT_COMPLETE: i32 = 0;
+pub const EXIT_MAX_ITERATIONS: i32 = 3;
+pub const EXIT_NO_CHANGES: i32 = 4;
+pub const EXIT_ERROR: i32 = 5;
+pub const EXIT_MANUAL: i32 = 6;
+pub const EXIT_BUDGET: i32 = 7;
+pub const EXIT_STALLED: i32 = 8;
+pub const EXIT_POLICY_VIOLATION: i32 = 9;
+pub const EXIT_OUTCOME_STOP: i32 = 10;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishType {
@@ -26,6 +88,31 @@ pub enum FinishType {
     MaxIterations,
     Complete,
     NoChanges,
+    /// A configured run budget was exhausted
+    Budget,
+    /// The loop stopped making progress
+    Stalled,
+    /// The agent tried something a guardrail denies
+    PolicyViolation,
+    /// The outcome policy said to stop after an iteration
+    OutcomeStop,
+}
+
+impl FinishType {
+    /// Process exit code for this finish type
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FinishType::Complete => EXIT_COMPLETE,
+            FinishType::MaxIterations => EXIT_MAX_ITERATIONS,
+            FinishType::NoChanges => EXIT_NO_CHANGES,
+            FinishType::Error => EXIT_ERROR,
+            FinishType::Manual => EXIT_MANUAL,
+            FinishType::Budget => EXIT_BUDGET,
+            FinishType::Stalled => EXIT_STALLED,
+            FinishType::PolicyViolation => EXIT_POLICY_VIOLATION,
+            FinishType::OutcomeStop => EXIT_OUTCOME_STOP,
+        }
+    }
 }
 
 impl std::fmt::Display for FinishType {
@@ -36,6 +123,10 @@ impl std::fmt::Display for FinishType {
             FinishType::MaxIterations => write!(f, "max_iterations"),
             FinishType::Complete => write!(f, "complete"),
             FinishType::NoChanges => write!(f, "no_changes"),
+            FinishType::Budget => write!(f, "budget"),
+            FinishType::Stalled => write!(f, "stalled"),
+            FinishType::PolicyViolation => write!(f, "policy_violation"),
+            FinishType::OutcomeStop => write!(f, "outcome_stop"),
         }
     }
 }
@@ -52,6 +143,20 @@ impl Default for State {
             finish_type: None,
             iteration_start: None,
             iteration_sha: None,
+            prompt_append: None,
+            model_override: None,
+            metadata: BTreeMap::new(),
+            iteration_usage: TokenUsage::default(),
+            usage: TokenUsage::default(),
+            cost_usd: 0.0,
+            outcome: None,
+            outcome_retries: 0,
+            stalled_iterations: 0,
+            max_turns_override: None,
+            scope_violations: Vec::new(),
+            secret_findings: Vec::new(),
+            todos: Vec::new(),
+            todo_on_focus: None,
         }
     }
 }
@@ -87,6 +192,11 @@ impl State {
         self.iteration += 1;
         self.iteration_start = Some(Utc::now());
         self.iteration_sha = commit_sha;
+        self.iteration_usage = TokenUsage::default();
+        self.scope_violations.clear();
+        self.secret_findings.clear();
+        self.todos.clear();
+        self.todo_on_focus = None;
     }
 
     /// Record iteration completion
@@ -99,6 +209,71 @@ impl State {
         self.update_duration();
     }
 
+    /// Add an iteration's token usage and cost to the run totals
+    pub fn record_usage(&mut self, usage: &TokenUsage, cost_usd: Option<f64>) {
+        self.iteration_usage.add(usage);
+        self.usage.add(usage);
+        self.cost_usd += cost_usd.unwrap_or(0.0);
+    }
+
+    /// Record how an iteration ended and decide what the loop does next
+    ///
+    /// `retry` raises the next iteration's turn limit (see
+    /// `max_turns_override`); once `max_retries` is used up it counts as a
+    /// stall instead, until an outcome with another action resets the count.
+    pub fn record_outcome(
+        &mut self,
+        outcome: IterationOutcome,
+        policy: &OutcomeConfig,
+        max_turns: u32,
+    ) -> OutcomeAction {
+        self.outcome = Some(outcome);
+
+        let mut action = policy.action_for(outcome);
+        if action == OutcomeAction::Retry {
+            if self.outcome_retries < policy.max_retries {
+                self.outcome_retries += 1;
+                self.max_turns_override = Some(max_turns + policy.retry_extra_turns);
+                return action;
+            }
+            action = OutcomeAction::Stall;
+        } else {
+            self.outcome_retries = 0;
+        }
+
+        self.max_turns_override = None;
+        if action == OutcomeAction::Stall {
+            self.stalled_iterations += 1;
+        } else {
+            self.stalled_iterations = 0;
+        }
+        action
+    }
+
+    /// Whether enough consecutive iterations stalled to stop the loop
+    pub fn is_stalled(&self, policy: &OutcomeConfig) -> bool {
+        policy.stall_limit > 0 && self.stalled_iterations >= policy.stall_limit
+    }
+
+    /// Why the run is over budget, if it is
+    pub fn exceeded_budget(&self, budget: &BudgetConfig) -> Option<String> {
+        if let Some(max) = budget.max_cost_usd {
+            if self.cost_usd >= max {
+                return Some(format!("cost ${:.4} reached the ${:.2} budget", self.cost_usd, max));
+            }
+        }
+        if let Some(max) = budget.max_tokens {
+            if self.usage.total() >= max {
+                return Some(format!(
+                    "{} tokens reached the {} token budget",
+                    self.usage.total(),
+                    max
+                ));
+            }
+        }
+        None
+    }
+
     /// Update the total duration
     pub fn update_duration(&mut self) {
         self.duration = (Utc::now() - self.started_at).num_seconds() as u64;
@@ -120,6 +295,46 @@ impl State {
             ("FRESHER_TOTAL_ITERATIONS".to_string(), self.iteration.to_string()),
         ];
 
+        let usage = [
+            ("FRESHER_INPUT_TOKENS", self.usage.input_tokens),
+            ("FRESHER_OUTPUT_TOKENS", self.usage.output_tokens),
+            ("FRESHER_CACHE_READ_TOKENS", self.usage.cache_read_input_tokens),
+            ("FRESHER_CACHE_CREATION_TOKENS", self.usage.cache_creation_input_tokens),
+            ("FRESHER_TOTAL_TOKENS", self.usage.total()),
+            ("FRESHER_ITERATION_TOKENS", self.iteration_usage.total()),
+        ];
+        vars.extend(usage.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        vars.push(("FRESHER_COST_USD".to_string(), format!("{:.4}", self.cost_usd)));
+        if let Some(ratio) = self.usage.cache_hit_ratio() {
+            vars.push(("FRESHER_CACHE_HIT_RATIO".to_string(), format!("{:.2}", ratio)));
+        }
+
+        vars.push((
+            "FRESHER_STALLED_ITERATIONS".to_string(),
+            self.stalled_iterations.to_string(),
+        ));
+        if let Some(outcome) = &self.outcome {
+            vars.push(("FRESHER_ITERATION_OUTCOME".to_string(), outcome.to_string()));
+        }
+        if !self.scope_violations.is_empty() {
+            vars.push((
+                "FRESHER_SCOPE_VIOLATIONS".to_string(),
+                self.scope_violations.join("\n"),
+            ));
+        }
+        if !self.secret_findings.is_empty() {
+            vars.push((
+                "FRESHER_SECRET_FINDINGS".to_string(),
+                self.secret_findings.join("\n"),
+            ));
+        }
+        if !self.todos.is_empty() {
+            vars.push(("FRESHER_TODO_PROGRESS".to_string(), todos::progress(&self.todos)));
+        }
+        if let Some(on_focus) = self.todo_on_focus {
+            vars.push(("FRESHER_TODO_ON_FOCUS".to_string(), on_focus.to_string()));
+        }
+
         if let Some(sha) = &self.last_commit_sha {
             vars.push(("FRESHER_LAST_COMMIT_SHA".to_string(), sha.clone()));
         }
@@ -128,8 +343,72 @@ impl State {
             vars.push(("FRESHER_FINISH_TYPE".to_string(), finish.to_string()));
         }
 
+        for (key, value) in &self.metadata {
+            let key: String = key
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            vars.push((format!("FRESHER_META_{}", key), value.clone()));
+        }
+
         vars
     }
+
+    /// `prompt` with any text queued by hooks appended
+    pub fn prompt_with_additions(&self, prompt: &str) -> String {
+        match &self.prompt_append {
+            Some(extra) => format!("{}\n\n{}", prompt.trim_end(), extra),
+            None => prompt.to_string(),
+        }
+    }
+
+    /// Consume the prompt text and model override hooks queued for this iteration
+    ///
+    /// Returns the prompt and model to run the iteration with.
+    pub fn take_hook_steering(&mut self, prompt: &str, model: &str) -> (String, String) {
+        let prompt = self.prompt_with_additions(prompt);
+        self.prompt_append = None;
+        let model = self.model_override.take().unwrap_or_else(|| model.to_string());
+        (prompt, model)
+    }
+
+    /// Single-line `key=value` run summary for CI logs
+    pub fn summary_line(&self, mode: &str) -> String {
+        let finish = self
+            .finish_type
+            .map(|f| f.to_string())
+            .unwrap_or_else(|| "none".to_string());
+        let exit_code = self.finish_type.map(|f| f.exit_code()).unwrap_or(EXIT_COMPLETE);
+
+        format!(
+            "fresher-summary mode={} finish={} exit_code={} iterations={} commits={} duration={} tokens={} cost_usd={:.4}",
+            mode,
+            finish,
+            exit_code,
+            self.iteration,
+            self.total_commits,
+            self.duration,
+            self.usage.total(),
+            self.cost_usd
+        )
+    }
+}
+
+/// Run git in `project_dir` and return its stdout, failing with its stderr
+pub fn git(project_dir: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(project_dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
 /// Get current git SHA
@@ -166,3 +445,152 @@ pub fn count_commits_since(sha: &str) -> u32 {
         })
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_finish_type_exit_codes_distinct() {
+        let all = [
+            FinishType::Complete,
+            FinishType::MaxIterations,
+            FinishType::NoChanges,
+            FinishType::Error,
+            FinishType::Manual,
+            FinishType::Budget,
+            FinishType::Stalled,
+            FinishType::PolicyViolation,
+            FinishType::OutcomeStop,
+        ];
+        let codes: HashSet<i32> = all.iter().map(|f| f.exit_code()).collect();
+
+        assert_eq!(codes.len(), all.len());
+        assert_eq!(FinishType::Complete.exit_code(), 0);
+        assert!(!codes.contains(&1), "1 is reserved for fresher failures");
+        assert!(!codes.contains(&2), "2 is reserved for usage errors");
+    }
+
+    #[test]
+    fn test_finish_type_display() {
+        assert_eq!(FinishType::Budget.to_string(), "budget");
+        assert_eq!(FinishType::Stalled.to_string(), "stalled");
+        assert_eq!(FinishType::PolicyViolation.to_string(), "policy_violation");
+        assert_eq!(FinishType::OutcomeStop.to_string(), "outcome_stop");
+        assert_eq!(FinishType::NoChanges.to_string(), "no_changes");
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let mut state = State::new();
+        state.iteration = 3;
+        state.total_commits = 2;
+        state.finish_type = Some(FinishType::NoChanges);
+        state.duration = 42;
+
+        assert_eq!(
+            state.summary_line("building"),
+            "fresher-summary mode=building finish=no_changes exit_code=4 iterations=3 commits=2 duration=42 tokens=0 cost_usd=0.0000"
+        );
+    }
+
+    #[test]
+    fn test_record_outcome() {
+        let policy = OutcomeConfig {
+            max_retries: 1,
+            retry_extra_turns: 25,
+            stall_limit: 2,
+            ..Default::default()
+        };
+        let mut state = State::new();
+
+        // First max-turns hit retries with more turns
+        let action = state.record_outcome(IterationOutcome::MaxTurns, &policy, 50);
+        assert_eq!(action, OutcomeAction::Retry);
+        assert_eq!(state.max_turns_override, Some(75));
+        assert!(state
+            .to_env_vars()
+            .contains(&("FRESHER_ITERATION_OUTCOME".to_string(), "max_turns".to_string())));
+
+        // Retries used up: the next ones stall
+        let action = state.record_outcome(IterationOutcome::MaxTurns, &policy, 75);
+        assert_eq!(action, OutcomeAction::Stall);
+        assert_eq!(state.max_turns_override, None);
+        assert!(!state.is_stalled(&policy));
+        state.record_outcome(IterationOutcome::MaxTurns, &policy, 50);
+        assert!(state.is_stalled(&policy));
+
+        // Success resets everything
+        let action = state.record_outcome(IterationOutcome::Success, &policy, 50);
+        assert_eq!(action, OutcomeAction::Continue);
+        assert_eq!(state.stalled_iterations, 0);
+        assert_eq!(state.outcome_retries, 0);
+        assert_eq!(
+            state.record_outcome(IterationOutcome::ExecutionError, &policy, 50),
+            OutcomeAction::Error
+        );
+    }
+
+    #[test]
+    fn test_usage_and_budget() {
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 400,
+            cache_read_input_tokens: 300,
+            cache_creation_input_tokens: 0,
+        };
+        let mut state = State::new();
+        state.start_iteration(None);
+        state.record_usage(&usage, Some(0.25));
+        state.start_iteration(None);
+        state.record_usage(&usage, Some(0.25));
+
+        assert_eq!(state.iteration_usage.total(), 800);
+        assert_eq!(state.usage.total(), 1600);
+        let vars = state.to_env_vars();
+        assert!(vars.contains(&("FRESHER_TOTAL_TOKENS".to_string(), "1600".to_string())));
+        assert!(vars.contains(&("FRESHER_ITERATION_TOKENS".to_string(), "800".to_string())));
+        assert!(vars.contains(&("FRESHER_CACHE_HIT_RATIO".to_string(), "0.75".to_string())));
+        assert!(vars.contains(&("FRESHER_COST_USD".to_string(), "0.5000".to_string())));
+
+        assert_eq!(state.exceeded_budget(&BudgetConfig::default()), None);
+        let budget = BudgetConfig {
+            max_cost_usd: Some(1.0),
+            max_tokens: Some(2000),
+        };
+        assert_eq!(state.exceeded_budget(&budget), None);
+
+        state.record_usage(&usage, Some(0.5));
+        let reason = state.exceeded_budget(&budget).unwrap();
+        assert!(reason.starts_with("cost $1.0000"), "{}", reason);
+
+        let budget = BudgetConfig {
+            max_cost_usd: None,
+            max_tokens: Some(2000),
+        };
+        assert!(state.exceeded_budget(&budget).unwrap().contains("2400 tokens"));
+    }
+
+    #[test]
+    fn test_take_hook_steering() {
+        let mut state = State::new();
+        state.prompt_append = Some("Focus on the parser.".to_string());
+        state.model_override = Some("opus".to_string());
+        state.metadata.insert("ticket-id".to_string(), "ABC-1".to_string());
+
+        assert!(state
+            .to_env_vars()
+            .contains(&("FRESHER_META_TICKET_ID".to_string(), "ABC-1".to_string())));
+
+        let (prompt, model) = state.take_hook_steering("Build it.\n", "sonnet");
+        assert_eq!(prompt, "Build it.\n\nFocus on the parser.");
+        assert_eq!(model, "opus");
+
+        // Steering applies to one iteration only
+        let (prompt, model) = state.take_hook_steering("Build it.", "sonnet");
+        assert_eq!(prompt, "Build it.");
+        assert_eq!(model, "sonnet");
+        assert_eq!(state.metadata["ticket-id"], "ABC-1");
+    }
+}
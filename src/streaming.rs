@@ -1,8 +1,56 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
 use colored::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
 use tokio::io::{AsyncBufReadExt, BufReader as AsyncBufReader};
+use tokio::sync::broadcast;
+
+use crate::config::OutputConfig;
+use crate::guardrails::{Guardrails, Violation};
+use crate::scope::Scope;
+use crate::todos::{self, Todo};
+use crate::tui;
+
+/// Version of the stream-json schema modelled below
+///
+/// Bump it when the event types change, and update or extend the hand-written
+/// fixtures under `tests/fixtures/stream/` to match.
+pub const STREAM_SCHEMA_VERSION: u32 = 2;
+
+/// Event types that are parsed (or deliberately ignored) without a warning
+const KNOWN_EVENT_TYPES: &[&str] = &[
+    "system",
+    "assistant",
+    "user",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "result",
+    // Partial-message framing; carries nothing the content blocks don't
+    "message_start",
+    "message_delta",
+    "message_stop",
+];
+
+/// Content block and delta types that are parsed without a warning
+const KNOWN_BLOCK_TYPES: &[&str] = &[
+    "text",
+    "tool_use",
+    "tool_result",
+    "thinking",
+    "redacted_thinking",
+    "image",
+    "text_delta",
+    "input_json_delta",
+    "thinking_delta",
+    "signature_delta",
+];
 
 /// Event types from Claude Code stream-json output
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +102,11 @@ pub struct AssistantMessage {
 pub enum ContentBlock {
     Text { text: String },
     ToolUse { id: String, name: String, input: Value },
+    /// Extended thinking (the text may be empty when streamed as deltas)
+    Thinking {
+        #[serde(default)]
+        thinking: String,
+    },
     #[serde(other)]
     Other,
 }
@@ -67,6 +120,8 @@ pub struct UserEvent {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserMessage {
+    /// A plain string (e.g. a sub-agent's prompt) becomes a single text block
+    #[serde(deserialize_with = "user_content")]
     pub content: Vec<UserContentBlock>,
 }
 
@@ -74,11 +129,55 @@ pub struct UserMessage {
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum UserContentBlock {
-    ToolResult { tool_use_id: String, content: String },
+    ToolResult {
+        tool_use_id: String,
+        /// A string, or the text of an array of content blocks
+        #[serde(default, deserialize_with = "tool_result_text")]
+        content: String,
+        #[serde(default)]
+        is_error: Option<bool>,
+    },
+    Text { text: String },
     #[serde(other)]
     Other,
 }
 
+fn user_content<'de, D>(deserializer: D) -> std::result::Result<Vec<UserContentBlock>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Content {
+        Text(String),
+        Blocks(Vec<UserContentBlock>),
+    }
+    Ok(match Content::deserialize(deserializer)? {
+        Content::Text(text) => vec![UserContentBlock::Text { text }],
+        Content::Blocks(blocks) => blocks,
+    })
+}
+
+fn tool_result_text<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(text) => text,
+        Value::Array(blocks) => blocks
+            .iter()
+            .map(|block| match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => block.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+                Some(other) => format!("[{}]", other),
+                None => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContentBlockStartEvent {
     pub index: Option<usize>,
@@ -101,6 +200,7 @@ pub struct ContentBlockDeltaEvent {
 pub enum Delta {
     TextDelta { text: String },
     InputJsonDelta { partial_json: String },
+    ThinkingDelta { thinking: String },
     #[serde(other)]
     Other,
 }
@@ -120,15 +220,435 @@ pub struct ResultEvent {
     pub duration_api_ms: Option<u64>,
     pub num_turns: Option<u32>,
     pub result: Option<String>,
+    /// Run cost; the CLI reports it as `total_cost_usd`, older versions as
+    /// `cost_usd`
+    #[serde(alias = "total_cost_usd")]
     pub cost_usd: Option<f64>,
     pub session_id: Option<String>,
+    pub usage: Option<TokenUsage>,
     #[serde(flatten)]
     pub extra: Value,
 }
 
+/// How an agent run ended, from the result event's `subtype`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IterationOutcome {
+    Success,
+    /// Stopped by `--max-turns` before finishing
+    MaxTurns,
+    /// Failed while running (`error_during_execution` or any other error)
+    ExecutionError,
+    /// Ended without a result event (killed, crashed or Ctrl+C)
+    Interrupted,
+}
+
+impl IterationOutcome {
+    pub fn from_subtype(subtype: Option<&str>, is_error: bool) -> Self {
+        match subtype {
+            None => IterationOutcome::Interrupted,
+            Some("error_max_turns") => IterationOutcome::MaxTurns,
+            Some(s) if s.starts_with("error") || is_error => IterationOutcome::ExecutionError,
+            Some(_) => IterationOutcome::Success,
+        }
+    }
+}
+
+impl std::fmt::Display for IterationOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IterationOutcome::Success => write!(f, "success"),
+            IterationOutcome::MaxTurns => write!(f, "max_turns"),
+            IterationOutcome::ExecutionError => write!(f, "execution_error"),
+            IterationOutcome::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+/// Token counts from a result event's `usage` block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+}
+
+impl TokenUsage {
+    /// All tokens, cached or not
+    pub fn total(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_read_input_tokens
+            + self.cache_creation_input_tokens
+    }
+
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+    }
+
+    /// Share of prompt tokens served from the prompt cache (None without input)
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let prompt =
+            self.input_tokens + self.cache_read_input_tokens + self.cache_creation_input_tokens;
+        if prompt == 0 {
+            return None;
+        }
+        Some(self.cache_read_input_tokens as f64 / prompt as f64)
+    }
+
+    /// e.g. "12840 tokens (1200 in, 640 out, 11000 cached) · cache hit 86%"
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} tokens ({} in, {} out, {} cached)",
+            self.total(),
+            self.input_tokens + self.cache_creation_input_tokens,
+            self.output_tokens,
+            self.cache_read_input_tokens
+        );
+        if let Some(ratio) = self.cache_hit_ratio() {
+            summary.push_str(&format!(" · cache hit {:.0}%", ratio * 100.0));
+        }
+        summary
+    }
+}
+
 /// Parse a single JSON line into a StreamEvent
+///
+/// With partial messages on, raw API events arrive wrapped as
+/// `{"type":"stream_event","event":{...}}`; those are unwrapped.
 pub fn parse_event(line: &str) -> Result<StreamEvent> {
-    serde_json::from_str(line).context("Failed to parse stream event")
+    let value: Value = serde_json::from_str(line).context("Failed to parse stream event")?;
+    serde_json::from_value(normalize(value)).context("Failed to parse stream event")
+}
+
+/// Bring older and newer spellings of an event to the modelled schema
+///
+/// Unwraps a partial-message `stream_event` (keeping its parent tool use ID),
+/// and keeps one cost key on result events that carry both spellings.
+fn normalize(value: Value) -> Value {
+    let mut value = match value.get("type").and_then(|t| t.as_str()) {
+        Some("stream_event") => {
+            let mut event = value.get("event").cloned().unwrap_or(Value::Null);
+            // Keep the sub-agent the delta belongs to
+            if let (Some(inner), Some(parent)) = (event.as_object_mut(), value.get("parent_tool_use_id")) {
+                inner.insert("parent_tool_use_id".to_string(), parent.clone());
+            }
+            event
+        }
+        _ => value,
+    };
+    if value.get("type").and_then(|t| t.as_str()) == Some("result") {
+        if let Some(event) = value.as_object_mut() {
+            if event.contains_key("total_cost_usd") {
+                event.remove("cost_usd");
+            }
+        }
+    }
+    value
+}
+
+/// Types in a normalized event that this schema version doesn't know,
+/// e.g. `event rate_limit` or `content block server_tool_use`
+fn unknown_types(value: &Value) -> Vec<String> {
+    let type_of = |v: &Value| v.get("type").and_then(|t| t.as_str()).map(str::to_string);
+    let Some(event_type) = type_of(value) else {
+        return vec!["event without a type".to_string()];
+    };
+    if !KNOWN_EVENT_TYPES.contains(&event_type.as_str()) {
+        return vec![format!("event {}", event_type)];
+    }
+
+    let blocks = value
+        .pointer("/message/content")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .chain(value.get("content_block"))
+        .chain(value.get("delta"));
+    let mut unknown: Vec<String> = blocks
+        .filter_map(type_of)
+        .filter(|t| !KNOWN_BLOCK_TYPES.contains(&t.as_str()))
+        .map(|t| format!("content block {}", t))
+        .collect();
+    unknown.dedup();
+    unknown
+}
+
+/// Parses stream-json lines and remembers the unknown types it has seen
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    unknown: BTreeSet<String>,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one line; also returns the unknown types seen for the first time,
+    /// so each can be warned about once
+    pub fn parse(&mut self, line: &str) -> Result<(StreamEvent, Vec<String>)> {
+        let value: Value = serde_json::from_str(line).context("Failed to parse stream event")?;
+        let value = normalize(value);
+        let new_unknown = unknown_types(&value)
+            .into_iter()
+            .filter(|t| self.unknown.insert(t.clone()))
+            .collect();
+        let event = serde_json::from_value(value).context("Failed to parse stream event")?;
+        Ok((event, new_unknown))
+    }
+
+    /// Every unknown type seen so far
+    pub fn unknown(&self) -> &BTreeSet<String> {
+        &self.unknown
+    }
+}
+
+impl StreamEvent {
+    /// The Task call whose sub-agent produced this event (`None` for the main agent)
+    pub fn parent_tool_use_id(&self) -> Option<&str> {
+        let extra = match self {
+            StreamEvent::System(e) => &e.extra,
+            StreamEvent::Assistant(e) => &e.extra,
+            StreamEvent::User(e) => &e.extra,
+            StreamEvent::ContentBlockStart(e) => &e.extra,
+            StreamEvent::ContentBlockDelta(e) => &e.extra,
+            StreamEvent::ContentBlockStop(e) => &e.extra,
+            StreamEvent::Result(e) => &e.extra,
+            StreamEvent::Unknown => return None,
+        };
+        extra.get("parent_tool_use_id").and_then(|v| v.as_str())
+    }
+}
+
+/// A Task sub-agent and the tool calls made inside it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SubagentRun {
+    /// ID of the Task tool call that spawned it
+    pub id: String,
+    pub description: String,
+    /// Task call of the sub-agent that spawned this one, if nested
+    pub parent: Option<String>,
+    /// Nesting level (0 = spawned by the main agent)
+    pub depth: usize,
+    /// Time from the Task call to its result (`None` if it never returned)
+    pub duration_ms: Option<u64>,
+    /// Calls per tool name made directly inside this sub-agent
+    pub calls: BTreeMap<String, u32>,
+}
+
+impl SubagentRun {
+    pub fn total_calls(&self) -> u32 {
+        self.calls.values().sum()
+    }
+
+    /// e.g. `Explore parser · 12.3s · 8 calls (Read 5, Grep 3)`
+    pub fn summary(&self) -> String {
+        let duration = match self.duration_ms {
+            Some(ms) => format!("{:.1}s", ms as f64 / 1000.0),
+            None => "unfinished".to_string(),
+        };
+        let mut by_count: Vec<_> = self.calls.iter().collect();
+        by_count.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let breakdown = by_count
+            .iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut summary = format!("{} · {} · {} calls", self.description, duration, self.total_calls());
+        if !breakdown.is_empty() {
+            summary.push_str(&format!(" ({})", breakdown));
+        }
+        summary
+    }
+}
+
+/// Totals over sub-agent runs, e.g. `3 sub-agents · 52.4s · 30 calls`
+pub fn subagents_summary(runs: &[SubagentRun]) -> String {
+    let ms: u64 = runs.iter().filter_map(|r| r.duration_ms).sum();
+    let calls: u32 = runs.iter().map(SubagentRun::total_calls).sum();
+    format!("{} sub-agents · {:.1}s · {} calls", runs.len(), ms as f64 / 1000.0, calls)
+}
+
+/// Task invocations seen so far, in spawn order
+#[derive(Debug, Default)]
+struct SubagentTree {
+    runs: Vec<SubagentRun>,
+    started: HashMap<String, Instant>,
+}
+
+impl SubagentTree {
+    fn get(&self, id: &str) -> Option<&SubagentRun> {
+        self.runs.iter().find(|r| r.id == id)
+    }
+
+    /// Nesting level of events from the sub-agent `parent` (0 for the main agent)
+    fn depth(&self, parent: Option<&str>) -> usize {
+        match parent {
+            Some(id) => self.get(id).map(|r| r.depth + 1).unwrap_or(1),
+            None => 0,
+        }
+    }
+
+    /// Record a tool call; Task calls start a new sub-agent
+    fn call(&mut self, id: &str, name: &str, input: &Value, parent: Option<&str>) {
+        if let Some(run) = parent.and_then(|p| self.runs.iter_mut().find(|r| r.id == p)) {
+            *run.calls.entry(name.to_string()).or_insert(0) += 1;
+        }
+        if name == "Task" && self.get(id).is_none() {
+            let description = input
+                .get("description")
+                .and_then(|v| v.as_str())
+                .unwrap_or("sub-agent")
+                .to_string();
+            self.runs.push(SubagentRun {
+                id: id.to_string(),
+                description,
+                parent: parent.map(str::to_string),
+                depth: self.depth(parent),
+                ..Default::default()
+            });
+            self.started.insert(id.to_string(), Instant::now());
+        }
+    }
+
+    /// Record a tool result; returns the sub-agent if it was a Task's
+    fn finish(&mut self, id: &str) -> Option<SubagentRun> {
+        let started = self.started.remove(id)?;
+        let run = self.runs.iter_mut().find(|r| r.id == id)?;
+        run.duration_ms = Some(started.elapsed().as_millis() as u64);
+        Some(run.clone())
+    }
+}
+
+/// Indentation for output at a sub-agent nesting level
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth + 1)
+}
+
+/// A content block still being streamed
+#[derive(Debug)]
+enum OpenBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+        partial_json: String,
+    },
+}
+
+/// A content block completed from deltas
+#[derive(Debug, PartialEq)]
+enum StreamedBlock {
+    Text(String),
+    ToolUse { id: String, name: String, input: Value },
+}
+
+/// Assembles content blocks from partial-message deltas
+#[derive(Debug, Default)]
+struct PartialBlocks {
+    open: HashMap<usize, OpenBlock>,
+    /// Rendered from deltas; skipped when the full assistant message arrives
+    streamed_texts: Vec<String>,
+    streamed_tools: HashSet<String>,
+}
+
+impl PartialBlocks {
+    fn start(&mut self, index: usize, block: &ContentBlock) {
+        let open = match block {
+            ContentBlock::Text { text } => OpenBlock::Text(text.clone()),
+            ContentBlock::ToolUse { id, name, input } => OpenBlock::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+                partial_json: String::new(),
+            },
+            ContentBlock::Thinking { .. } | ContentBlock::Other => return,
+        };
+        self.open.insert(index, open);
+    }
+
+    /// Apply a delta; returns text to render right away
+    fn delta<'a>(&mut self, index: usize, delta: &'a Delta) -> Option<&'a str> {
+        match (self.open.get_mut(&index), delta) {
+            (Some(OpenBlock::Text(text)), Delta::TextDelta { text: chunk }) => {
+                text.push_str(chunk);
+                Some(chunk)
+            }
+            (
+                Some(OpenBlock::ToolUse { partial_json, .. }),
+                Delta::InputJsonDelta { partial_json: chunk },
+            ) => {
+                partial_json.push_str(chunk);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn stop(&mut self, index: usize) -> Option<StreamedBlock> {
+        match self.open.remove(&index)? {
+            OpenBlock::Text(text) => {
+                self.streamed_texts.push(text.clone());
+                Some(StreamedBlock::Text(text))
+            }
+            OpenBlock::ToolUse {
+                id,
+                name,
+                input,
+                partial_json,
+            } => {
+                // The start event carries an empty input; the deltas carry the real one
+                let input = serde_json::from_str(&partial_json).unwrap_or(input);
+                self.streamed_tools.insert(id.clone());
+                Some(StreamedBlock::ToolUse { id, name, input })
+            }
+        }
+    }
+
+    /// Whether this text block was already rendered (forgets it if so)
+    fn take_text(&mut self, text: &str) -> bool {
+        match self.streamed_texts.iter().position(|t| t == text) {
+            Some(i) => {
+                self.streamed_texts.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether this tool call was already rendered (forgets it if so)
+    fn take_tool(&mut self, id: &str) -> bool {
+        self.streamed_tools.remove(id)
+    }
+}
+
+/// Short description of a tool call's main argument (command, path, pattern, ...)
+pub fn tool_call_detail(input: &Value) -> Option<String> {
+    ["command", "file_path", "pattern", "description"]
+        .iter()
+        .find_map(|key| input.get(*key).and_then(|v| v.as_str()))
+        .map(|s| s.chars().take(120).collect())
+}
+
+/// Shorten `s` to at most `max` characters, marking the cut with "..."
+///
+/// Counts characters rather than bytes, so multibyte text is never split
+/// mid-character. `max == 0` means no limit.
+pub fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((end, _)) if max > 0 => format!("{}...", &s[..end]),
+        _ => s.to_string(),
+    }
 }
 
 /// Stream handler for processing Claude Code output
@@ -137,6 +657,29 @@ pub struct StreamHandler {
     pub show_tool_results: bool,
     pub show_text: bool,
     pub verbose: bool,
+    /// Maximum characters of a tool result preview (0 = no limit)
+    pub truncate: usize,
+    /// Only show calls to these tools, and their results (empty = all)
+    pub tools: Vec<String>,
+    /// Deny patterns checked against each tool call
+    pub guardrails: Guardrails,
+    /// Warn when a shown Write/Edit targets a path outside this scope
+    pub scope: Option<Scope>,
+    /// Render `TodoWrite` calls as a checklist
+    pub show_todos: bool,
+    /// Receives a copy of every event (e.g. for the `--serve` dashboard)
+    pub event_tx: Option<broadcast::Sender<StreamEvent>>,
+    /// Render text and tool calls from partial-message deltas as they arrive
+    pub partial: bool,
+    partial_blocks: Mutex<PartialBlocks>,
+    /// IDs of tool calls hidden by `tools`, so their results are hidden too
+    hidden_calls: Mutex<HashSet<String>>,
+    /// First guardrail violation seen, until `process_stream` takes it
+    violation: Mutex<Option<Violation>>,
+    /// Task sub-agents, until `process_stream` takes them
+    subagents: Mutex<SubagentTree>,
+    /// Last todo list printed, so unchanged lists are not printed again
+    shown_todos: Mutex<Vec<Todo>>,
 }
 
 impl Default for StreamHandler {
@@ -146,6 +689,18 @@ impl Default for StreamHandler {
             show_tool_results: false,
             show_text: true,
             verbose: false,
+            truncate: 200,
+            tools: Vec::new(),
+            guardrails: Guardrails::default(),
+            scope: None,
+            show_todos: true,
+            event_tx: None,
+            partial: false,
+            partial_blocks: Mutex::new(PartialBlocks::default()),
+            hidden_calls: Mutex::new(HashSet::new()),
+            violation: Mutex::new(None),
+            subagents: Mutex::new(SubagentTree::default()),
+            shown_todos: Mutex::new(Vec::new()),
         }
     }
 }
@@ -155,13 +710,73 @@ impl StreamHandler {
         Self::default()
     }
 
+    /// Build a handler from `[output]` config and the CLI verbosity level
+    ///
+    /// `-q` (-1) prints nothing from the stream; `-v` (1) adds tool results
+    /// and event details; `-vv` (2) also stops truncating tool results.
+    pub fn from_output(output: &OutputConfig, verbosity: i8) -> Self {
+        let quiet = verbosity < 0;
+        Self {
+            show_tool_calls: !quiet,
+            show_tool_results: !quiet && (output.show_tool_results || verbosity >= 1),
+            show_text: !quiet && !output.hide_text,
+            verbose: verbosity >= 1,
+            truncate: if verbosity >= 2 { 0 } else { output.truncate },
+            tools: output.tools.clone(),
+            ..Self::default()
+        }
+    }
+
     pub fn verbose(mut self, verbose: bool) -> Self {
         self.verbose = verbose;
         self
     }
 
+    pub fn event_tx(mut self, event_tx: Option<broadcast::Sender<StreamEvent>>) -> Self {
+        self.event_tx = event_tx;
+        self
+    }
+
+    pub fn partial(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    pub fn guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    pub fn scope(mut self, scope: Option<Scope>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn show_todos(mut self, show_todos: bool) -> Self {
+        self.show_todos = show_todos;
+        self
+    }
+
+    /// The guardrail violation seen since the last call, if any
+    pub fn take_violation(&self) -> Option<Violation> {
+        self.violation.lock().unwrap().take()
+    }
+
+    /// The sub-agents spawned since the last call, in spawn order
+    pub fn take_subagents(&self) -> Vec<SubagentRun> {
+        std::mem::take(&mut *self.subagents.lock().unwrap()).runs
+    }
+
     /// Process a single event and print appropriate output
     pub fn handle_event(&self, event: &StreamEvent) {
+        if let Some(tx) = &self.event_tx {
+            // No receivers is fine; nobody is watching
+            let _ = tx.send(event.clone());
+        }
+
+        let parent = event.parent_tool_use_id();
+        let depth = self.subagents.lock().unwrap().depth(parent);
+
         match event {
             StreamEvent::System(e) => {
                 if self.verbose {
@@ -175,13 +790,32 @@ impl StreamHandler {
                     for block in &msg.content {
                         match block {
                             ContentBlock::Text { text } => {
-                                if self.show_text && !text.is_empty() {
-                                    println!("{}", text);
+                                let streamed = self.streamed_text(text);
+                                if self.show_text && !text.is_empty() && !streamed {
+                                    if depth == 0 {
+                                        println!("{}", text);
+                                    } else {
+                                        for line in text.lines() {
+                                            println!("{}{}", indent(depth), line.dimmed());
+                                        }
+                                    }
+                                }
+                            }
+                            ContentBlock::ToolUse { id, name, input } => {
+                                self.check_guardrails(name, input);
+                                self.subagents.lock().unwrap().call(id, name, input, parent);
+                                let streamed = self.streamed_tool(id);
+                                if !self.shows_tool(id, name) {
+                                    continue;
+                                }
+                                if self.show_tool_calls && !streamed {
+                                    self.print_tool_call(name, input, depth);
                                 }
                             }
-                            ContentBlock::ToolUse { name, input, .. } => {
-                                if self.show_tool_calls {
-                                    self.print_tool_call(name, input);
+                            ContentBlock::Thinking { thinking } => {
+                                if self.verbose && !thinking.is_empty() {
+                                    let preview = truncate(thinking.trim(), self.truncate);
+                                    println!("{}{} {}", indent(depth), "[thinking]".dimmed(), preview.dimmed());
                                 }
                             }
                             ContentBlock::Other => {}
@@ -190,16 +824,25 @@ impl StreamHandler {
                 }
             }
             StreamEvent::User(e) => {
-                if self.show_tool_results {
-                    if let Some(msg) = &e.message {
-                        for block in &msg.content {
-                            if let UserContentBlock::ToolResult { content, .. } = block {
-                                let preview = if content.len() > 200 {
-                                    format!("{}...", &content[..200])
-                                } else {
-                                    content.clone()
-                                };
-                                println!("  {} {}", "→".dimmed(), preview.dimmed());
+                if let Some(msg) = &e.message {
+                    for block in &msg.content {
+                        if let UserContentBlock::ToolResult { tool_use_id, content, .. } = block {
+                            let finished = self.subagents.lock().unwrap().finish(tool_use_id);
+                            let hidden = self.hidden_calls.lock().unwrap().remove(tool_use_id);
+                            if let (Some(run), true, false) = (&finished, self.show_tool_calls, hidden)
+                            {
+                                if self.tools.is_empty() || self.tools.iter().any(|t| t == "Task") {
+                                    println!(
+                                        "{}{} {}",
+                                        indent(run.depth),
+                                        "✓ Task:".magenta(),
+                                        run.summary().dimmed()
+                                    );
+                                }
+                            }
+                            if self.show_tool_results && !hidden {
+                                let preview = truncate(content, self.truncate);
+                                println!("{}{} {}", indent(depth), "→".dimmed(), preview.dimmed());
                             }
                         }
                     }
@@ -207,18 +850,49 @@ impl StreamHandler {
             }
             StreamEvent::ContentBlockStart(e) => {
                 if self.verbose {
-                    if let Some(block) = &e.content_block {
-                        if let ContentBlock::ToolUse { name, .. } = block {
-                            println!("  {} {}", "starting:".dimmed(), name.yellow());
-                        }
+                    if let Some(ContentBlock::ToolUse { name, .. }) = &e.content_block {
+                        println!("  {} {}", "starting:".dimmed(), name.yellow());
                     }
                 }
+                if let (true, Some(block)) = (self.partial, &e.content_block) {
+                    self.partial_blocks
+                        .lock()
+                        .unwrap()
+                        .start(e.index.unwrap_or(0), block);
+                }
             }
-            StreamEvent::ContentBlockDelta(_) => {
-                // Streaming deltas - usually not printed in loop mode
+            StreamEvent::ContentBlockDelta(e) => {
+                // Deltas are only rendered in partial mode
+                if let (true, Some(delta)) = (self.partial, &e.delta) {
+                    let mut blocks = self.partial_blocks.lock().unwrap();
+                    if let Some(text) = blocks.delta(e.index.unwrap_or(0), delta) {
+                        if self.show_text {
+                            print!("{}", text);
+                            let _ = std::io::stdout().flush();
+                        }
+                    }
+                }
             }
-            StreamEvent::ContentBlockStop(_) => {
-                // Block complete
+            StreamEvent::ContentBlockStop(e) => {
+                if self.partial {
+                    let finished = self.partial_blocks.lock().unwrap().stop(e.index.unwrap_or(0));
+                    // Tool input is complete here, before the assistant message is
+                    if let Some(StreamedBlock::ToolUse { name, input, .. }) = &finished {
+                        self.check_guardrails(name, input);
+                    }
+                    match finished {
+                        // End the line the text deltas were printed on
+                        Some(StreamedBlock::Text(text)) if self.show_text && !text.is_empty() => {
+                            println!();
+                        }
+                        Some(StreamedBlock::ToolUse { id, name, input })
+                            if self.show_tool_calls && self.shows_tool(&id, &name) =>
+                        {
+                            self.print_tool_call(&name, &input, depth);
+                        }
+                        _ => {}
+                    }
+                }
             }
             StreamEvent::Result(e) => {
                 if let Some(result) = &e.result {
@@ -240,6 +914,9 @@ impl StreamHandler {
                     if let Some(turns) = e.num_turns {
                         println!("{} {}", "Turns:".dimmed(), turns);
                     }
+                    if let Some(usage) = &e.usage {
+                        println!("{} {}", "Tokens:".dimmed(), usage.summary());
+                    }
                 }
             }
             StreamEvent::Unknown => {
@@ -250,16 +927,38 @@ impl StreamHandler {
         }
     }
 
-    fn print_tool_call(&self, name: &str, input: &Value) {
+    fn streamed_text(&self, text: &str) -> bool {
+        self.partial && self.partial_blocks.lock().unwrap().take_text(text)
+    }
+
+    fn streamed_tool(&self, id: &str) -> bool {
+        self.partial && self.partial_blocks.lock().unwrap().take_tool(id)
+    }
+
+    fn check_guardrails(&self, name: &str, input: &Value) {
+        if let Some(violation) = self.guardrails.check(name, input) {
+            self.violation.lock().unwrap().get_or_insert(violation);
+        }
+    }
+
+    /// Whether the `tools` filter lets this call through; remembers hidden
+    /// calls so their results can be skipped as well
+    fn shows_tool(&self, id: &str, name: &str) -> bool {
+        if self.tools.is_empty() || self.tools.iter().any(|t| t == name) {
+            return true;
+        }
+        if self.show_tool_results {
+            self.hidden_calls.lock().unwrap().insert(id.to_string());
+        }
+        false
+    }
+
+    fn print_tool_call(&self, name: &str, input: &Value, depth: usize) {
+        let mut checklist = Vec::new();
         let formatted = match name {
             "Bash" => {
                 if let Some(cmd) = input.get("command").and_then(|v| v.as_str()) {
-                    let preview = if cmd.len() > 100 {
-                        format!("{}...", &cmd[..100])
-                    } else {
-                        cmd.to_string()
-                    };
-                    format!("{} {}", "Bash:".blue().bold(), preview)
+                    format!("{} {}", "Bash:".blue().bold(), truncate(cmd, 100))
                 } else {
                     format!("{}", "Bash".blue().bold())
                 }
@@ -299,14 +998,139 @@ impl StreamHandler {
                     format!("{}", "Task".magenta().bold())
                 }
             }
-            "TodoWrite" => {
-                format!("{}", "TodoWrite".cyan().bold())
-            }
+            // Sub-agents keep lists of their own; only the main agent's is tracked
+            "TodoWrite" => match todos::parse_todos(input) {
+                Some(list) if self.show_todos && depth == 0 => {
+                    let mut shown = self.shown_todos.lock().unwrap();
+                    let header = format!("{} {}", "Todos:".cyan().bold(), todos::progress(&list));
+                    if *shown == list {
+                        format!("{} {}", header, "(unchanged)".dimmed())
+                    } else {
+                        checklist = todos::checklist(&list);
+                        *shown = list;
+                        header
+                    }
+                }
+                _ => format!("{}", "TodoWrite".cyan().bold()),
+            },
             _ => {
                 format!("{}", name.bold())
             }
         };
-        println!("  {} {}", "→".dimmed(), formatted);
+        println!("{}{} {}", indent(depth), "→".dimmed(), formatted);
+        for line in checklist {
+            println!("{}    {}", indent(depth), line);
+        }
+
+        // Early warning; the iteration's changes are checked once it ends
+        let project_dir = std::env::current_dir().unwrap_or_default();
+        if let Some(path) = self
+            .scope
+            .as_ref()
+            .and_then(|scope| scope.check_tool(name, input, &project_dir))
+        {
+            println!("{}  {} {} is out of scope", indent(depth), "⚠".yellow(), path);
+        }
+    }
+}
+
+/// Tool usage aggregated from the `ToolUse` blocks of an iteration
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolStats {
+    /// Calls per tool name
+    pub calls: BTreeMap<String, u32>,
+    pub files_read: BTreeSet<String>,
+    pub files_written: BTreeSet<String>,
+    pub files_edited: BTreeSet<String>,
+    pub bash_commands: Vec<String>,
+    /// Descriptions of the Task sub-agents spawned
+    pub subagents: Vec<String>,
+}
+
+impl ToolStats {
+    /// Record one tool call
+    pub fn record(&mut self, name: &str, input: &Value) {
+        *self.calls.entry(name.to_string()).or_insert(0) += 1;
+
+        let str_field = |key: &str| input.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        match name {
+            "Read" => self.files_read.extend(str_field("file_path")),
+            "Write" => self.files_written.extend(str_field("file_path")),
+            "Edit" | "MultiEdit" => self.files_edited.extend(str_field("file_path")),
+            "NotebookEdit" => self.files_edited.extend(str_field("notebook_path")),
+            "Bash" => self.bash_commands.extend(str_field("command")),
+            "Task" => self
+                .subagents
+                .push(str_field("description").unwrap_or_else(|| "sub-agent".to_string())),
+            _ => {}
+        }
+    }
+
+    /// Add another iteration's stats to these (for run totals)
+    pub fn merge(&mut self, other: &ToolStats) {
+        for (name, count) in &other.calls {
+            *self.calls.entry(name.clone()).or_insert(0) += count;
+        }
+        self.files_read.extend(other.files_read.iter().cloned());
+        self.files_written.extend(other.files_written.iter().cloned());
+        self.files_edited.extend(other.files_edited.iter().cloned());
+        self.bash_commands.extend(other.bash_commands.iter().cloned());
+        self.subagents.extend(other.subagents.iter().cloned());
+    }
+
+    pub fn total_calls(&self) -> u32 {
+        self.calls.values().sum()
+    }
+
+    /// One-line summary, e.g. `12 calls (Read 8, Bash 3, Edit 1) · 8 read · 0 written · 1 edited · 3 commands · 0 sub-agents`
+    pub fn summary(&self) -> String {
+        let mut by_count: Vec<_> = self.calls.iter().collect();
+        by_count.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let breakdown = by_count
+            .iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!(
+            "{} calls ({}) · {} read · {} written · {} edited · {} commands · {} sub-agents",
+            self.total_calls(),
+            if breakdown.is_empty() { "none".to_string() } else { breakdown },
+            self.files_read.len(),
+            self.files_written.len(),
+            self.files_edited.len(),
+            self.bash_commands.len(),
+            self.subagents.len()
+        )
+    }
+
+    /// Append a detailed report for `iteration` to its log file
+    pub fn append_to_log(&self, path: &Path, iteration: u32) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+        writeln!(
+            file,
+            "[{}] iteration {} tools: {}",
+            Utc::now().to_rfc3339(),
+            iteration,
+            self.summary()
+        )?;
+        let sections: [(&str, Vec<&String>); 5] = [
+            ("read", self.files_read.iter().collect()),
+            ("written", self.files_written.iter().collect()),
+            ("edited", self.files_edited.iter().collect()),
+            ("bash", self.bash_commands.iter().collect()),
+            ("sub-agents", self.subagents.iter().collect()),
+        ];
+        for (label, items) in sections {
+            for item in items {
+                writeln!(file, "  {}: {}", label, item)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -319,6 +1143,78 @@ pub struct ProcessResult {
     pub num_turns: Option<u32>,
     pub is_error: bool,
     pub result_text: Option<String>,
+    /// Tool usage during the iteration
+    pub tool_stats: ToolStats,
+    /// Token usage reported by the result event
+    pub usage: TokenUsage,
+    /// Result event subtype (`None` if no result event arrived)
+    pub subtype: Option<String>,
+    /// Guardrail violation that ended the stream early
+    pub violation: Option<Violation>,
+    /// Task sub-agents, in spawn order
+    pub subagents: Vec<SubagentRun>,
+    /// The main agent's last `TodoWrite` list
+    pub todos: Vec<Todo>,
+    /// Claude Code version from the `system` init event
+    pub claude_code_version: Option<String>,
+    /// Event and block types this schema version doesn't know
+    pub unknown_types: Vec<String>,
+    /// Lines that could not be parsed
+    pub parse_errors: u32,
+}
+
+impl ProcessResult {
+    pub fn outcome(&self) -> IterationOutcome {
+        IterationOutcome::from_subtype(self.subtype.as_deref(), self.is_error)
+    }
+
+    /// Why the iteration counts as failed
+    pub fn error_message(&self) -> String {
+        if self.exit_code != 0 {
+            format!("Claude exited with code {}", self.exit_code)
+        } else {
+            format!("Iteration ended with {}", self.outcome())
+        }
+    }
+
+    /// Append the outcome, token usage and tool report to the iteration log
+    pub fn append_to_log(&self, path: &Path, iteration: u32) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(
+            file,
+            "[{}] iteration {} outcome: {} (subtype {}, exit code {}, turns {})",
+            Utc::now().to_rfc3339(),
+            iteration,
+            self.outcome(),
+            self.subtype.as_deref().unwrap_or("none"),
+            self.exit_code,
+            self.num_turns.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string())
+        )?;
+        if self.usage.total() > 0 {
+            writeln!(file, "  tokens: {}", self.usage.summary())?;
+        }
+        writeln!(
+            file,
+            "  stream: schema v{}, claude code {}, {} unparsed lines",
+            STREAM_SCHEMA_VERSION,
+            self.claude_code_version.as_deref().unwrap_or("unknown"),
+            self.parse_errors
+        )?;
+        if !self.unknown_types.is_empty() {
+            writeln!(file, "  unknown stream types: {}", self.unknown_types.join(", "))?;
+        }
+        if let Some(violation) = &self.violation {
+            writeln!(file, "  guardrail violation: {}", violation)?;
+        }
+        for run in &self.subagents {
+            writeln!(file, "{}sub-agent: {}", indent(run.depth), run.summary())?;
+        }
+        drop(file);
+        self.tool_stats.append_to_log(path, iteration)
+    }
 }
 
 /// Process Claude Code stream output and return summary
@@ -329,6 +1225,7 @@ pub async fn process_stream<R: tokio::io::AsyncRead + Unpin>(
     let mut buf_reader = AsyncBufReader::new(reader);
     let mut line = String::new();
     let mut result = ProcessResult::default();
+    let mut parser = StreamParser::new();
 
     loop {
         line.clear();
@@ -342,10 +1239,39 @@ pub async fn process_stream<R: tokio::io::AsyncRead + Unpin>(
             continue;
         }
 
-        match parse_event(trimmed) {
-            Ok(event) => {
+        match parser.parse(trimmed) {
+            Ok((event, new_unknown)) => {
+                for unknown in new_unknown {
+                    tui::eprint(format!(
+                        "{} unrecognised stream {} (schema v{}); ignoring it",
+                        "Warning:".yellow(),
+                        unknown,
+                        STREAM_SCHEMA_VERSION
+                    ));
+                }
                 handler.handle_event(&event);
 
+                if let StreamEvent::System(e) = &event {
+                    if let Some(version) = e.extra.get("claude_code_version").and_then(|v| v.as_str()) {
+                        result.claude_code_version = Some(version.to_string());
+                    }
+                }
+
+                // Complete tool calls arrive in assistant messages
+                if let StreamEvent::Assistant(AssistantEvent { message: Some(msg), .. }) = &event {
+                    let from_main_agent = event.parent_tool_use_id().is_none();
+                    for block in &msg.content {
+                        if let ContentBlock::ToolUse { name, input, .. } = block {
+                            result.tool_stats.record(name, input);
+                            if name == "TodoWrite" && from_main_agent {
+                                if let Some(list) = todos::parse_todos(input) {
+                                    result.todos = list;
+                                }
+                            }
+                        }
+                    }
+                }
+
                 // Capture result info
                 if let StreamEvent::Result(ref e) = event {
                     result.duration_ms = e.duration_ms;
@@ -353,17 +1279,29 @@ pub async fn process_stream<R: tokio::io::AsyncRead + Unpin>(
                     result.num_turns = e.num_turns;
                     result.is_error = e.is_error.unwrap_or(false);
                     result.result_text = e.result.clone();
+                    result.usage = e.usage.unwrap_or_default();
+                    result.subtype = Some(e.subtype.clone().unwrap_or_default());
+                }
+
+                // Stop reading; the caller kills the agent
+                if let Some(violation) = handler.take_violation() {
+                    result.violation = Some(violation);
+                    break;
                 }
             }
             Err(e) => {
+                result.parse_errors += 1;
                 if handler.verbose {
-                    eprintln!("Warning: failed to parse event: {}", e);
-                    eprintln!("  Line: {}", trimmed);
+                    tui::eprint(format!("Warning: failed to parse event: {}", e));
+                    tui::eprint(format!("  Line: {}", trimmed));
                 }
             }
         }
     }
 
+    result.subagents = handler.take_subagents();
+    result.unknown_types = parser.unknown().iter().cloned().collect();
+
     Ok(result)
 }
 
@@ -435,7 +1373,7 @@ mod tests {
                 let msg = e.message.unwrap();
                 assert_eq!(msg.content.len(), 1);
                 match &msg.content[0] {
-                    UserContentBlock::ToolResult { tool_use_id, content } => {
+                    UserContentBlock::ToolResult { tool_use_id, content, .. } => {
                         assert_eq!(tool_use_id, "tool1");
                         assert_eq!(content, "output text");
                     }
@@ -524,11 +1462,114 @@ mod tests {
                 assert_eq!(e.result, Some("Task completed".to_string()));
                 assert_eq!(e.cost_usd, Some(0.05));
                 assert_eq!(e.session_id, Some("xyz789".to_string()));
+                assert_eq!(e.usage, None);
             }
             _ => panic!("Expected Result event"),
         }
     }
 
+    #[tokio::test]
+    async fn test_process_stream_usage() {
+        let data = r#"{"type":"result","subtype":"success","cost_usd":0.02,"usage":{"input_tokens":120,"output_tokens":640,"cache_read_input_tokens":900,"cache_creation_input_tokens":180,"service_tier":"standard"}}"#;
+        let handler = StreamHandler::new();
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+
+        let usage = result.usage;
+        assert_eq!(usage.input_tokens, 120);
+        assert_eq!(usage.cache_creation_input_tokens, 180);
+        assert_eq!(usage.total(), 1840);
+        assert_eq!(usage.cache_hit_ratio(), Some(0.75));
+        assert_eq!(
+            usage.summary(),
+            "1840 tokens (300 in, 640 out, 900 cached) · cache hit 75%"
+        );
+    }
+
+    #[test]
+    fn test_iteration_outcome() {
+        assert_eq!(
+            IterationOutcome::from_subtype(Some("success"), false),
+            IterationOutcome::Success
+        );
+        assert_eq!(
+            IterationOutcome::from_subtype(Some("success"), true),
+            IterationOutcome::ExecutionError
+        );
+        assert_eq!(
+            IterationOutcome::from_subtype(Some("error_max_turns"), true),
+            IterationOutcome::MaxTurns
+        );
+        assert_eq!(
+            IterationOutcome::from_subtype(Some("error_during_execution"), true),
+            IterationOutcome::ExecutionError
+        );
+        assert_eq!(IterationOutcome::from_subtype(None, false), IterationOutcome::Interrupted);
+        assert_eq!(IterationOutcome::MaxTurns.to_string(), "max_turns");
+    }
+
+    #[tokio::test]
+    async fn test_process_stream_outcome() {
+        let handler = StreamHandler::new();
+        let data = r#"{"type":"result","subtype":"error_max_turns","is_error":true,"num_turns":50}"#;
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+        assert_eq!(result.outcome(), IterationOutcome::MaxTurns);
+
+        let data = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Working"}]}}"#;
+        let handler = StreamHandler {
+            show_text: false,
+            ..Default::default()
+        };
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+        assert_eq!(result.outcome(), IterationOutcome::Interrupted);
+    }
+
+    #[tokio::test]
+    async fn test_process_stream_stops_on_guardrail_violation() {
+        let guardrails = Guardrails::from_config(&crate::config::GuardrailsConfig {
+            deny: vec![r"git\s+push".to_string()],
+            rollback: false,
+        })
+        .unwrap();
+        let handler = StreamHandler {
+            show_text: false,
+            show_tool_calls: false,
+            ..Default::default()
+        }
+        .guardrails(guardrails);
+
+        let lines = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"git push origin main"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Pushed"}]}}"#,
+            r#"{"type":"result","subtype":"success"}"#,
+        ];
+        let result = process_stream(lines.join("\n").as_bytes(), &handler).await.unwrap();
+
+        let violation = result.violation.as_ref().unwrap();
+        assert_eq!(violation.command, "git push origin main");
+        assert_eq!(result.tool_stats.total_calls(), 2);
+        // Nothing after the violating call was read
+        assert_eq!(result.outcome(), IterationOutcome::Interrupted);
+        assert!(handler.take_violation().is_none());
+    }
+
+    #[test]
+    fn test_token_usage_add() {
+        let mut total = TokenUsage::default();
+        assert_eq!(total.cache_hit_ratio(), None);
+
+        let usage = TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_read_input_tokens: 30,
+            cache_creation_input_tokens: 0,
+        };
+        total.add(&usage);
+        total.add(&usage);
+        assert_eq!(total.total(), 90);
+        assert_eq!(total.output_tokens, 10);
+    }
+
     #[test]
     fn test_parse_event_unknown() {
         let json = r#"{"type":"some_new_type","data":"value"}"#;
@@ -568,6 +1609,93 @@ mod tests {
         assert!(handler.verbose);
     }
 
+    #[test]
+    fn test_truncate_is_char_safe() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello world", 5), "hello...");
+        assert_eq!(truncate("héllo wörld", 7), "héllo w...");
+        // Byte 200 falls inside a character here; slicing bytes would panic
+        let cut = truncate(&"日本語".repeat(100), 200);
+        assert_eq!(cut.chars().count(), 203);
+        assert!(cut.ends_with("日本..."));
+        assert_eq!(truncate("no limit", 0), "no limit");
+    }
+
+    #[test]
+    fn test_stream_handler_from_output() {
+        let output = OutputConfig {
+            hide_text: true,
+            tools: vec!["Bash".to_string()],
+            ..Default::default()
+        };
+
+        let handler = StreamHandler::from_output(&output, 0);
+        assert!(!handler.show_text);
+        assert!(handler.show_tool_calls);
+        assert!(!handler.show_tool_results);
+        assert_eq!(handler.truncate, 200);
+        assert_eq!(handler.tools, vec!["Bash"]);
+
+        let handler = StreamHandler::from_output(&output, 1);
+        assert!(handler.verbose);
+        assert!(handler.show_tool_results);
+        assert_eq!(handler.truncate, 200);
+
+        let handler = StreamHandler::from_output(&output, 2);
+        assert_eq!(handler.truncate, 0);
+
+        let handler = StreamHandler::from_output(&OutputConfig::default(), -1);
+        assert!(!handler.show_text);
+        assert!(!handler.show_tool_calls);
+        assert!(!handler.show_tool_results);
+    }
+
+    #[test]
+    fn test_tool_filter_hides_calls_and_results() {
+        let handler = StreamHandler {
+            show_tool_calls: false,
+            show_tool_results: true,
+            tools: vec!["Bash".to_string()],
+            ..Default::default()
+        };
+
+        let lines = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a.rs"}},{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"ls"}}]}}"#,
+        ];
+        for line in lines {
+            handler.handle_event(&parse_event(line).unwrap());
+        }
+        assert!(handler.hidden_calls.lock().unwrap().contains("t1"));
+        assert!(!handler.hidden_calls.lock().unwrap().contains("t2"));
+
+        let result = r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"fn main() {}"}]}}"#;
+        handler.handle_event(&parse_event(result).unwrap());
+        assert!(handler.hidden_calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tool_call_detail() {
+        let input = serde_json::json!({"command": "cargo test", "timeout": 10});
+        assert_eq!(tool_call_detail(&input), Some("cargo test".to_string()));
+
+        let input = serde_json::json!({"file_path": "src/main.rs"});
+        assert_eq!(tool_call_detail(&input), Some("src/main.rs".to_string()));
+
+        assert_eq!(tool_call_detail(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_stream_handler_forwards_events() {
+        let (tx, mut rx) = broadcast::channel(4);
+        let handler = StreamHandler::new().event_tx(Some(tx));
+        handler.handle_event(&parse_event(r#"{"type":"result","num_turns":3}"#).unwrap());
+
+        match rx.try_recv().unwrap() {
+            StreamEvent::Result(e) => assert_eq!(e.num_turns, Some(3)),
+            _ => panic!("Expected Result event"),
+        }
+    }
+
     #[test]
     fn test_process_result_default() {
         let result = ProcessResult::default();
@@ -629,6 +1757,259 @@ mod tests {
         assert!(result.is_error);
     }
 
+    #[tokio::test]
+    async fn test_process_stream_tool_stats() {
+        let lines = [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"t0","name":"Read","input":{}}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/a.rs"}},{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"src/a.rs"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t3","name":"Edit","input":{"file_path":"src/a.rs"}},{"type":"tool_use","id":"t4","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t5","name":"Task","input":{"description":"Explore parser"}}]}}"#,
+        ];
+        let data = lines.join("\n");
+        let handler = StreamHandler {
+            show_tool_calls: false,
+            ..Default::default()
+        };
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+
+        let stats = result.tool_stats;
+        assert_eq!(stats.total_calls(), 5);
+        assert_eq!(stats.calls["Read"], 2);
+        assert_eq!(stats.files_read.len(), 1);
+        assert!(stats.files_edited.contains("src/a.rs"));
+        assert_eq!(stats.bash_commands, vec!["cargo test"]);
+        assert_eq!(stats.subagents, vec!["Explore parser"]);
+        assert!(stats.summary().starts_with("5 calls (Read 2, Bash 1, Edit 1, Task 1) · 1 read"));
+    }
+
+    #[tokio::test]
+    async fn test_process_stream_tracks_subagents() {
+        let lines = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"task1","name":"Task","input":{"description":"Explore parser"}}]},"parent_tool_use_id":null}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"r1","name":"Read","input":{"file_path":"src/a.rs"}}]},"parent_tool_use_id":"task1"}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"r1","content":"..."}]},"parent_tool_use_id":"task1"}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"task2","name":"Task","input":{"description":"Check tests"}}]},"parent_tool_use_id":"task1"}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"g1","name":"Grep","input":{"pattern":"fn"}},{"type":"tool_use","id":"g2","name":"Grep","input":{"pattern":"mod"}}]},"parent_tool_use_id":"task2"}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"task2","content":"done"}]},"parent_tool_use_id":"task1"}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"task1","content":"done"}]},"parent_tool_use_id":null}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"task3","name":"Task","input":{}}]}}"#,
+        ];
+        let data = lines.join("\n");
+        let handler = StreamHandler {
+            show_tool_calls: false,
+            ..Default::default()
+        };
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+
+        let runs = &result.subagents;
+        assert_eq!(runs.len(), 3);
+        assert_eq!((runs[0].description.as_str(), runs[0].depth), ("Explore parser", 0));
+        assert_eq!(runs[0].calls.len(), 2);
+        assert_eq!(runs[0].calls["Task"], 1);
+        assert!(runs[0].duration_ms.is_some());
+        assert_eq!(runs[1].parent.as_deref(), Some("task1"));
+        assert_eq!(runs[1].depth, 1);
+        assert_eq!(runs[1].calls["Grep"], 2);
+        assert_eq!(runs[2].description, "sub-agent");
+        assert!(runs[2].summary().starts_with("sub-agent · unfinished · 0 calls"));
+        assert!(runs[1].summary().ends_with("2 calls (Grep 2)"));
+        assert!(subagents_summary(runs).starts_with("3 sub-agents · "));
+        assert!(subagents_summary(runs).ends_with(" · 4 calls"));
+
+        // Nested calls still count toward the iteration's tool stats
+        assert_eq!(result.tool_stats.total_calls(), 6);
+        assert!(handler.take_subagents().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_stream_keeps_last_todo_list() {
+        let lines = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"w1","name":"TodoWrite","input":{"todos":[{"content":"Add flag","status":"in_progress"}]}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"w2","name":"TodoWrite","input":{"todos":[{"content":"Add flag","status":"completed"},{"content":"Test flag","status":"pending"}]}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"w3","name":"TodoWrite","input":{"todos":[{"content":"Sub-agent list","status":"pending"}]}}]},"parent_tool_use_id":"task1"}"#,
+        ];
+        let data = lines.join("\n");
+        let handler = StreamHandler::default();
+        let result = process_stream(data.as_bytes(), &handler).await.unwrap();
+
+        // Sub-agent lists don't replace the main agent's, here or on screen
+        assert_eq!(*handler.shown_todos.lock().unwrap(), result.todos);
+        assert_eq!(result.todos.len(), 2);
+        assert_eq!(result.todos[0].status, crate::todos::TodoStatus::Completed);
+        assert_eq!(crate::todos::progress(&result.todos), "1/2");
+    }
+
+    #[test]
+    fn test_stream_parser_reports_unknown_types_once() {
+        let mut parser = StreamParser::new();
+        let (_, unknown) = parser.parse(r#"{"type":"rate_limit","status":"allowed"}"#).unwrap();
+        assert_eq!(unknown, vec!["event rate_limit"]);
+        let (event, unknown) = parser.parse(r#"{"type":"rate_limit","status":"allowed"}"#).unwrap();
+        assert!(matches!(event, StreamEvent::Unknown));
+        assert!(unknown.is_empty());
+
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"a"},{"type":"web_search_tool_result","content":[]}]}}"#;
+        assert_eq!(parser.parse(line).unwrap().1, vec!["content block web_search_tool_result"]);
+        let line = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"citations_delta"}}}"#;
+        assert_eq!(parser.parse(line).unwrap().1, vec!["content block citations_delta"]);
+        let line = r#"{"type":"stream_event","event":{"type":"message_stop"}}"#;
+        assert!(parser.parse(line).unwrap().1.is_empty());
+        assert_eq!(parser.unknown().len(), 3);
+    }
+
+    #[test]
+    fn test_parse_event_schema_variants() {
+        let json = r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Plan first.","signature":"abc"}]}}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::Assistant(e) => assert!(matches!(
+                &e.message.unwrap().content[0],
+                ContentBlock::Thinking { thinking } if thinking == "Plan first."
+            )),
+            _ => panic!("Expected Assistant event"),
+        }
+
+        let json = r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"one"},{"type":"image","source":{}}],"is_error":true}]}}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::User(e) => match &e.message.unwrap().content[0] {
+                UserContentBlock::ToolResult { content, is_error, .. } => {
+                    assert_eq!(content, "one\n[image]");
+                    assert_eq!(*is_error, Some(true));
+                }
+                _ => panic!("Expected ToolResult"),
+            },
+            _ => panic!("Expected User event"),
+        }
+
+        let json = r#"{"type":"user","message":{"role":"user","content":"Explore the parser"}}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::User(e) => assert!(matches!(
+                &e.message.unwrap().content[0],
+                UserContentBlock::Text { text } if text == "Explore the parser"
+            )),
+            _ => panic!("Expected User event"),
+        }
+    }
+
+    #[test]
+    fn test_parse_result_event_total_cost() {
+        // As printed by `claude -p --output-format stream-json --verbose`
+        let json = r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":2875,"duration_api_ms":4172,"num_turns":1,"result":"Done.","session_id":"8d1c6a52-3f0e-4b6b-9c1e-2f4f0b8f6a11","total_cost_usd":0.0172146,"usage":{"input_tokens":4,"cache_creation_input_tokens":4302,"cache_read_input_tokens":13567,"output_tokens":12,"server_tool_use":{"web_search_requests":0},"service_tier":"standard"},"permission_denials":[],"uuid":"0b6f5e0e-7a3c-4d7e-8f55-3c2a1e9d4b70"}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::Result(e) => {
+                assert_eq!(e.cost_usd, Some(0.0172146));
+                assert_eq!(e.usage.unwrap().output_tokens, 12);
+            }
+            _ => panic!("Expected Result event"),
+        }
+
+        // Both spellings at once is not a duplicate field
+        let json = r#"{"type":"result","subtype":"success","cost_usd":0.1,"total_cost_usd":0.3}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::Result(e) => assert_eq!(e.cost_usd, Some(0.3)),
+            _ => panic!("Expected Result event"),
+        }
+    }
+
+    #[test]
+    fn test_parse_event_keeps_parent_tool_use_id() {
+        let json = r#"{"type":"stream_event","parent_tool_use_id":"task1","event":{"type":"content_block_stop","index":0}}"#;
+        assert_eq!(parse_event(json).unwrap().parent_tool_use_id(), Some("task1"));
+
+        let json = r#"{"type":"assistant","message":{"content":[]},"parent_tool_use_id":null}"#;
+        assert_eq!(parse_event(json).unwrap().parent_tool_use_id(), None);
+    }
+
+    #[test]
+    fn test_parse_event_unwraps_stream_event() {
+        let json = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hel"}}}"#;
+        match parse_event(json).unwrap() {
+            StreamEvent::ContentBlockDelta(e) => {
+                assert_eq!(e.index, Some(1));
+                assert!(matches!(e.delta, Some(Delta::TextDelta { ref text }) if text == "Hel"));
+            }
+            _ => panic!("Expected ContentBlockDelta event"),
+        }
+    }
+
+    #[test]
+    fn test_partial_blocks_assemble_tool_input() {
+        let mut blocks = PartialBlocks::default();
+        blocks.start(
+            0,
+            &ContentBlock::ToolUse {
+                id: "t1".to_string(),
+                name: "Bash".to_string(),
+                input: serde_json::json!({}),
+            },
+        );
+        for chunk in ["{\"comm", "and\": \"cargo", " test\"}"] {
+            let delta = Delta::InputJsonDelta {
+                partial_json: chunk.to_string(),
+            };
+            assert!(blocks.delta(0, &delta).is_none());
+        }
+
+        assert_eq!(
+            blocks.stop(0),
+            Some(StreamedBlock::ToolUse {
+                id: "t1".to_string(),
+                name: "Bash".to_string(),
+                input: serde_json::json!({"command": "cargo test"}),
+            })
+        );
+        assert!(blocks.stop(0).is_none());
+    }
+
+    #[test]
+    fn test_partial_mode_does_not_render_twice() {
+        let handler = StreamHandler {
+            show_text: false,
+            show_tool_calls: false,
+            ..Default::default()
+        }
+        .partial(true);
+
+        let lines = [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Looking "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"around"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"t1","name":"Read","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"file_path\":\"a.rs\"}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+        ];
+        for line in lines {
+            handler.handle_event(&parse_event(line).unwrap());
+        }
+        {
+            let blocks = handler.partial_blocks.lock().unwrap();
+            assert_eq!(blocks.streamed_texts, vec!["Looking around"]);
+            assert!(blocks.streamed_tools.contains("t1"));
+        }
+
+        // The complete message repeats both blocks; they were already shown
+        let message = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Looking around"},{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a.rs"}}]}}"#;
+        handler.handle_event(&parse_event(message).unwrap());
+        assert!(!handler.streamed_text("Looking around"));
+        assert!(!handler.streamed_tool("t1"));
+    }
+
+    #[test]
+    fn test_tool_stats_merge() {
+        let mut total = ToolStats::default();
+        let mut iteration = ToolStats::default();
+        iteration.record("Read", &serde_json::json!({"file_path": "a.rs"}));
+        iteration.record("Write", &serde_json::json!({"file_path": "b.rs"}));
+
+        total.merge(&iteration);
+        total.merge(&iteration);
+
+        assert_eq!(total.calls["Read"], 2);
+        assert_eq!(total.files_read.len(), 1);
+        assert_eq!(total.files_written.len(), 1);
+        assert!(ToolStats::default().summary().starts_with("0 calls (none) · 0 read"));
+    }
+
     #[test]
     fn test_parse_event_assistant_multiple_blocks() {
         let json = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"First"},{"type":"text","text":"Second"}]}}"#;
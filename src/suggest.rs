@@ -0,0 +1,327 @@
+//! Suggested requirement refs for tasks, from text similarity
+//!
+//! Plans with few `(refs: ...)` annotations show no coverage. This matches
+//! task descriptions against the requirements from `extract_requirements`
+//! (each read with its heading) offline, with TF-IDF vectors compared by
+//! cosine similarity, and suggests the likeliest requirement for each task.
+//! Requirements that neither a ref nor a suggestion reaches are reported as
+//! probably uncovered.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::verify::{requirement_ref, spec_name, PlanTasks, Requirement, RequirementType, Task};
+
+/// Suggestions below this confidence are dropped
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.3;
+
+/// Confidence from which a suggestion is shown as a strong match
+pub const STRONG_CONFIDENCE: f64 = 0.6;
+
+/// Confidence from which a suggestion is shown as a fair match
+pub const FAIR_CONFIDENCE: f64 = 0.45;
+
+/// A likely link between a task and a requirement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub file: PathBuf,
+    pub line_number: usize,
+    pub task: String,
+    pub requirement: String,
+    pub spec_name: String,
+    pub requirement_text: String,
+    /// Cosine similarity, 0 to 1
+    pub confidence: f64,
+}
+
+/// Suggested links, and requirements no task seems to cover
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Suggestions {
+    pub suggestions: Vec<Suggestion>,
+    pub probably_uncovered: Vec<Requirement>,
+}
+
+/// Lowercase, crudely stemmed words of three or more letters, minus filler
+/// and RFC 2119 keywords
+fn terms(text: &str) -> Vec<String> {
+    const FILLER: &[&str] = &[
+        "the", "and", "for", "with", "from", "into", "that", "this", "when", "then", "are",
+        "not", "all", "any", "its", "has", "have", "can", "will", "use", "each", "must",
+        "shall", "should", "may", "required", "recommended", "optional",
+    ];
+    let word = Regex::new(r"[A-Za-z][A-Za-z0-9_]+").unwrap();
+    word.find_iter(text)
+        .map(|m| m.as_str().to_lowercase())
+        .filter(|w| w.len() >= 3 && !FILLER.contains(&w.as_str()))
+        .map(|w| stem(&w))
+        .collect()
+}
+
+fn stem(word: &str) -> String {
+    for suffix in ["ing", "ed", "es", "s"] {
+        if let Some(root) = word.strip_suffix(suffix) {
+            if root.len() >= 3 && !root.ends_with('s') {
+                return root.to_string();
+            }
+        }
+    }
+    word.to_string()
+}
+
+type Vector = HashMap<String, f64>;
+
+/// TF-IDF vectors for `docs`, with IDF taken over all of them
+fn vectors(docs: &[Vec<String>]) -> Vec<Vector> {
+    let mut df: HashMap<&str, usize> = HashMap::new();
+    for doc in docs {
+        for term in doc.iter().map(String::as_str).collect::<BTreeSet<_>>() {
+            *df.entry(term).or_default() += 1;
+        }
+    }
+    let n = docs.len() as f64;
+    docs.iter()
+        .map(|doc| {
+            let mut vector = Vector::new();
+            for term in doc {
+                let idf = ((n + 1.0) / (df[term.as_str()] as f64 + 1.0)).ln() + 1.0;
+                *vector.entry(term.clone()).or_default() += idf;
+            }
+            vector
+        })
+        .collect()
+}
+
+fn cosine(a: &Vector, b: &Vector) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(t, x)| b.get(t).map(|y| x * y)).sum();
+    let norm = |v: &Vector| v.values().map(|x| x * x).sum::<f64>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 {
+        0.0
+    } else {
+        dot / denom
+    }
+}
+
+/// Suggest a requirement for each task that doesn't cite one yet
+///
+/// A task that refers to specs is only matched against their requirements.
+/// `tasks` holds each plan file with its tasks, so suggestions can be
+/// written back with `apply`.
+pub fn suggest(
+    requirements: &[Requirement],
+    tasks: &PlanTasks,
+    min_confidence: f64,
+) -> Suggestions {
+    let all_tasks: Vec<(&Path, &Task)> = tasks
+        .iter()
+        .flat_map(|(file, tasks)| tasks.iter().map(move |task| (file.as_path(), task)))
+        .collect();
+
+    let mut docs: Vec<Vec<String>> = requirements
+        .iter()
+        .map(|r| match r.req_type {
+            RequirementType::Section => terms(&r.text),
+            _ => terms(&format!("{} {}", r.heading, r.text)),
+        })
+        .collect();
+    docs.extend(all_tasks.iter().map(|(_, task)| terms(&task.description)));
+    let vectors = vectors(&docs);
+    let (req_vectors, task_vectors) = vectors.split_at(requirements.len());
+
+    let cited: BTreeSet<String> = all_tasks
+        .iter()
+        .flat_map(|(_, task)| task.spec_refs.iter().filter_map(|r| requirement_ref(r)))
+        .collect();
+    let mut reached = vec![false; requirements.len()];
+    let mut suggestions = Vec::new();
+
+    for ((file, task), task_vector) in all_tasks.iter().zip(task_vectors) {
+        if task.spec_refs.iter().any(|r| requirement_ref(r).is_some()) {
+            continue;
+        }
+        let specs: BTreeSet<String> = task.spec_refs.iter().map(|r| spec_name(r)).collect();
+
+        let mut best: Option<(usize, f64)> = None;
+        for (i, req) in requirements.iter().enumerate() {
+            if !specs.is_empty() && !specs.contains(&req.spec_name) {
+                continue;
+            }
+            let score = cosine(task_vector, &req_vectors[i]);
+            if score < min_confidence {
+                continue;
+            }
+            reached[i] = true;
+            if best.is_none_or(|(_, b)| score > b) {
+                best = Some((i, score));
+            }
+        }
+
+        if let Some((i, confidence)) = best {
+            let req = &requirements[i];
+            suggestions.push(Suggestion {
+                file: file.to_path_buf(),
+                line_number: task.line_number,
+                task: task.description.clone(),
+                requirement: req.id.clone(),
+                spec_name: req.spec_name.clone(),
+                requirement_text: req.text.clone(),
+                confidence,
+            });
+        }
+    }
+
+    let probably_uncovered = requirements
+        .iter()
+        .zip(&reached)
+        .filter(|(req, reached)| !**reached && !cited.contains(&req.id))
+        .map(|(req, _)| req.clone())
+        .collect();
+
+    Suggestions {
+        suggestions,
+        probably_uncovered,
+    }
+}
+
+/// Add each suggested requirement to its task's `(refs: ...)` in the plan
+///
+/// Skips a suggestion whose line no longer holds its task. Keeps the file's
+/// line endings. Returns how many refs were added.
+pub fn apply(suggestions: &[Suggestion]) -> Result<usize> {
+    let refs_re = Regex::new(r"\(refs?:\s*([^)]*)\)").unwrap();
+    let mut by_file: HashMap<&Path, Vec<&Suggestion>> = HashMap::new();
+    for suggestion in suggestions {
+        by_file.entry(&suggestion.file).or_default().push(suggestion);
+    }
+
+    let mut applied = 0;
+    for (file, suggestions) in by_file {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+        for suggestion in suggestions {
+            let Some(line) = lines.get_mut(suggestion.line_number - 1) else {
+                continue;
+            };
+            if !line.contains(suggestion.task.as_str()) {
+                continue;
+            }
+            *line = match refs_re.captures(line) {
+                Some(caps) => {
+                    let refs = caps.get(1).unwrap();
+                    let separator = if refs.as_str().trim().is_empty() { "" } else { ", " };
+                    let end = refs.end();
+                    format!("{}{}{}{}", &line[..end], separator, suggestion.requirement, &line[end..])
+                }
+                None => format!("{} (refs: {})", line.trim_end(), suggestion.requirement),
+            };
+            applied += 1;
+        }
+
+        let mut updated = lines.join(newline);
+        if content.ends_with('\n') {
+            updated.push_str(newline);
+        }
+        fs::write(file, updated).with_context(|| format!("Failed to write {}", file.display()))?;
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::{extract_requirements, parse_plan};
+    use tempfile::TempDir;
+
+    fn setup(plan: &str) -> (TempDir, Vec<Requirement>, PlanTasks) {
+        let dir = TempDir::new().unwrap();
+        let specs = dir.path().join("specs");
+        fs::create_dir(&specs).unwrap();
+        fs::write(
+            specs.join("auth.md"),
+            "## Login\n\nUsers MUST log in with an email and password.\nSessions MUST expire after 30 days.\n",
+        )
+        .unwrap();
+        fs::write(specs.join("export.md"), "Reports MUST be exportable as CSV files.\n").unwrap();
+        let plan_path = dir.path().join("IMPLEMENTATION_PLAN.md");
+        fs::write(&plan_path, plan).unwrap();
+
+        let requirements = extract_requirements(&specs).unwrap();
+        let tasks = parse_plan(&plan_path).unwrap();
+        (dir, requirements, vec![(plan_path, tasks)])
+    }
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(terms("Users MUST log in with passwords"), vec!["user", "log", "password"]);
+        assert_eq!(terms("Add CSV exporting"), vec!["add", "csv", "export"]);
+        assert_eq!(stem("class"), "class");
+    }
+
+    #[test]
+    fn test_suggest() {
+        let (_dir, requirements, tasks) = setup(
+            "- [ ] Email and password login form\n- [ ] Export reports to CSV (refs: specs/auth.md)\n- [ ] Set up CI\n",
+        );
+        let result = suggest(&requirements, &tasks, DEFAULT_MIN_CONFIDENCE);
+
+        assert_eq!(result.suggestions.len(), 1);
+        let login = &result.suggestions[0];
+        assert_eq!(login.task, "Email and password login form");
+        assert_eq!(login.spec_name, "auth");
+        assert!(login.requirement_text.contains("email"));
+        assert!(login.confidence > 0.3 && login.confidence <= 1.0);
+
+        // The CSV task refers to auth only, so export stays uncovered
+        let uncovered: Vec<&str> = result.probably_uncovered.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(
+            uncovered,
+            vec!["Sessions MUST expire after 30 days.", "Reports MUST be exportable as CSV files."]
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let (dir, requirements, tasks) = setup(
+            "# Plan\n- [ ] Email and password login form\n- [ ] Expire sessions after 30 days (refs: specs/auth.md)\n",
+        );
+        let result = suggest(&requirements, &tasks, DEFAULT_MIN_CONFIDENCE);
+        assert_eq!(apply(&result.suggestions).unwrap(), 2);
+
+        let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+        let lines: Vec<&str> = plan.lines().collect();
+        assert_eq!(lines[1], format!("- [ ] Email and password login form (refs: {})", result.suggestions[0].requirement));
+        assert_eq!(
+            lines[2],
+            format!("- [ ] Expire sessions after 30 days (refs: specs/auth.md, {})", result.suggestions[1].requirement)
+        );
+        assert!(plan.ends_with('\n'));
+
+        // Once applied, the tasks cite requirements and get no suggestions
+        let tasks = vec![(tasks[0].0.clone(), parse_plan(&tasks[0].0).unwrap())];
+        assert!(suggest(&requirements, &tasks, DEFAULT_MIN_CONFIDENCE).suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_apply_keeps_crlf() {
+        let (dir, requirements, tasks) =
+            setup("# Plan\r\n- [ ] Email and password login form\r\n- [ ] Set up CI\r\n");
+        let result = suggest(&requirements, &tasks, DEFAULT_MIN_CONFIDENCE);
+        assert_eq!(apply(&result.suggestions).unwrap(), 1);
+
+        let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+        assert_eq!(
+            plan,
+            format!(
+                "# Plan\r\n- [ ] Email and password login form (refs: {})\r\n- [ ] Set up CI\r\n",
+                result.suggestions[0].requirement
+            )
+        );
+    }
+}
@@ -1,4 +1,4 @@
-/// Embedded prompt templates for fresher
+//! Embedded prompt templates for fresher
 
 /// Planning mode prompt template
 pub const PROMPT_PLANNING: &str = r#"# Planning Mode
@@ -161,7 +161,7 @@ Your final output should confirm:
 - Assume specs describe INTENT, not reality
 - Always verify against actual code before concluding something is implemented
 - Tasks should be small enough to complete in one building iteration
-- Include spec references for traceability
+- Include spec references for traceability; when a spec tags requirements like `[REQ-AUTH-3]`, cite the IDs a task implements (refs: specs/auth.md, REQ-AUTH-3)
 - Use hierarchical structure for better context efficiency in large projects
 "#;
 
@@ -351,6 +351,17 @@ timeout = 30
 use_docker = false
 memory = "4g"
 cpus = "2"
+
+# Deny patterns (regexes) for Bash commands the agent runs. A match kills
+# the agent and finishes the loop with policy_violation.
+# [guardrails]
+# deny = [
+#   'git\s+push',
+#   'rm\s+-rf\s+/(\s|$)',
+#   'curl[^|]*\|\s*(ba)?sh',
+#   '~/\.ssh|\$HOME/\.ssh',
+# ]
+# rollback = true
 "#;
 
 /// Example hook script for started hook
@@ -396,7 +407,7 @@ pub const HOOK_FINISHED: &str = r#"#!/bin/bash
 #   FRESHER_TOTAL_ITERATIONS - Total iterations completed
 #   FRESHER_TOTAL_COMMITS - Total commits made
 #   FRESHER_DURATION - Total duration in seconds
-#   FRESHER_FINISH_TYPE - How loop ended (manual, error, max_iterations, complete, no_changes)
+#   FRESHER_FINISH_TYPE - How loop ended (manual, error, max_iterations, complete, no_changes, budget, stalled, policy_violation, outcome_stop)
 
 echo "Fresher loop finished"
 echo "  Iterations: $FRESHER_TOTAL_ITERATIONS"
@@ -405,6 +416,7 @@ echo "  Duration: ${FRESHER_DURATION}s"
 echo "  Finish type: $FRESHER_FINISH_TYPE"
 
 # Example: Send notification
+# (or use the built-in [notify] section in .fresher/config.toml instead)
 # curl -X POST "https://slack.webhook/..." -d "{\"text\": \"Fresher completed: $FRESHER_FINISH_TYPE\"}"
 
 exit 0
@@ -0,0 +1,185 @@
+//! The agent's own task list, from `TodoWrite` tool calls
+//!
+//! Each `TodoWrite` call carries the whole list. The stream handler renders it
+//! as a checklist when it changes, the last list of an iteration is kept in
+//! the state and the iteration log, and with `[todos] check_focus` it is
+//! compared with the plan's focus task.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::impl_plan::ImplIndex;
+use crate::verify::{parse_plan, TaskStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// One entry of a `TodoWrite` list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Todo {
+    pub content: String,
+    pub status: TodoStatus,
+    #[serde(default, rename = "activeForm", skip_serializing_if = "Option::is_none")]
+    pub active_form: Option<String>,
+}
+
+/// The `todos` array of a `TodoWrite` input (`None` if malformed)
+pub fn parse_todos(input: &Value) -> Option<Vec<Todo>> {
+    serde_json::from_value(input.get("todos")?.clone()).ok()
+}
+
+/// e.g. `2/5`
+pub fn progress(todos: &[Todo]) -> String {
+    let done = todos.iter().filter(|t| t.status == TodoStatus::Completed).count();
+    format!("{}/{}", done, todos.len())
+}
+
+/// One line per todo: `✓`, `▸` (in progress) or `○`
+pub fn checklist(todos: &[Todo]) -> Vec<String> {
+    todos
+        .iter()
+        .map(|todo| {
+            let mark = match todo.status {
+                TodoStatus::Completed => "✓",
+                TodoStatus::InProgress => "▸",
+                TodoStatus::Pending => "○",
+            };
+            format!("{} {}", mark, todo.content)
+        })
+        .collect()
+}
+
+/// The plan task the agent should be working on
+///
+/// With a hierarchical plan, the first open task of the current focus
+/// feature (or the next feature `select_next_focus` picks); otherwise the
+/// first open task of `plan_path`.
+pub fn focus_task(plan_path: &Path, impl_dir: &Path) -> Option<String> {
+    let plan_file = match ImplIndex::load(impl_dir) {
+        Ok(index) => {
+            let focus = index.current_focus.as_deref().and_then(|name| {
+                let name = name.trim_end_matches(".md");
+                index.features.iter().find(|f| f.name == name && f.pending_tasks > 0)
+            });
+            focus.or_else(|| index.select_next_focus())?.file.clone()
+        }
+        Err(_) => plan_path.to_path_buf(),
+    };
+    parse_plan(&plan_file)
+        .ok()?
+        .into_iter()
+        .find(|task| task.status != TaskStatus::Completed)
+        .map(|task| task.description)
+}
+
+/// Lowercase words of three or more letters, minus common filler
+fn words(text: &str) -> HashSet<String> {
+    const FILLER: &[&str] = &["the", "and", "for", "with", "from", "into", "that", "this"];
+    let word = Regex::new(r"[A-Za-z0-9_]{3,}").unwrap();
+    word.find_iter(text)
+        .map(|m| m.as_str().to_lowercase())
+        .filter(|w| !FILLER.contains(&w.as_str()))
+        .collect()
+}
+
+/// Whether a todo covers at least half the focus task's words
+///
+/// Completed todos count too: the list is the iteration's last one, and by
+/// then the focus task is often done.
+pub fn on_focus(todos: &[Todo], task: &str) -> bool {
+    let task_words = words(task);
+    if task_words.is_empty() {
+        return true;
+    }
+    todos.iter().any(|todo| {
+        let shared = words(&todo.content).intersection(&task_words).count();
+        shared * 2 >= task_words.len()
+    })
+}
+
+/// Append the todo list to an iteration log
+pub fn append_to_log(path: &Path, todos: &[Todo], focus: Option<(&str, bool)>) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "  todos: {}", progress(todos))?;
+    for line in checklist(todos) {
+        writeln!(file, "    {}", line)?;
+    }
+    if let Some((task, on_focus)) = focus {
+        let verdict = if on_focus { "on focus task" } else { "not on focus task" };
+        writeln!(file, "  {}: {}", verdict, task)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn todos() -> Vec<Todo> {
+        parse_todos(&json!({"todos": [
+            {"content": "Parse config sections", "status": "completed", "activeForm": "Parsing config sections"},
+            {"content": "Add retry logic to webhook delivery", "status": "in_progress", "activeForm": "Adding retry logic"},
+            {"content": "Update docs", "status": "pending"},
+        ]}))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_render() {
+        let todos = todos();
+        assert_eq!(todos[1].status, TodoStatus::InProgress);
+        assert_eq!(todos[2].active_form, None);
+        assert_eq!(progress(&todos), "1/3");
+        assert_eq!(
+            checklist(&todos),
+            vec![
+                "✓ Parse config sections",
+                "▸ Add retry logic to webhook delivery",
+                "○ Update docs"
+            ]
+        );
+        assert!(parse_todos(&json!({"todos": "nope"})).is_none());
+        assert!(parse_todos(&json!({})).is_none());
+    }
+
+    #[test]
+    fn test_on_focus() {
+        let todos = todos();
+        assert!(on_focus(&todos, "Retry webhook delivery on failure"));
+        // A finished focus task still counts
+        assert!(on_focus(&todos, "Parse config sections"));
+        assert!(!on_focus(&todos, "Implement the TUI dashboard layout"));
+
+        let done: Vec<Todo> = todos
+            .into_iter()
+            .map(|todo| Todo { status: TodoStatus::Completed, ..todo })
+            .collect();
+        assert!(on_focus(&done, "Retry webhook delivery on failure"));
+    }
+
+    #[test]
+    fn test_focus_task() {
+        let dir = TempDir::new().unwrap();
+        let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+        std::fs::write(&plan, "# Plan\n- [x] Set up project\n- [ ] Add webhook retries (refs: notify)\n- [ ] Docs\n").unwrap();
+        let impl_dir = dir.path().join("impl");
+        assert_eq!(focus_task(&plan, &impl_dir).as_deref(), Some("Add webhook retries"));
+
+        std::fs::create_dir(&impl_dir).unwrap();
+        std::fs::write(impl_dir.join("README.md"), "# Plan\n\n## Current Focus\n\n**Active:** [cli]\n").unwrap();
+        std::fs::write(impl_dir.join("auth.md"), "# Auth\n- [ ] Login\n").unwrap();
+        std::fs::write(impl_dir.join("cli.md"), "# CLI\n- [x] Parse args\n- [ ] Add --quiet flag\n").unwrap();
+        assert_eq!(focus_task(&plan, &impl_dir).as_deref(), Some("Add --quiet flag"));
+    }
+}
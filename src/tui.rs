@@ -0,0 +1,672 @@
+//! Full-screen terminal UI for `fresher build --tui`
+//!
+//! The loop keeps running as usual and reports to the UI through [`TuiEvent`]s;
+//! stream events arrive on the same broadcast channel the dashboard uses. The
+//! UI owns the terminal (raw mode, alternate screen) until [`Tui::shutdown`].
+
+use anyhow::Result;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::dashboard::PlanProgress;
+use crate::streaming::{tool_call_detail, ContentBlock, StreamEvent, UserContentBlock};
+
+/// Lines kept in the tool-call feed
+const FEED_LINES: usize = 500;
+
+/// Hook results kept in the hooks pane
+const HOOK_LINES: usize = 50;
+
+/// Redraw / input polling interval
+const TICK: Duration = Duration::from_millis(100);
+
+/// Updates sent from the loop to the UI
+#[derive(Debug, Clone)]
+pub enum TuiEvent {
+    /// A new iteration started
+    Iteration {
+        iteration: u32,
+        focus: Option<String>,
+    },
+    /// Fresh plan progress
+    Plan(PlanProgress),
+    /// A hook ran (`name`, outcome)
+    Hook(String, String),
+    /// A message from the loop itself
+    Log(String),
+    /// The loop is waiting because pause-after-iteration is on
+    Paused(bool),
+    /// Something else wrote to the terminal; repaint everything
+    Redraw,
+}
+
+/// Flags the UI sets for the loop to act on between iterations
+#[derive(Debug, Clone)]
+pub struct TuiControls {
+    /// Stop after the current iteration (shared with the Ctrl+C handler)
+    pub stop: Arc<AtomicBool>,
+    /// Pause after the current iteration until toggled off
+    pub pause: Arc<AtomicBool>,
+}
+
+/// Kind of a line in the tool-call feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedKind {
+    ToolCall,
+    ToolResult,
+    Log,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedLine {
+    pub kind: FeedKind,
+    pub iteration: u32,
+    pub text: String,
+}
+
+/// UI state, independent of the terminal so it can be tested
+#[derive(Debug)]
+pub struct TuiApp {
+    pub mode: String,
+    pub max_iterations: u32,
+    pub iteration: u32,
+    pub focus: Option<String>,
+    pub plan: PlanProgress,
+    pub feed: VecDeque<FeedLine>,
+    pub hooks: VecDeque<String>,
+    pub show_tool_results: bool,
+    pub cost_usd: f64,
+    pub turns: u32,
+    pub started: Instant,
+    pub paused: bool,
+}
+
+impl TuiApp {
+    pub fn new(mode: &str, max_iterations: u32) -> Self {
+        Self {
+            mode: mode.to_string(),
+            max_iterations,
+            iteration: 0,
+            focus: None,
+            plan: PlanProgress::default(),
+            feed: VecDeque::new(),
+            hooks: VecDeque::new(),
+            show_tool_results: false,
+            cost_usd: 0.0,
+            turns: 0,
+            started: Instant::now(),
+            paused: false,
+        }
+    }
+
+    /// Apply an update from the loop
+    pub fn apply(&mut self, event: TuiEvent) {
+        match event {
+            TuiEvent::Iteration { iteration, focus } => {
+                self.iteration = iteration;
+                if focus.is_some() {
+                    self.focus = focus;
+                }
+                self.push_feed(FeedKind::Log, format!("── Iteration {}", iteration));
+            }
+            TuiEvent::Plan(plan) => {
+                if plan.current_focus.is_some() {
+                    self.focus = plan.current_focus.clone();
+                }
+                self.plan = plan;
+            }
+            TuiEvent::Hook(name, outcome) => {
+                self.hooks.push_back(format!("#{} {}: {}", self.iteration, name, outcome));
+                while self.hooks.len() > HOOK_LINES {
+                    self.hooks.pop_front();
+                }
+            }
+            TuiEvent::Log(line) => self.push_feed(FeedKind::Log, line),
+            TuiEvent::Paused(paused) => self.paused = paused,
+            TuiEvent::Redraw => {}
+        }
+    }
+
+    /// Fold a stream event into the feed and totals
+    pub fn apply_stream(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::Assistant(e) => {
+                let Some(msg) = &e.message else {
+                    return;
+                };
+                for block in &msg.content {
+                    if let ContentBlock::ToolUse { name, input, .. } = block {
+                        let text = match tool_call_detail(input) {
+                            Some(detail) => format!("{}: {}", name, detail),
+                            None => name.clone(),
+                        };
+                        self.push_feed(FeedKind::ToolCall, text);
+                    }
+                }
+            }
+            StreamEvent::User(e) => {
+                let Some(msg) = &e.message else {
+                    return;
+                };
+                for block in &msg.content {
+                    if let UserContentBlock::ToolResult { content, .. } = block {
+                        let preview = content.lines().next().unwrap_or("");
+                        let preview: String = preview.chars().take(200).collect();
+                        self.push_feed(FeedKind::ToolResult, preview);
+                    }
+                }
+            }
+            StreamEvent::Result(e) => {
+                self.cost_usd += e.cost_usd.unwrap_or(0.0);
+                self.turns += e.num_turns.unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle a key press; returns true when it changed something
+    pub fn handle_key(&mut self, key: KeyEvent, controls: &TuiControls) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                controls.stop.store(true, Ordering::SeqCst);
+                true
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                controls.stop.store(true, Ordering::SeqCst);
+                true
+            }
+            KeyCode::Char('p') => {
+                controls.pause.fetch_xor(true, Ordering::SeqCst);
+                true
+            }
+            KeyCode::Char('r') => {
+                self.show_tool_results = !self.show_tool_results;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Feed lines currently shown (tool results only when toggled on)
+    pub fn visible_feed(&self) -> impl Iterator<Item = &FeedLine> {
+        self.feed
+            .iter()
+            .filter(|l| self.show_tool_results || l.kind != FeedKind::ToolResult)
+    }
+
+    fn push_feed(&mut self, kind: FeedKind, text: String) {
+        self.feed.push_back(FeedLine {
+            kind,
+            iteration: self.iteration,
+            text,
+        });
+        while self.feed.len() > FEED_LINES {
+            self.feed.pop_front();
+        }
+    }
+
+    /// Draw the whole UI
+    pub fn render(&self, frame: &mut Frame, controls: &TuiControls) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+        let [feed, side] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(body);
+        let [plan, hooks] =
+            Layout::vertical([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(side);
+
+        self.render_header(frame, header);
+        self.render_feed(frame, feed);
+        self.render_plan(frame, plan);
+        self.render_hooks(frame, hooks);
+        self.render_footer(frame, footer, controls);
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let iteration = if self.max_iterations > 0 {
+            format!("Iteration {}/{}", self.iteration, self.max_iterations)
+        } else {
+            format!("Iteration {}", self.iteration)
+        };
+        let mut spans = vec![
+            Span::styled("Fresher ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!("({}) ", self.mode), Style::default().fg(Color::Green)),
+            Span::styled(iteration, Style::default().fg(Color::Cyan)),
+        ];
+        if let Some(focus) = &self.focus {
+            spans.push(Span::raw("  Focus: "));
+            spans.push(Span::styled(focus.clone(), Style::default().fg(Color::Yellow)));
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL)),
+            area,
+        );
+    }
+
+    fn render_feed(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: Vec<&FeedLine> = self.visible_feed().collect();
+        let start = lines.len().saturating_sub(height);
+        let items: Vec<ListItem> = lines[start..]
+            .iter()
+            .map(|l| {
+                let line = match l.kind {
+                    FeedKind::ToolCall => Line::from(vec![
+                        Span::styled("→ ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(l.text.clone()),
+                    ]),
+                    FeedKind::ToolResult => Line::styled(
+                        format!("    {}", l.text),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    FeedKind::Log => Line::styled(l.text.clone(), Style::default().fg(Color::Cyan)),
+                };
+                ListItem::new(line)
+            })
+            .collect();
+        let title = if self.show_tool_results {
+            " Tool calls (with results) "
+        } else {
+            " Tool calls "
+        };
+        frame.render_widget(
+            List::new(items).block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+
+    fn render_plan(&self, frame: &mut Frame, area: Rect) {
+        let bar_width = 12;
+        let mut lines = vec![Line::from(vec![
+            Span::raw("Total    "),
+            Span::raw(progress_bar(
+                self.plan.completed_tasks,
+                self.plan.total_tasks,
+                bar_width,
+            )),
+            Span::raw(format!(
+                " {}/{}",
+                self.plan.completed_tasks, self.plan.total_tasks
+            )),
+        ])];
+        for feature in &self.plan.features {
+            let name: String = feature.name.chars().take(16).collect();
+            let style = if Some(&feature.name) == self.focus.as_ref()
+                || self.focus.as_deref() == Some(&format!("{}.md", feature.name))
+            {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:16} ", name), style),
+                Span::raw(progress_bar(
+                    feature.completed_tasks,
+                    feature.total_tasks,
+                    bar_width,
+                )),
+                Span::raw(format!(
+                    " {}/{}",
+                    feature.completed_tasks, feature.total_tasks
+                )),
+            ]));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Plan ")),
+            area,
+        );
+    }
+
+    fn render_hooks(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let start = self.hooks.len().saturating_sub(height);
+        let items: Vec<ListItem> = self
+            .hooks
+            .iter()
+            .skip(start)
+            .map(|h| ListItem::new(h.as_str()))
+            .collect();
+        frame.render_widget(
+            List::new(items).block(Block::default().borders(Borders::ALL).title(" Hooks ")),
+            area,
+        );
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect, controls: &TuiControls) {
+        let elapsed = self.started.elapsed().as_secs();
+        let mut spans = vec![
+            Span::raw(format!("Cost ${:.4}", self.cost_usd)),
+            Span::raw(format!("  Turns {}", self.turns)),
+            Span::raw(format!(
+                "  Duration {:02}:{:02}:{:02}",
+                elapsed / 3600,
+                (elapsed / 60) % 60,
+                elapsed % 60
+            )),
+            Span::raw("   "),
+        ];
+        if controls.stop.load(Ordering::SeqCst) {
+            spans.push(Span::styled(
+                "STOPPING after this iteration ",
+                Style::default().fg(Color::Red),
+            ));
+        } else if self.paused {
+            spans.push(Span::styled(
+                "PAUSED (p to resume) ",
+                Style::default().fg(Color::Yellow),
+            ));
+        } else if controls.pause.load(Ordering::SeqCst) {
+            spans.push(Span::styled(
+                "pausing after this iteration ",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        spans.push(Span::styled(
+            "[p] pause  [r] tool results  [q] stop",
+            Style::default().fg(Color::DarkGray),
+        ));
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL)),
+            area,
+        );
+    }
+}
+
+fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(width).min(width);
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// The running TUI's sender, for output from code without a handle to it
+static ACTIVE: Mutex<Option<mpsc::UnboundedSender<TuiEvent>>> = Mutex::new(None);
+
+/// Print a line to stderr, or to the TUI log while the TUI owns the terminal
+pub fn eprint(line: impl Into<String>) {
+    let line = line.into();
+    match ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(tx) => {
+            let _ = tx.send(TuiEvent::Log(line));
+        }
+        None => eprintln!("{}", line),
+    }
+}
+
+/// Whether the TUI can be used (stdout is a terminal)
+pub fn is_supported() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+/// Handle to the running TUI
+pub struct Tui {
+    tx: mpsc::UnboundedSender<TuiEvent>,
+    quit: Arc<AtomicBool>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Tui {
+    /// Take over the terminal and start drawing
+    pub fn start(
+        mode: &str,
+        max_iterations: u32,
+        controls: TuiControls,
+        stream_events: broadcast::Receiver<StreamEvent>,
+    ) -> Result<Self> {
+        enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+        terminal.clear()?;
+
+        // Log lines are plain text in the UI
+        colored::control::set_override(false);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(tx.clone());
+        let quit = Arc::new(AtomicBool::new(false));
+        let app = TuiApp::new(mode, max_iterations);
+        let task = tokio::spawn(run_ui(
+            terminal,
+            app,
+            controls,
+            rx,
+            stream_events,
+            quit.clone(),
+        ));
+
+        Ok(Self {
+            tx,
+            quit,
+            task: Some(task),
+        })
+    }
+
+    pub fn send(&self, event: TuiEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    /// Sender for tasks that outlive a borrow of the handle
+    pub fn sender(&self) -> mpsc::UnboundedSender<TuiEvent> {
+        self.tx.clone()
+    }
+
+    pub fn log(&self, line: impl Into<String>) {
+        self.send(TuiEvent::Log(line.into()));
+    }
+
+    /// Stop drawing and give the terminal back
+    pub async fn shutdown(mut self) {
+        self.quit.store(true, Ordering::SeqCst);
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        restore_terminal();
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        // Error paths drop the TUI without shutdown(); never leave the
+        // terminal in raw mode
+        if let Some(task) = self.task.take() {
+            task.abort();
+            restore_terminal();
+        }
+    }
+}
+
+fn restore_terminal() {
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen);
+    colored::control::unset_override();
+}
+
+async fn run_ui<B: Backend>(
+    mut terminal: Terminal<B>,
+    mut app: TuiApp,
+    controls: TuiControls,
+    mut rx: mpsc::UnboundedReceiver<TuiEvent>,
+    mut stream_events: broadcast::Receiver<StreamEvent>,
+    quit: Arc<AtomicBool>,
+) {
+    while !quit.load(Ordering::SeqCst) {
+        while let Ok(event) = rx.try_recv() {
+            if matches!(event, TuiEvent::Redraw) {
+                let _ = terminal.clear();
+            }
+            app.apply(event);
+        }
+
+        loop {
+            match stream_events.try_recv() {
+                Ok(event) => app.apply_stream(&event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                app.handle_key(key, &controls);
+            }
+        }
+
+        let _ = terminal.draw(|frame| app.render(frame, &controls));
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::FeatureProgress;
+    use crate::streaming::parse_event;
+    use ratatui::backend::TestBackend;
+
+    fn controls() -> TuiControls {
+        TuiControls {
+            stop: Arc::new(AtomicBool::new(false)),
+            pause: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_stream_events_fill_feed_and_totals() {
+        let mut app = TuiApp::new("building", 10);
+        app.apply(TuiEvent::Iteration {
+            iteration: 1,
+            focus: None,
+        });
+        app.apply_stream(
+            &parse_event(
+                r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/lib.rs"}}]}}"#,
+            )
+            .unwrap(),
+        );
+        app.apply_stream(
+            &parse_event(
+                r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"pub mod cli;\npub mod config;"}]}}"#,
+            )
+            .unwrap(),
+        );
+        app.apply_stream(
+            &parse_event(r#"{"type":"result","cost_usd":0.5,"num_turns":4}"#).unwrap(),
+        );
+
+        let visible: Vec<_> = app.visible_feed().map(|l| l.text.as_str()).collect();
+        assert_eq!(visible, vec!["── Iteration 1", "Read: src/lib.rs"]);
+        assert_eq!(app.turns, 4);
+        assert!((app.cost_usd - 0.5).abs() < f64::EPSILON);
+
+        app.show_tool_results = true;
+        assert_eq!(app.visible_feed().last().unwrap().text, "pub mod cli;");
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        let mut app = TuiApp::new("building", 0);
+        let controls = controls();
+
+        assert!(app.handle_key(press(KeyCode::Char('r')), &controls));
+        assert!(app.show_tool_results);
+
+        app.handle_key(press(KeyCode::Char('p')), &controls);
+        assert!(controls.pause.load(Ordering::SeqCst));
+        app.handle_key(press(KeyCode::Char('p')), &controls);
+        assert!(!controls.pause.load(Ordering::SeqCst));
+
+        assert!(!app.handle_key(press(KeyCode::Char('x')), &controls));
+        assert!(!controls.stop.load(Ordering::SeqCst));
+
+        app.handle_key(
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            &controls,
+        );
+        assert!(controls.stop.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut app = TuiApp::new("building", 5);
+        app.apply(TuiEvent::Iteration {
+            iteration: 2,
+            focus: Some("auth".to_string()),
+        });
+        app.apply(TuiEvent::Plan(PlanProgress {
+            total_tasks: 4,
+            completed_tasks: 1,
+            pending_tasks: 3,
+            current_focus: None,
+            features: vec![FeatureProgress {
+                name: "auth".to_string(),
+                completed_tasks: 1,
+                total_tasks: 4,
+            }],
+        }));
+        app.apply(TuiEvent::Hook("next_iteration".to_string(), "continue".to_string()));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        let controls = controls();
+        terminal.draw(|f| app.render(f, &controls)).unwrap();
+
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("Iteration 2/5"));
+        assert!(screen.contains("Focus: auth"));
+        assert!(screen.contains("1/4"));
+        assert!(screen.contains("#2 next_iteration: continue"));
+        assert!(screen.contains("[q] stop"));
+    }
+
+    #[test]
+    fn test_eprint_goes_to_active_tui() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        *ACTIVE.lock().unwrap() = Some(tx);
+        eprint("Warning: hook timed out");
+        restore_terminal();
+        eprint("after shutdown");
+
+        match rx.try_recv() {
+            Ok(TuiEvent::Log(line)) => assert_eq!(line, "Warning: hook timed out"),
+            _ => panic!("expected a log line"),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(1, 4, 4), "[█░░░]");
+        assert_eq!(progress_bar(0, 0, 2), "[██]");
+    }
+}
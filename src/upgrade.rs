@@ -3,7 +3,7 @@ use flate2::read::GzDecoder;
 use semver::Version;
 use std::env;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use tar::Archive;
 
@@ -1,9 +1,13 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::config::VerifyConfig;
+use crate::impl_plan::ImplIndex;
 
 /// A task extracted from the implementation plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,10 +42,23 @@ impl std::fmt::Display for TaskStatus {
 /// A requirement extracted from specifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Requirement {
+    /// Stable ID: an explicit tag like `REQ-AUTH-3`, or derived from the
+    /// spec, the enclosing heading and a hash of the text, e.g. `auth#login-3f2a9c`
+    pub id: String,
     pub spec_name: String,
     pub req_type: RequirementType,
     pub text: String,
     pub line_number: usize,
+    /// The heading the requirement sits under (empty before the first one)
+    #[serde(default)]
+    pub heading: String,
+}
+
+impl Requirement {
+    /// Whether the ID comes from a `[REQ-...]` tag rather than being derived
+    pub fn has_explicit_id(&self) -> bool {
+        self.id.starts_with("REQ-")
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -50,8 +67,13 @@ pub enum RequirementType {
     Section,
     Task,
     Rfc2119,
+    /// A line with an explicit ID and no other marker
+    Tagged,
 }
 
+/// Plan files, each with its tasks
+pub type PlanTasks = Vec<(PathBuf, Vec<Task>)>;
+
 /// Verification report
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifyReport {
@@ -63,6 +85,15 @@ pub struct VerifyReport {
     pub orphan_tasks: usize,
     pub coverage: Vec<CoverageEntry>,
     pub tasks: Vec<Task>,
+    /// Spec refs that don't match a file in the spec directory
+    #[serde(default)]
+    pub dangling_refs: Vec<String>,
+    /// Traceability matrix: every requirement and the tasks citing it
+    #[serde(default)]
+    pub requirements: Vec<TracedRequirement>,
+    /// Requirement IDs cited by tasks that no spec defines
+    #[serde(default)]
+    pub unknown_requirement_refs: Vec<UnknownRequirementRef>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,9 +101,34 @@ pub struct CoverageEntry {
     pub spec_name: String,
     pub requirement_count: usize,
     pub task_count: usize,
+    /// Requirements cited by ID, when any task cites this spec's requirements
+    ///
+    /// When set, `coverage_percent` is traced requirements over all
+    /// requirements; otherwise it estimates from the task count.
+    #[serde(default)]
+    pub traced_requirements: Option<usize>,
     pub coverage_percent: f64,
 }
 
+/// One row of the traceability matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedRequirement {
+    pub id: String,
+    pub spec_name: String,
+    pub text: String,
+    pub line_number: usize,
+    /// Descriptions of the tasks citing this requirement
+    pub tasks: Vec<String>,
+}
+
+/// A task citing a requirement ID that doesn't exist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownRequirementRef {
+    pub requirement: String,
+    pub task: String,
+    pub line_number: usize,
+}
+
 /// Parse implementation plan and extract tasks
 pub fn parse_plan(plan_path: &Path) -> Result<Vec<Task>> {
     let content = fs::read_to_string(plan_path)
@@ -82,7 +138,8 @@ pub fn parse_plan(plan_path: &Path) -> Result<Vec<Task>> {
     let mut current_priority: Option<u32> = None;
 
     // Regex patterns
-    let priority_re = Regex::new(r"^##\s+Priority\s+(\d+)")?;
+    // `##` in flat plans, `###` in feature files
+    let priority_re = Regex::new(r"^#{2,4}\s+Priority\s+(\d+)")?;
     let checkbox_re = Regex::new(r"^(\s*)-\s*\[([ xX~])\]\s+(.+)$")?;
     let refs_re = Regex::new(r"\(refs?:\s*([^)]+)\)")?;
     let deps_re = Regex::new(r"Dependencies:\s*(.+)")?;
@@ -257,6 +314,11 @@ fn has_pending_tasks_legacy(plan_path: &Path) -> bool {
 }
 
 /// Extract requirements from specification files
+///
+/// One requirement per `###` heading, checkbox, or line with an RFC 2119
+/// keyword. A `[REQ-...]` tag on a line gives it an explicit ID (and makes
+/// it a requirement even without a marker); once a spec tags any line, only
+/// its tagged lines count. Untagged requirements get derived IDs.
 pub fn extract_requirements(spec_dir: &Path) -> Result<Vec<Requirement>> {
     let mut requirements = Vec::new();
 
@@ -269,12 +331,16 @@ pub fn extract_requirements(spec_dir: &Path) -> Result<Vec<Requirement>> {
         r"\b(MUST|MUST NOT|REQUIRED|SHALL|SHALL NOT|SHOULD|SHOULD NOT|RECOMMENDED|MAY|OPTIONAL)\b"
     )?;
     let section_re = Regex::new(r"^###\s+(.+)$")?;
+    let heading_re = Regex::new(r"^#{1,6}\s+(.+)$")?;
     let checkbox_re = Regex::new(r"^(\s*)-\s*\[([ xX])\]\s+(.+)$")?;
+    let tag_re = Regex::new(r"\[(REQ-[A-Za-z0-9][A-Za-z0-9_.-]*)\]")?;
 
-    for entry in fs::read_dir(spec_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+    let mut paths: Vec<_> = fs::read_dir(spec_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
 
+    for path in paths {
         if path.extension().map(|e| e == "md").unwrap_or(false) {
             let spec_name = path
                 .file_stem()
@@ -282,50 +348,168 @@ pub fn extract_requirements(spec_dir: &Path) -> Result<Vec<Requirement>> {
                 .unwrap_or_default();
 
             let content = fs::read_to_string(&path)?;
+            let mut spec_reqs = Vec::new();
+            let mut heading = String::new();
+            let mut tagged = false;
 
             for (line_num, line) in content.lines().enumerate() {
-                // Check for section headers
-                if let Some(caps) = section_re.captures(line) {
-                    requirements.push(Requirement {
-                        spec_name: spec_name.clone(),
-                        req_type: RequirementType::Section,
-                        text: caps.get(1).map(|m| m.as_str()).unwrap_or("").to_string(),
-                        line_number: line_num + 1,
-                    });
-                }
+                let explicit = tag_re.captures(line).map(|c| c[1].to_string());
+                let line = tag_re.replace_all(line, "");
+                let line = line.trim_end();
 
-                // Check for checkboxes in specs
-                if let Some(caps) = checkbox_re.captures(line) {
-                    requirements.push(Requirement {
-                        spec_name: spec_name.clone(),
-                        req_type: RequirementType::Task,
-                        text: caps.get(3).map(|m| m.as_str()).unwrap_or("").to_string(),
-                        line_number: line_num + 1,
-                    });
+                if let Some(caps) = heading_re.captures(line) {
+                    heading = caps[1].trim().to_string();
                 }
 
-                // Check for RFC 2119 keywords
-                if rfc2119_re.is_match(line) {
-                    requirements.push(Requirement {
+                let found = if let Some(caps) = section_re.captures(line) {
+                    Some((RequirementType::Section, caps[1].trim().to_string()))
+                } else if let Some(caps) = checkbox_re.captures(line) {
+                    Some((RequirementType::Task, caps[3].trim().to_string()))
+                } else if rfc2119_re.is_match(line) {
+                    Some((RequirementType::Rfc2119, line.trim().to_string()))
+                } else {
+                    explicit
+                        .is_some()
+                        .then(|| (RequirementType::Tagged, line.trim().to_string()))
+                };
+
+                if let Some((req_type, text)) = found {
+                    tagged |= explicit.is_some();
+                    let id = explicit.unwrap_or_else(|| derived_id(&spec_name, &heading, &text));
+                    spec_reqs.push(Requirement {
+                        id,
                         spec_name: spec_name.clone(),
-                        req_type: RequirementType::Rfc2119,
-                        text: line.to_string(),
+                        req_type,
+                        text,
                         line_number: line_num + 1,
+                        heading: heading.clone(),
                     });
                 }
             }
+
+            if tagged {
+                spec_reqs.retain(Requirement::has_explicit_id);
+            }
+            requirements.extend(spec_reqs);
+        }
+    }
+
+    // An explicit ID must be unique; refs to it would be ambiguous
+    let mut tagged_at: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for req in requirements.iter().filter(|r| r.has_explicit_id()) {
+        tagged_at
+            .entry(&req.id)
+            .or_default()
+            .push(format!("{}.md:{}", req.spec_name, req.line_number));
+    }
+    let duplicates: Vec<String> = tagged_at
+        .into_iter()
+        .filter(|(_, places)| places.len() > 1)
+        .map(|(id, places)| format!("{} ({})", id, places.join(", ")))
+        .collect();
+    if !duplicates.is_empty() {
+        bail!("Duplicate requirement IDs: {}", duplicates.join("; "));
+    }
+
+    // Identical text under the same heading gets a numbered suffix
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for req in requirements.iter_mut().filter(|r| !r.has_explicit_id()) {
+        let count = seen.entry(req.id.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            req.id = format!("{}-{}", req.id, count);
         }
     }
 
     Ok(requirements)
 }
 
+/// `spec#heading-slug-hash`, stable while the heading and text don't change
+fn derived_id(spec_name: &str, heading: &str, text: &str) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let hash: String = Sha256::digest(normalized.as_bytes())
+        .iter()
+        .take(3)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let slug = heading
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        format!("{}#{}", spec_name, hash)
+    } else {
+        format!("{}#{}-{}", spec_name, slug, hash)
+    }
+}
+
+/// The requirement ID a ref names, if it names one rather than a spec
+///
+/// `REQ-AUTH-3` as is; `auth#login-3f2a9c` or `specs/auth.md#login-3f2a9c`
+/// as `auth#login-3f2a9c`. Plain anchors like `auth.md#login` are spec refs.
+pub fn requirement_ref(spec_ref: &str) -> Option<String> {
+    let spec_ref = spec_ref.trim();
+    let explicit = Regex::new(r"^REQ-[A-Za-z0-9][A-Za-z0-9_.-]*$").unwrap();
+    if explicit.is_match(spec_ref) {
+        return Some(spec_ref.to_string());
+    }
+    let derived = Regex::new(r"^(?:[a-z0-9]+(?:-[a-z0-9]+)*-)?[0-9a-f]{6}(?:-\d+)?$").unwrap();
+    let (path, anchor) = spec_ref.split_once('#')?;
+    derived
+        .is_match(anchor)
+        .then(|| format!("{}#{}", spec_name(path), anchor))
+}
+
+/// Build the traceability matrix from requirements and the tasks citing them
+pub fn trace_requirements(
+    requirements: &[Requirement],
+    tasks: &[Task],
+) -> (Vec<TracedRequirement>, Vec<UnknownRequirementRef>) {
+    let mut matrix: Vec<TracedRequirement> = requirements
+        .iter()
+        .map(|req| TracedRequirement {
+            id: req.id.clone(),
+            spec_name: req.spec_name.clone(),
+            text: req.text.clone(),
+            line_number: req.line_number,
+            tasks: Vec::new(),
+        })
+        .collect();
+    let index: HashMap<String, usize> = matrix
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (row.id.clone(), i))
+        .collect();
+
+    let mut unknown = Vec::new();
+    for task in tasks {
+        for id in task.spec_refs.iter().filter_map(|r| requirement_ref(r)) {
+            match index.get(&id) {
+                Some(&i) => {
+                    if !matrix[i].tasks.contains(&task.description) {
+                        matrix[i].tasks.push(task.description.clone());
+                    }
+                }
+                None => unknown.push(UnknownRequirementRef {
+                    requirement: id,
+                    task: task.description.clone(),
+                    line_number: task.line_number,
+                }),
+            }
+        }
+    }
+    (matrix, unknown)
+}
+
 /// Analyze coverage of specs by tasks
 pub fn analyze_coverage(
     spec_dir: &Path,
     tasks: &[Task],
 ) -> Result<Vec<CoverageEntry>> {
     let requirements = extract_requirements(spec_dir)?;
+    let (matrix, _) = trace_requirements(&requirements, tasks);
 
     // Group requirements by spec
     let mut spec_reqs: HashMap<String, Vec<&Requirement>> = HashMap::new();
@@ -336,16 +520,33 @@ pub fn analyze_coverage(
             .push(req);
     }
 
-    // Count tasks referencing each spec
+    // Count tasks referencing each spec, directly or through a requirement ID
+    let spec_of: HashMap<&str, &str> = requirements
+        .iter()
+        .map(|r| (r.id.as_str(), r.spec_name.as_str()))
+        .collect();
     let mut spec_tasks: HashMap<String, usize> = HashMap::new();
     for task in tasks {
-        for spec_ref in &task.spec_refs {
-            // Extract spec name from path (e.g., "specs/foo.md" -> "foo")
-            let spec_name = spec_ref
-                .trim_start_matches("specs/")
-                .trim_end_matches(".md")
-                .to_string();
-            *spec_tasks.entry(spec_name).or_default() += 1;
+        let specs: BTreeSet<String> = task
+            .spec_refs
+            .iter()
+            .map(|spec_ref| match requirement_ref(spec_ref) {
+                Some(id) => spec_of.get(id.as_str()).map(|s| s.to_string()).unwrap_or_else(|| spec_name(&id)),
+                None => spec_name(spec_ref),
+            })
+            .collect();
+        for spec in specs {
+            *spec_tasks.entry(spec).or_default() += 1;
+        }
+    }
+
+    // Specs whose requirements are cited by ID are measured by the matrix
+    let mut spec_traced: HashMap<&str, (usize, bool)> = HashMap::new();
+    for row in &matrix {
+        let entry = spec_traced.entry(row.spec_name.as_str()).or_default();
+        if !row.tasks.is_empty() {
+            entry.0 += 1;
+            entry.1 = true;
         }
     }
 
@@ -354,8 +555,13 @@ pub fn analyze_coverage(
     for (spec_name, reqs) in spec_reqs {
         let req_count = reqs.len();
         let task_count = spec_tasks.get(&spec_name).copied().unwrap_or(0);
+        let traced_requirements = spec_traced
+            .get(spec_name.as_str())
+            .filter(|(_, cited)| *cited)
+            .map(|(traced, _)| *traced);
+        let covered = traced_requirements.unwrap_or(task_count);
         let coverage_percent = if req_count > 0 {
-            (task_count as f64 / req_count as f64 * 100.0).min(100.0)
+            (covered as f64 / req_count as f64 * 100.0).min(100.0)
         } else {
             0.0
         };
@@ -364,6 +570,7 @@ pub fn analyze_coverage(
             spec_name,
             requirement_count: req_count,
             task_count,
+            traced_requirements,
             coverage_percent,
         });
     }
@@ -372,15 +579,85 @@ pub fn analyze_coverage(
     Ok(coverage)
 }
 
+/// Spec a ref points at, e.g. `auth` for `specs/auth.md`, `../specs/auth.md#login` or `auth`
+pub fn spec_name(spec_ref: &str) -> String {
+    let path = spec_ref.split('#').next().unwrap_or(spec_ref).trim();
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.trim_end_matches(".md").to_string()
+}
+
+/// Spec refs (deduplicated, in task order) that don't match a spec file
+///
+/// Requirement IDs are checked by `trace_requirements` instead.
+fn find_dangling_refs(spec_dir: &Path, tasks: &[Task]) -> Result<Vec<String>> {
+    let mut specs = BTreeSet::new();
+    if spec_dir.exists() {
+        for entry in fs::read_dir(spec_dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "md").unwrap_or(false) {
+                specs.extend(path.file_stem().map(|s| s.to_string_lossy().to_string()));
+            }
+        }
+    }
+
+    let mut dangling: Vec<String> = Vec::new();
+    for spec_ref in tasks.iter().flat_map(|t| &t.spec_refs) {
+        if requirement_ref(spec_ref).is_some() {
+            continue;
+        }
+        if !specs.contains(&spec_name(spec_ref)) && !dangling.contains(spec_ref) {
+            dangling.push(spec_ref.clone());
+        }
+    }
+    Ok(dangling)
+}
+
 /// Generate a full verification report
 pub fn generate_report(plan_path: &Path, spec_dir: &Path) -> Result<VerifyReport> {
-    let tasks = parse_plan(plan_path)?;
+    report_from_tasks(parse_plan(plan_path)?, spec_dir)
+}
+
+/// Generate a verification report for a hierarchical plan
+pub fn generate_hierarchical_report(index: &ImplIndex, spec_dir: &Path) -> Result<VerifyReport> {
+    let tasks = hierarchical_tasks(index)?
+        .into_iter()
+        .flat_map(|(_, tasks)| tasks)
+        .collect();
+    report_from_tasks(tasks, spec_dir)
+}
+
+/// The tasks of a hierarchical plan, per file
+///
+/// Covers every feature file, plus the cross-cutting tasks in
+/// `impl/README.md`. A task without refs of its own is traced to its
+/// feature's `**Spec:**` link.
+pub fn hierarchical_tasks(index: &ImplIndex) -> Result<PlanTasks> {
+    let mut files = Vec::new();
+    for feature in &index.features {
+        let mut tasks = parse_plan(&feature.file)?;
+        for task in tasks.iter_mut().filter(|t| t.spec_refs.is_empty()) {
+            task.spec_refs.extend(feature.spec_ref.clone());
+        }
+        files.push((feature.file.clone(), tasks));
+    }
+    if index.cross_cutting_tasks.total > 0 {
+        let readme = index.impl_dir.join("README.md");
+        let tasks = parse_plan(&readme)?;
+        files.push((readme, tasks));
+    }
+    Ok(files)
+}
+
+fn report_from_tasks(tasks: Vec<Task>, spec_dir: &Path) -> Result<VerifyReport> {
     let (total, pending, completed, in_progress) = count_tasks(&tasks);
 
     let tasks_with_refs = tasks.iter().filter(|t| !t.spec_refs.is_empty()).count();
     let orphan_tasks = total - tasks_with_refs;
 
     let coverage = analyze_coverage(spec_dir, &tasks)?;
+    let dangling_refs = find_dangling_refs(spec_dir, &tasks)?;
+    let (requirements, unknown_requirement_refs) =
+        trace_requirements(&extract_requirements(spec_dir)?, &tasks);
 
     Ok(VerifyReport {
         total_tasks: total,
@@ -391,9 +668,58 @@ pub fn generate_report(plan_path: &Path, spec_dir: &Path) -> Result<VerifyReport
         orphan_tasks,
         coverage,
         tasks,
+        dangling_refs,
+        requirements,
+        unknown_requirement_refs,
     })
 }
 
+/// Everything in `report` that breaks a threshold, one line each
+pub fn check_thresholds(report: &VerifyReport, thresholds: &VerifyConfig) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if let Some(min) = thresholds.min_coverage {
+        for entry in report.coverage.iter().filter(|e| e.coverage_percent < min) {
+            violations.push(format!(
+                "Spec {} is {:.0}% covered (minimum {:.0}%)",
+                entry.spec_name, entry.coverage_percent, min
+            ));
+        }
+    }
+
+    if let Some(max) = thresholds.max_orphan_tasks {
+        if report.orphan_tasks > max {
+            violations.push(format!(
+                "{} tasks have no spec refs (maximum {})",
+                report.orphan_tasks, max
+            ));
+        }
+    }
+
+    if thresholds.no_dangling_refs {
+        for spec_ref in &report.dangling_refs {
+            violations.push(format!("Ref {} does not match a spec", spec_ref));
+        }
+        for unknown in &report.unknown_requirement_refs {
+            violations.push(format!(
+                "Requirement {} does not exist: {} (line {})",
+                unknown.requirement, unknown.task, unknown.line_number
+            ));
+        }
+    }
+
+    if thresholds.require_priority {
+        for task in report.tasks.iter().filter(|t| t.priority.is_none()) {
+            violations.push(format!(
+                "Task has no priority: {} (line {})",
+                task.description, task.line_number
+            ));
+        }
+    }
+
+    violations
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -500,6 +826,23 @@ mod tests {
         assert_eq!(tasks[0].priority, Some(3));
     }
 
+    #[test]
+    fn test_parse_plan_hierarchical_priority() {
+        let content = r#"## Tasks
+
+### Priority 2: Core Implementation
+
+#### P2.1: Add parser
+
+- [ ] Parse the config (refs: specs/config.md)
+"#;
+        let (_dir, path) = create_temp_plan(content);
+        let tasks = parse_plan(&path).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].priority, Some(2));
+    }
+
     #[test]
     fn test_parse_plan_with_dependencies() {
         let content = r#"- [ ] Task with deps
@@ -912,4 +1255,115 @@ Line 4
 
         assert!(!result, "Should ignore non-markdown files");
     }
+
+    #[test]
+    fn test_requirement_ids() {
+        let dir = TempDir::new().unwrap();
+        create_temp_spec(&dir, "auth.md", "## Login\n\nUsers MUST log in.\nUsers MUST   log in.\n");
+        create_temp_spec(
+            &dir,
+            "api.md",
+            "### Endpoints\n[REQ-API-1] List users\nThe API MUST paginate. [REQ-API-2]\nIt SHOULD cache.\n",
+        );
+        let reqs = extract_requirements(&dir.path().join("specs")).unwrap();
+        let ids: Vec<&str> = reqs.iter().map(|r| r.id.as_str()).collect();
+
+        // Tagged specs only count their tagged lines
+        assert_eq!(ids[..2], ["REQ-API-1", "REQ-API-2"]);
+        assert_eq!(reqs[0].req_type, RequirementType::Tagged);
+        assert_eq!(reqs[1].text, "The API MUST paginate.");
+
+        // Derived IDs ignore whitespace; duplicates are numbered
+        assert!(Regex::new(r"^auth#login-[0-9a-f]{6}$").unwrap().is_match(ids[2]));
+        assert_eq!(ids[3], format!("{}-2", ids[2]));
+        assert_eq!(derived_id("auth", "", "x"), derived_id("auth", "", "x"));
+
+        assert_eq!(requirement_ref("REQ-API-1").as_deref(), Some("REQ-API-1"));
+        assert_eq!(requirement_ref("specs/auth.md#login-3f2a9c").as_deref(), Some("auth#login-3f2a9c"));
+        assert_eq!(requirement_ref("specs/auth.md#login"), None);
+        assert_eq!(requirement_ref("specs/auth.md"), None);
+
+        // Explicit IDs are never renumbered; reusing one is an error
+        create_temp_spec(&dir, "billing.md", "[REQ-API-2] Charge monthly\n");
+        let err = extract_requirements(&dir.path().join("specs")).unwrap_err().to_string();
+        assert_eq!(err, "Duplicate requirement IDs: REQ-API-2 (api.md:3, billing.md:1)");
+    }
+
+    #[test]
+    fn test_traceability_matrix() {
+        let (dir, path) = create_temp_plan(
+            "- [ ] Paginate (refs: REQ-API-2)\n- [ ] Cache (refs: specs/api.md, REQ-API-9)\n",
+        );
+        create_temp_spec(&dir, "api.md", "[REQ-API-1] List users\n[REQ-API-2] MUST paginate\n");
+        let report = generate_report(&path, &dir.path().join("specs")).unwrap();
+
+        assert_eq!(report.requirements.len(), 2);
+        assert!(report.requirements[0].tasks.is_empty());
+        assert_eq!(report.requirements[1].tasks, vec!["Paginate"]);
+        assert_eq!(report.unknown_requirement_refs.len(), 1);
+        assert_eq!(report.unknown_requirement_refs[0].requirement, "REQ-API-9");
+        assert!(report.dangling_refs.is_empty());
+
+        // Two tasks, but only one of two requirements is traced
+        assert_eq!(report.coverage[0].task_count, 2);
+        assert_eq!(report.coverage[0].traced_requirements, Some(1));
+        assert_eq!(report.coverage[0].coverage_percent, 50.0);
+
+        let violations = check_thresholds(
+            &report,
+            &VerifyConfig {
+                no_dangling_refs: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(violations, vec!["Requirement REQ-API-9 does not exist: Cache (line 2)"]);
+    }
+
+    #[test]
+    fn test_spec_name() {
+        assert_eq!(spec_name("specs/auth.md"), "auth");
+        assert_eq!(spec_name("../specs/auth.md#login"), "auth");
+        assert_eq!(spec_name("auth"), "auth");
+    }
+
+    #[test]
+    fn test_check_thresholds() {
+        let (dir, path) = create_temp_plan(
+            "## Priority 1\n- [ ] A (refs: specs/api.md)\n- [ ] B (refs: specs/missing.md)\n- [ ] C\n",
+        );
+        create_temp_spec(&dir, "api.md", "### One\n### Two\n### Three\n### Four\n");
+        let report = generate_report(&path, &dir.path().join("specs")).unwrap();
+        assert_eq!(report.dangling_refs, vec!["specs/missing.md"]);
+
+        assert!(check_thresholds(&report, &VerifyConfig::default()).is_empty());
+        let violations = check_thresholds(
+            &report,
+            &VerifyConfig {
+                min_coverage: Some(50.0),
+                max_orphan_tasks: Some(0),
+                no_dangling_refs: true,
+                require_priority: true,
+            },
+        );
+        assert_eq!(
+            violations,
+            vec![
+                "Spec api is 25% covered (minimum 50%)",
+                "1 tasks have no spec refs (maximum 0)",
+                "Ref specs/missing.md does not match a spec",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_thresholds_hierarchical_priority() {
+        let (dir, path) =
+            create_temp_plan("## Tasks\n\n### Priority 1: Core\n\n#### P1.1: A\n\n- [ ] A\n");
+        let report = generate_report(&path, &dir.path().join("specs")).unwrap();
+        let thresholds = VerifyConfig {
+            require_priority: true,
+            ..Default::default()
+        };
+        assert!(check_thresholds(&report, &thresholds).is_empty());
+    }
 }
@@ -0,0 +1,263 @@
+//! File watching support for `fresher watch`
+//!
+//! Change detection is based on snapshots of the watched directories rather than
+//! on raw watcher events, so the same reporting works for native (inotify) and
+//! polling watchers, and editor save patterns (temp file + rename) or plain
+//! `touch` don't show up as spurious changes.
+
+use anyhow::Result;
+use notify::{Config as NotifyConfig, Event, PollWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Content fingerprints of every file under a set of directories
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub files: BTreeMap<PathBuf, u64>,
+}
+
+impl Snapshot {
+    /// Take a snapshot of all files under `dirs` (missing directories are skipped)
+    pub fn take(dirs: &[PathBuf]) -> Self {
+        let mut files = BTreeMap::new();
+        for dir in dirs {
+            collect_files(dir, &mut files);
+        }
+        Self { files }
+    }
+
+    /// Compare against a newer snapshot
+    pub fn diff(&self, newer: &Snapshot) -> ChangeSet {
+        let mut changes = ChangeSet::default();
+
+        for (path, hash) in &newer.files {
+            match self.files.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(old) if old != hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for path in self.files.keys() {
+            if !newer.files.contains_key(path) {
+                changes.removed.push(path.clone());
+            }
+        }
+
+        changes
+    }
+}
+
+/// Files that changed between two snapshots
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet {
+    pub added: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Total number of changed files
+    pub fn len(&self) -> usize {
+        self.added.len() + self.modified.len() + self.removed.len()
+    }
+
+    /// Changed files under `dir`, with a one-letter status (`A`, `M`, `D`)
+    pub fn under(&self, dir: &Path) -> Vec<(char, &Path)> {
+        let mut out: Vec<(char, &Path)> = Vec::new();
+        out.extend(
+            self.added
+                .iter()
+                .filter(|p| p.starts_with(dir))
+                .map(|p| ('A', p.as_path())),
+        );
+        out.extend(
+            self.modified
+                .iter()
+                .filter(|p| p.starts_with(dir))
+                .map(|p| ('M', p.as_path())),
+        );
+        out.extend(
+            self.removed
+                .iter()
+                .filter(|p| p.starts_with(dir))
+                .map(|p| ('D', p.as_path())),
+        );
+        out.sort_by(|a, b| a.1.cmp(b.1));
+        out
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut BTreeMap<PathBuf, u64>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        // Skip hidden files and directories (editor swap files, .archive, ...)
+        if path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with('.'))
+            .unwrap_or(false)
+        {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if let Ok(content) = fs::read(&path) {
+            let mut hasher = DefaultHasher::new();
+            content.hash(&mut hasher);
+            files.insert(path, hasher.finish());
+        }
+    }
+}
+
+/// Which kind of watcher ended up being used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatcherKind {
+    /// Platform notifications (inotify on Linux, FSEvents/kqueue elsewhere)
+    Native,
+    /// Periodic directory scans
+    Polling,
+}
+
+impl std::fmt::Display for WatcherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatcherKind::Native => write!(f, "native"),
+            WatcherKind::Polling => write!(f, "polling"),
+        }
+    }
+}
+
+/// Start watching `dirs`, sending a unit value for every filesystem event.
+///
+/// Uses the platform watcher unless `force_poll` is set, and falls back to
+/// polling when the platform watcher can't be created or can't watch a
+/// directory (e.g. inotify watch limits, some network or container mounts).
+pub fn start_watcher(
+    dirs: &[PathBuf],
+    tx: UnboundedSender<()>,
+    force_poll: bool,
+    poll_interval: Duration,
+) -> Result<(Box<dyn Watcher + Send>, WatcherKind)> {
+    if !force_poll {
+        let native_tx = tx.clone();
+        let native = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if res.is_ok() {
+                let _ = native_tx.send(());
+            }
+        });
+
+        if let Ok(mut watcher) = native {
+            if watch_all(&mut watcher, dirs).is_ok() {
+                return Ok((Box::new(watcher), WatcherKind::Native));
+            }
+        }
+    }
+
+    let config = NotifyConfig::default().with_poll_interval(poll_interval);
+    let mut watcher = PollWatcher::new(
+        move |res: notify::Result<Event>| {
+            if res.is_ok() {
+                let _ = tx.send(());
+            }
+        },
+        config,
+    )?;
+    watch_all(&mut watcher, dirs)?;
+
+    Ok((Box::new(watcher), WatcherKind::Polling))
+}
+
+fn watch_all<W: Watcher>(watcher: &mut W, dirs: &[PathBuf]) -> notify::Result<()> {
+    for dir in dirs {
+        if dir.exists() {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_diff_detects_changes() {
+        let dir = TempDir::new().unwrap();
+        let specs = dir.path().join("specs");
+        fs::create_dir_all(&specs).unwrap();
+        fs::write(specs.join("auth.md"), "# Auth").unwrap();
+        fs::write(specs.join("api.md"), "# API").unwrap();
+
+        let before = Snapshot::take(std::slice::from_ref(&specs));
+
+        fs::write(specs.join("auth.md"), "# Auth\n\nMUST hash passwords").unwrap();
+        fs::remove_file(specs.join("api.md")).unwrap();
+        fs::write(specs.join("billing.md"), "# Billing").unwrap();
+
+        let after = Snapshot::take(std::slice::from_ref(&specs));
+        let changes = before.diff(&after);
+
+        assert_eq!(changes.modified, vec![specs.join("auth.md")]);
+        assert_eq!(changes.removed, vec![specs.join("api.md")]);
+        assert_eq!(changes.added, vec![specs.join("billing.md")]);
+        assert_eq!(changes.len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_ignores_rewrite_with_same_content() {
+        let dir = TempDir::new().unwrap();
+        let specs = dir.path().join("specs");
+        fs::create_dir_all(&specs).unwrap();
+        fs::write(specs.join("auth.md"), "# Auth").unwrap();
+
+        let before = Snapshot::take(std::slice::from_ref(&specs));
+        fs::write(specs.join("auth.md"), "# Auth").unwrap();
+        let after = Snapshot::take(&[specs]);
+
+        assert!(before.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_skips_hidden_files() {
+        let dir = TempDir::new().unwrap();
+        let specs = dir.path().join("specs");
+        fs::create_dir_all(&specs).unwrap();
+        fs::write(specs.join(".auth.md.swp"), "swap").unwrap();
+
+        assert!(Snapshot::take(&[specs]).files.is_empty());
+    }
+
+    #[test]
+    fn test_change_set_under() {
+        let changes = ChangeSet {
+            added: vec![PathBuf::from("src/new.rs")],
+            modified: vec![PathBuf::from("specs/auth.md")],
+            removed: vec![PathBuf::from("specs/old.md")],
+        };
+
+        let spec_changes = changes.under(Path::new("specs"));
+        assert_eq!(
+            spec_changes,
+            vec![
+                ('M', Path::new("specs/auth.md")),
+                ('D', Path::new("specs/old.md"))
+            ]
+        );
+    }
+}
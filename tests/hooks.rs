@@ -192,11 +192,11 @@ async fn test_hook_not_executable() {
 async fn test_started_hook_continue() {
     let dir = setup_test_project();
     let config = create_test_config(true, 30);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "started", "#!/bin/bash\nexit 0\n");
 
-    let result = run_started_hook(&state, &config, dir.path()).await.unwrap();
+    let result = run_started_hook(&mut state, &config, dir.path()).await.unwrap();
 
     assert!(result);
 }
@@ -206,11 +206,11 @@ async fn test_started_hook_continue() {
 async fn test_started_hook_abort() {
     let dir = setup_test_project();
     let config = create_test_config(true, 30);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "started", "#!/bin/bash\nexit 2\n");
 
-    let result = run_started_hook(&state, &config, dir.path()).await.unwrap();
+    let result = run_started_hook(&mut state, &config, dir.path()).await.unwrap();
 
     assert!(!result);
 }
@@ -220,11 +220,11 @@ async fn test_started_hook_abort() {
 async fn test_started_hook_timeout_continues() {
     let dir = setup_test_project();
     let config = create_test_config(true, 1);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "started", "#!/bin/bash\nsleep 10\nexit 0\n");
 
-    let result = run_started_hook(&state, &config, dir.path()).await.unwrap();
+    let result = run_started_hook(&mut state, &config, dir.path()).await.unwrap();
 
     // Should continue despite timeout
     assert!(result);
@@ -235,12 +235,12 @@ async fn test_started_hook_timeout_continues() {
 async fn test_next_iteration_hook_skip() {
     let dir = setup_test_project();
     let config = create_test_config(true, 30);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "next_iteration", "#!/bin/bash\nexit 1\n");
 
     let (should_continue, should_skip) =
-        run_next_iteration_hook(&state, &config, dir.path())
+        run_next_iteration_hook(&mut state, &config, dir.path())
             .await
             .unwrap();
 
@@ -253,12 +253,12 @@ async fn test_next_iteration_hook_skip() {
 async fn test_next_iteration_hook_abort() {
     let dir = setup_test_project();
     let config = create_test_config(true, 30);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "next_iteration", "#!/bin/bash\nexit 2\n");
 
     let (should_continue, should_skip) =
-        run_next_iteration_hook(&state, &config, dir.path())
+        run_next_iteration_hook(&mut state, &config, dir.path())
             .await
             .unwrap();
 
@@ -271,11 +271,11 @@ async fn test_next_iteration_hook_abort() {
 async fn test_finished_hook() {
     let dir = setup_test_project();
     let config = create_test_config(true, 30);
-    let state = create_test_state();
+    let mut state = create_test_state();
 
     create_hook_script(&dir, "finished", "#!/bin/bash\nexit 0\n");
 
-    let result = run_finished_hook(&state, &config, dir.path()).await;
+    let result = run_finished_hook(&mut state, &config, dir.path()).await;
 
     assert!(result.is_ok());
 }
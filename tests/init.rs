@@ -3,6 +3,10 @@
 //! Note: These tests should run serially because they change the working directory.
 //! Run with: cargo test --test init -- --test-threads=1
 
+// The std Mutex is held across `init::run().await` on purpose: it serializes
+// working-directory changes, and each test runs on a single-threaded runtime.
+#![allow(clippy::await_holding_lock)]
+
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
@@ -0,0 +1,141 @@
+//! Regression tests for the stream-json parser
+//!
+//! Replays the hand-written transcripts in `tests/fixtures/stream/` (see the
+//! README there) through `process_stream` with all output off.
+//!
+//! Run with: cargo test --test stream
+
+use std::path::{Path, PathBuf};
+
+use fresher::config::OutputConfig;
+use fresher::streaming::{
+    parse_event, process_stream, ContentBlock, IterationOutcome, ProcessResult, StreamEvent,
+    StreamHandler, UserContentBlock,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/stream")
+}
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture_dir().join(name)).unwrap()
+}
+
+/// Replay a fixture with all output off
+async fn replay(name: &str) -> ProcessResult {
+    let handler = StreamHandler::from_output(&OutputConfig::default(), -1);
+    process_stream(fixture(name).as_bytes(), &handler).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_every_fixture_parses() {
+    let mut names: Vec<String> = std::fs::read_dir(fixture_dir())
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".jsonl"))
+        .collect();
+    names.sort();
+    assert!(names.len() >= 6, "fixtures missing: {:?}", names);
+
+    for name in names {
+        let result = replay(&name).await;
+        assert_eq!(result.parse_errors, 0, "{} has unparsed lines", name);
+        assert!(result.subtype.is_some(), "{} has no result event", name);
+        assert!(result.claude_code_version.is_some(), "{} has no init event", name);
+        if name != "unknown_types.jsonl" {
+            assert!(result.unknown_types.is_empty(), "{}: {:?}", name, result.unknown_types);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_build_iteration() {
+    let result = replay("build_iteration.jsonl").await;
+    assert_eq!(result.outcome(), IterationOutcome::Success);
+    assert_eq!(result.claude_code_version.as_deref(), Some("0.0.0-synthetic"));
+    assert_eq!(result.num_turns, Some(5));
+    // Current releases only report total_cost_usd
+    assert_eq!(result.cost_usd, Some(0.1834));
+    assert_eq!(result.usage.output_tokens, 2210);
+    assert_eq!(result.tool_stats.calls["Bash"], 2);
+    assert!(result.tool_stats.files_edited.contains("/workspace/src/cli.rs"));
+}
+
+#[tokio::test]
+async fn test_thinking_and_array_results() {
+    let text = fixture("thinking_and_array_results.jsonl");
+    let events: Vec<StreamEvent> = text.lines().map(|line| parse_event(line).unwrap()).collect();
+
+    let thinking = events.iter().find_map(|event| match event {
+        StreamEvent::Assistant(e) => e.message.as_ref()?.content.iter().find_map(|block| match block {
+            ContentBlock::Thinking { thinking } => Some(thinking.clone()),
+            _ => None,
+        }),
+        _ => None,
+    });
+    assert!(thinking.unwrap().contains("parser.rs"));
+
+    let results: Vec<(String, Option<bool>)> = events
+        .iter()
+        .filter_map(|event| match event {
+            StreamEvent::User(e) => e.message.as_ref(),
+            _ => None,
+        })
+        .flat_map(|msg| msg.content.iter())
+        .filter_map(|block| match block {
+            UserContentBlock::ToolResult { content, is_error, .. } => Some((content.clone(), *is_error)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(results[0].0, "[image]");
+    assert_eq!(results[1].0, "Found 1 file\n/workspace/src/parser.rs");
+    assert_eq!(results[2], ("error[E0308]: mismatched types".to_string(), Some(true)));
+
+    let result = replay("thinking_and_array_results.jsonl").await;
+    assert_eq!(result.tool_stats.total_calls(), 3);
+}
+
+#[tokio::test]
+async fn test_partial_messages() {
+    let handler = StreamHandler::from_output(&OutputConfig::default(), -1).partial(true);
+    let result = process_stream(fixture("partial_messages.jsonl").as_bytes(), &handler)
+        .await
+        .unwrap();
+    assert!(result.unknown_types.is_empty());
+    // The tool call is counted once, from the complete assistant message
+    assert_eq!(result.tool_stats.calls["Read"], 1);
+    assert!(result.tool_stats.files_read.contains("/workspace/IMPLEMENTATION_PLAN.md"));
+}
+
+#[tokio::test]
+async fn test_subagents_and_todos() {
+    let result = replay("subagents_and_todos.jsonl").await;
+    assert_eq!(result.subagents.len(), 1);
+    let run = &result.subagents[0];
+    assert_eq!(run.description, "Explore parser module");
+    assert_eq!(run.total_calls(), 2);
+    assert!(run.duration_ms.is_some());
+
+    assert_eq!(result.todos.len(), 2);
+    assert_eq!(fresher::todos::progress(&result.todos), "1/2");
+}
+
+#[tokio::test]
+async fn test_max_turns() {
+    let result = replay("max_turns.jsonl").await;
+    assert_eq!(result.outcome(), IterationOutcome::MaxTurns);
+    assert_eq!(result.cost_usd, Some(0.1834));
+    assert!(result.result_text.is_none());
+}
+
+#[tokio::test]
+async fn test_unknown_types_are_reported_once() {
+    let result = replay("unknown_types.jsonl").await;
+    assert_eq!(
+        result.unknown_types,
+        vec!["content block server_tool_use", "event rate_limit"]
+    );
+    // Known blocks next to unknown ones still count
+    assert_eq!(result.tool_stats.calls["Read"], 1);
+    assert_eq!(result.outcome(), IterationOutcome::Success);
+}
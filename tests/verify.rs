@@ -3,11 +3,17 @@
 //! Note: These tests must run serially because they change the working directory.
 //! Run with: cargo test --test verify -- --test-threads=1
 
+// The std Mutex is held across `verify::run().await` on purpose: it serializes
+// working-directory changes, and each test runs on a single-threaded runtime.
+#![allow(clippy::await_holding_lock)]
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tempfile::TempDir;
 
+use fresher::config::VerifyConfig;
+
 // Mutex to serialize tests that change working directory
 static TEST_MUTEX: Mutex<()> = Mutex::new(());
 
@@ -72,10 +78,18 @@ async fn test_verify_missing_plan_file() {
     let _lock = acquire_lock();
     let (_dir, original_dir) = setup_test_project();
 
-    let result = fresher::commands::verify::run(false, "nonexistent.md".to_string()).await;
+    let result = fresher::commands::verify::run(false, "nonexistent.md".to_string(), false, Default::default(), false, None).await;
+    let thresholds = VerifyConfig {
+        min_coverage: Some(80.0),
+        ..Default::default()
+    };
+    let with_thresholds =
+        fresher::commands::verify::run(false, "nonexistent.md".to_string(), false, thresholds, false, None).await;
     teardown_test_project(original_dir);
 
-    assert!(result.is_ok());
+    assert!(result.unwrap());
+    // Nothing to hold to the thresholds counts as breaking them
+    assert!(!with_thresholds.unwrap());
 }
 
 /// Test verify command with valid plan file
@@ -103,7 +117,7 @@ The system MUST implement feature A.
     fs::write(dir.path().join("specs/feature.md"), spec_content).unwrap();
 
     let result =
-        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string()).await;
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), false, None).await;
     teardown_test_project(original_dir);
 
     assert!(result.is_ok());
@@ -126,7 +140,7 @@ async fn test_verify_json_output() {
     fs::write(dir.path().join("specs/test.md"), "### Test Section\n").unwrap();
 
     let result =
-        fresher::commands::verify::run(true, "IMPLEMENTATION_PLAN.md".to_string()).await;
+        fresher::commands::verify::run(true, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), false, None).await;
     teardown_test_project(original_dir);
 
     assert!(result.is_ok());
@@ -389,8 +403,228 @@ async fn test_verify_json_missing_plan() {
     let _lock = acquire_lock();
     let (_dir, original_dir) = setup_test_project();
 
-    let result = fresher::commands::verify::run(true, "missing.md".to_string()).await;
+    let result = fresher::commands::verify::run(true, "missing.md".to_string(), false, Default::default(), false, None).await;
     teardown_test_project(original_dir);
 
     assert!(result.is_ok());
 }
+
+/// Test --strict fails verification when specs changed after planning
+#[tokio::test]
+async fn test_verify_strict_spec_drift() {
+    let _lock = acquire_lock();
+    let (dir, original_dir) = setup_test_project();
+
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [ ] Implement feature A (refs: specs/feature.md)\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("specs/feature.md"), "The system MUST do A.\n").unwrap();
+    fresher::spec_drift::record(dir.path(), &dir.path().join("specs")).unwrap();
+
+    let unchanged =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), true, Default::default(), false, None).await;
+
+    fs::write(dir.path().join("specs/feature.md"), "The system MUST do B.\n").unwrap();
+    let lenient =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), false, None).await;
+    let strict =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), true, Default::default(), false, None).await;
+    teardown_test_project(original_dir);
+
+    assert!(unchanged.is_ok());
+    assert!(lenient.is_ok());
+    assert!(strict.is_err());
+}
+
+/// Test thresholds from flags and `[verify]` config fail verification
+#[tokio::test]
+async fn test_verify_thresholds() {
+    let _lock = acquire_lock();
+    let (dir, original_dir) = setup_test_project();
+
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "## Priority 1: Core\n\n- [ ] Task A (refs: specs/feature.md)\n- [ ] Task B (refs: specs/gone.md)\n- [ ] Task C\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("specs/feature.md"), "### One\n### Two\n").unwrap();
+    let run = |thresholds: VerifyConfig| {
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, thresholds, false, None)
+    };
+
+    let none = run(VerifyConfig::default()).await;
+    let orphans = run(VerifyConfig {
+        max_orphan_tasks: Some(1),
+        ..Default::default()
+    })
+    .await;
+    let too_many_orphans = run(VerifyConfig {
+        max_orphan_tasks: Some(0),
+        ..Default::default()
+    })
+    .await;
+    let dangling = run(VerifyConfig {
+        no_dangling_refs: true,
+        ..Default::default()
+    })
+    .await;
+    let priority = run(VerifyConfig {
+        require_priority: true,
+        ..Default::default()
+    })
+    .await;
+
+    // Thresholds in [verify] apply without flags
+    let config = fs::read_to_string(dir.path().join(".fresher/config.toml")).unwrap();
+    fs::write(
+        dir.path().join(".fresher/config.toml"),
+        format!("{}\n[verify]\nmin_coverage = 80.0\n", config),
+    )
+    .unwrap();
+    let coverage = run(VerifyConfig::default()).await;
+
+    // A config that doesn't parse is an error, not a run without thresholds
+    fs::write(
+        dir.path().join(".fresher/config.toml"),
+        format!("{}\n[verify]\nmin_coverage = \"high\"\n", config),
+    )
+    .unwrap();
+    let bad_config = run(VerifyConfig::default()).await;
+    teardown_test_project(original_dir);
+
+    assert!(none.unwrap());
+    assert!(orphans.unwrap());
+    assert!(!too_many_orphans.unwrap());
+    assert!(!dangling.unwrap());
+    assert!(priority.unwrap());
+    assert!(!coverage.unwrap());
+    assert!(bad_config.is_err());
+}
+
+/// Test thresholds apply to hierarchical plans
+#[tokio::test]
+async fn test_verify_thresholds_hierarchical() {
+    let _lock = acquire_lock();
+    let (dir, original_dir) = setup_test_project();
+
+    fs::create_dir_all(dir.path().join("impl")).unwrap();
+    fs::write(dir.path().join("impl/README.md"), "# Plan\n\n**Active:** [auth]\n").unwrap();
+    fs::write(
+        dir.path().join("impl/auth.md"),
+        "# Auth\n\n**Spec:** [auth](../specs/auth.md)\n\n## Tasks\n\n### Priority 1: Core\n\n- [ ] Login\n- [ ] Logout (refs: specs/sessions.md)\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("impl/cli.md"), "# CLI\n\n### Priority 2\n\n- [ ] Parse args\n").unwrap();
+    fs::write(dir.path().join("specs/auth.md"), "### Login\n").unwrap();
+
+    let run = |thresholds: VerifyConfig| {
+        fresher::commands::verify::run(true, "IMPLEMENTATION_PLAN.md".to_string(), false, thresholds, false, None)
+    };
+    // Login inherits the feature's spec link; only Parse args is an orphan
+    let orphans = run(VerifyConfig {
+        max_orphan_tasks: Some(1),
+        ..Default::default()
+    })
+    .await;
+    let dangling = run(VerifyConfig {
+        no_dangling_refs: true,
+        ..Default::default()
+    })
+    .await;
+    // Feature files use `### Priority N` headings
+    let priority = run(VerifyConfig {
+        require_priority: true,
+        ..Default::default()
+    })
+    .await;
+    teardown_test_project(original_dir);
+
+    assert!(orphans.unwrap());
+    assert!(!dangling.unwrap());
+    assert!(priority.unwrap());
+}
+
+/// Test tasks citing requirement IDs produce a traceability matrix
+#[tokio::test]
+async fn test_verify_requirement_traceability() {
+    let _lock = acquire_lock();
+    let (dir, original_dir) = setup_test_project();
+
+    fs::write(
+        dir.path().join("specs/auth.md"),
+        "# Auth\n\n[REQ-AUTH-1] Users log in with email\n[REQ-AUTH-2] Sessions MUST expire\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [ ] Login form (refs: REQ-AUTH-1)\n- [ ] Remember me (refs: REQ-AUTH-7)\n",
+    )
+    .unwrap();
+
+    let report = fresher::verify::generate_report(Path::new("IMPLEMENTATION_PLAN.md"), Path::new("specs")).unwrap();
+    let dangling = VerifyConfig {
+        no_dangling_refs: true,
+        ..Default::default()
+    };
+    let matrix =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), true, None).await;
+    let strict_refs =
+        fresher::commands::verify::run(true, "IMPLEMENTATION_PLAN.md".to_string(), false, dangling, false, None).await;
+    teardown_test_project(original_dir);
+
+    let auth = report.coverage.iter().find(|e| e.spec_name == "auth").unwrap();
+    assert_eq!(auth.requirement_count, 2);
+    assert_eq!(auth.traced_requirements, Some(1));
+    assert_eq!(auth.coverage_percent, 50.0);
+    assert_eq!(report.requirements[0].tasks, vec!["Login form"]);
+    assert!(report.requirements[1].tasks.is_empty());
+    assert_eq!(report.unknown_requirement_refs[0].requirement, "REQ-AUTH-7");
+
+    assert!(matrix.unwrap());
+    assert!(!strict_refs.unwrap());
+}
+
+/// Test suggested refs are written back into the plan with --apply
+#[tokio::test]
+async fn test_verify_suggest_refs_apply() {
+    let _lock = acquire_lock();
+    let (dir, original_dir) = setup_test_project();
+
+    fs::write(
+        dir.path().join("specs/auth.md"),
+        "# Auth\n\nUsers MUST log in with an email and password.\nSessions MUST expire after 30 days.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "## Priority 1\n- [ ] Email and password login form\n- [ ] Set up CI\n",
+    )
+    .unwrap();
+    let suggest = |apply| {
+        Some(fresher::commands::verify::SuggestOptions {
+            min_confidence: fresher::suggest::DEFAULT_MIN_CONFIDENCE,
+            apply,
+        })
+    };
+
+    let dry_run =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), false, suggest(false)).await;
+    let unchanged = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    let applied =
+        fresher::commands::verify::run(false, "IMPLEMENTATION_PLAN.md".to_string(), false, Default::default(), false, suggest(true)).await;
+    let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    let report = fresher::verify::generate_report(Path::new("IMPLEMENTATION_PLAN.md"), Path::new("specs")).unwrap();
+    teardown_test_project(original_dir);
+
+    assert!(dry_run.unwrap());
+    assert!(!unchanged.contains("refs:"));
+    assert!(applied.unwrap());
+    assert!(plan.contains("- [ ] Email and password login form (refs: auth#"));
+    assert!(plan.contains("- [ ] Set up CI\n"));
+
+    // The written ref traces the requirement
+    assert_eq!(report.requirements[0].tasks, vec!["Email and password login form"]);
+    assert!(report.unknown_requirement_refs.is_empty());
+}